@@ -0,0 +1,224 @@
+/* stem-splitter-core C API (src/capi.rs). Regenerate with: cbindgen --config cbindgen.toml --output capi/include/stem_splitter.h */
+
+#ifndef STEM_SPLITTER_H
+#define STEM_SPLITTER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Kind of an [`ss_progress`] event.
+ */
+typedef enum ss_progress_kind {
+  /*
+   A pipeline stage started; `name` is the stage.
+   */
+  SS_PROGRESS_STAGE = 0,
+  SS_PROGRESS_CHUNKS = 1,
+  /*
+   Output is being written; `name` is the stem or chunk.
+   */
+  SS_PROGRESS_WRITING = 2,
+  SS_PROGRESS_FINISHED = 3,
+} ss_progress_kind;
+
+/*
+ Result of a fallible C API call.
+ */
+typedef enum ss_status {
+  SS_OK = 0,
+  /*
+   NULL pointer, invalid UTF-8, malformed options JSON or an index out
+   of range.
+   */
+  SS_ERR_INVALID_ARGUMENT = 1,
+  /*
+   Reading or writing a file failed.
+   */
+  SS_ERR_IO = 2,
+  /*
+   The model could not be resolved, downloaded or verified.
+   */
+  SS_ERR_MODEL = 3,
+  /*
+   An output file could not be written (or already exists).
+   */
+  SS_ERR_OUTPUT = 4,
+  /*
+   Decoding or separation failed.
+   */
+  SS_ERR_SEPARATION = 5,
+  /*
+   The library panicked; this is a bug.
+   */
+  SS_ERR_PANIC = 6,
+} ss_status;
+
+/*
+ Separation settings: options and an optional progress callback.
+ */
+typedef struct ss_engine ss_engine;
+
+/*
+ Separated stems, each interleaved stereo `f32`.
+ */
+typedef struct ss_stems ss_stems;
+
+/*
+ A progress event. `name` is NULL for kinds without one and, like the
+ event itself, only valid during the callback.
+ */
+typedef struct ss_progress {
+  ss_progress_kind kind;
+  const char *name;
+  size_t done;
+  size_t total;
+  float percent;
+} ss_progress;
+
+/*
+ Progress callback: `(event, user_data)`. Called on the thread running
+ the separation.
+ */
+typedef void (*ss_progress_fn)(const ss_progress*, void*);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Version of the library, e.g. `"1.1.0"`. Static; do not free.
+ */
+const char *ss_version(void);
+
+/*
+ Message of the last error on this thread, or NULL. Valid until the next
+ failing call on this thread; do not free.
+ */
+const char *ss_last_error_message(void);
+
+/*
+ Create an engine. `options_json` is a `SplitOptions` object (fields left
+ out keep their defaults) or NULL for the defaults.
+
+ # Safety
+ `options_json` is NULL or a NUL-terminated string; `out` is writable.
+ */
+ss_status ss_engine_new(const char *options_json, ss_engine **out);
+
+/*
+ # Safety
+ `engine` is NULL or was returned by [`ss_engine_new`] and not freed.
+ */
+void ss_engine_free(ss_engine *engine);
+
+/*
+ Receive progress of separations run with `engine`, passing `user_data`
+ back to `callback`. A NULL callback removes it.
+
+ # Safety
+ `engine` is a live engine; `callback` must be safe to call with
+ `user_data` from the thread calling `ss_separate_*`.
+ */
+ss_status ss_engine_set_progress_callback(ss_engine *engine,
+                                          ss_progress_fn callback,
+                                          void *user_data);
+
+/*
+ Replace model inference with the test engine (see [`TestEngine`]): no
+ model is loaded and each of the four stems is a quarter of the input.
+ `window` and `hop` are in samples; 0 keeps the test engine's defaults.
+ For testing applications without a model.
+
+ # Safety
+ `engine` is a live engine.
+ */
+ss_status ss_engine_use_test_engine(ss_engine *engine, size_t window, size_t hop);
+
+/*
+ Separate an audio file.
+
+ # Safety
+ `engine` is a live engine, `path` a NUL-terminated string and `out`
+ writable.
+ */
+ss_status ss_separate_file(const ss_engine *engine, const char *path, ss_stems **out);
+
+/*
+ Separate `frames` frames of interleaved audio with `channels` channels.
+ The samples are copied; the buffer can be reused once this returns.
+
+ # Safety
+ `engine` is a live engine, `samples` points to `frames * channels`
+ floats and `out` is writable.
+ */
+ss_status ss_separate_buffer(const ss_engine *engine,
+                             const float *samples,
+                             size_t frames,
+                             uint16_t channels,
+                             uint32_t sample_rate,
+                             ss_stems **out);
+
+/*
+ # Safety
+ `stems` is NULL or was returned by `ss_separate_*` and not freed.
+ */
+void ss_stems_free(ss_stems *stems);
+
+/*
+ Number of stems; valid indices are `0..count`.
+
+ # Safety
+ `stems` is NULL or a live stems object.
+ */
+size_t ss_stems_count(const ss_stems *stems);
+
+/*
+ Name of stem `index` (`"vocals"`, `"drums"`, `"bass"`, `"other"`), or
+ NULL if out of range. Static; do not free.
+
+ # Safety
+ `stems` is NULL or a live stems object.
+ */
+const char *ss_stems_name(const ss_stems *stems, size_t index);
+
+/*
+ Frames per stem.
+
+ # Safety
+ `stems` is NULL or a live stems object.
+ */
+size_t ss_stems_frames(const ss_stems *stems);
+
+/*
+ # Safety
+ `stems` is NULL or a live stems object.
+ */
+uint32_t ss_stems_sample_rate(const ss_stems *stems);
+
+/*
+ Interleaved stereo samples of stem `index` (`2 * frames` floats), owned
+ by `stems`; NULL if out of range.
+
+ # Safety
+ `stems` is NULL or a live stems object. The pointer is valid until
+ `stems` is freed.
+ */
+const float *ss_stems_data(const ss_stems *stems, size_t index);
+
+/*
+ Write stem `index` as a WAV file.
+
+ # Safety
+ `stems` is a live stems object and `path` a NUL-terminated string.
+ */
+ss_status ss_stems_save(const ss_stems *stems, size_t index, const char *path);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* STEM_SPLITTER_H */
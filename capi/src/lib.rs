@@ -0,0 +1,5 @@
+//! Shared library exporting the `ss_*` C API of `stem-splitter-core`,
+//! declared in `include/stem_splitter.h`. See
+//! [`stem_splitter_core::capi`] for the conventions.
+
+pub use stem_splitter_core::capi::*;
@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::tempdir;
+
+/// Directory holding the cdylib built alongside this test binary.
+fn lib_dir() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program_separates_through_the_c_api() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = tempdir().unwrap();
+    let out_dir = tmp.path().join("out");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let lib_dir = lib_dir();
+    assert!(
+        lib_dir.join("libstem_splitter_capi.so").exists()
+            || lib_dir.join("libstem_splitter_capi.dylib").exists(),
+        "cdylib not found in {}",
+        lib_dir.display()
+    );
+
+    let exe = tmp.path().join("capi_test");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg(root.join("tests/capi_test.c"))
+        .arg("-o")
+        .arg(&exe)
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lstem_splitter_capi")
+        .arg("-lm")
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling capi_test.c failed");
+
+    // cargo puts target/<profile> on the library path, whose copy of the
+    // cdylib is only refreshed by `cargo build`; load the one next to us
+    let output = Command::new(&exe)
+        .arg(&out_dir)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "capi_test failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("capi_test: ok"));
+}
+
+#[test]
+fn header_declares_every_exported_function() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let source = std::fs::read_to_string(root.join("../src/capi.rs")).unwrap();
+    let header = std::fs::read_to_string(root.join("include/stem_splitter.h")).unwrap();
+
+    let exported: Vec<&str> = source
+        .split("#[no_mangle]")
+        .skip(1)
+        .filter_map(|item| {
+            let name = item.split("fn ").nth(1)?;
+            name.split('(').next()
+        })
+        .collect();
+    assert!(exported.len() > 10);
+    for name in exported {
+        assert!(
+            header.contains(&format!("{name}(")),
+            "{name} missing from the header"
+        );
+    }
+}
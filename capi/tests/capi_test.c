@@ -0,0 +1,135 @@
+/*
+ * Exercises the C API with the test engine, whose stems are each a quarter
+ * of the input. Run by capi/tests/capi.rs:
+ *
+ *   capi_test <output dir>
+ */
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "stem_splitter.h"
+
+#define FRAMES 20000
+#define CHECK(cond)                                                        \
+  do {                                                                     \
+    if (!(cond)) {                                                         \
+      const char *err = ss_last_error_message();                           \
+      fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n",        \
+              __FILE__, __LINE__, #cond, err ? err : "none");              \
+      return 1;                                                            \
+    }                                                                      \
+  } while (0)
+
+typedef struct {
+  int stages;
+  int finished;
+  float last_percent;
+} progress_state;
+
+static void on_progress(const ss_progress *event, void *user_data) {
+  progress_state *state = (progress_state *)user_data;
+  switch (event->kind) {
+  case SS_PROGRESS_STAGE:
+    state->stages++;
+    break;
+  case SS_PROGRESS_WRITING:
+    state->last_percent = event->percent;
+    break;
+  case SS_PROGRESS_FINISHED:
+    state->finished++;
+    break;
+  default:
+    break;
+  }
+}
+
+static int check_quarters(const ss_stems *stems, const float *input, size_t frames) {
+  CHECK(ss_stems_count(stems) == 4);
+  CHECK(ss_stems_frames(stems) == frames);
+  CHECK(ss_stems_sample_rate(stems) == 44100);
+  CHECK(strcmp(ss_stems_name(stems, 0), "vocals") == 0);
+  CHECK(strcmp(ss_stems_name(stems, 3), "other") == 0);
+  CHECK(ss_stems_name(stems, 4) == NULL);
+  CHECK(ss_stems_data(stems, 4) == NULL);
+  for (size_t s = 0; s < 4; s++) {
+    const float *data = ss_stems_data(stems, s);
+    CHECK(data != NULL);
+    for (size_t i = 0; i < 2 * frames; i++) {
+      CHECK(fabsf(data[i] - 0.25f * input[i]) < 1e-4f);
+    }
+  }
+  return 0;
+}
+
+int main(int argc, char **argv) {
+  if (argc != 2) {
+    fprintf(stderr, "usage: %s <output dir>\n", argv[0]);
+    return 2;
+  }
+  CHECK(ss_version() != NULL && strlen(ss_version()) > 0);
+
+  static float input[2 * FRAMES];
+  for (size_t i = 0; i < FRAMES; i++) {
+    float t = (float)i / 44100.0f;
+    input[2 * i] = 0.2f * sinf(2.0f * 3.14159265f * 440.0f * t);
+    input[2 * i + 1] = 0.2f * sinf(2.0f * 3.14159265f * 660.0f * t);
+  }
+
+  /* Errors come back as codes with a message */
+  ss_engine *engine = NULL;
+  CHECK(ss_engine_new("{\"chunk_seconds\": \"soon\"}", &engine) == SS_ERR_INVALID_ARGUMENT);
+  CHECK(engine == NULL);
+  CHECK(strstr(ss_last_error_message(), "invalid options JSON") != NULL);
+  CHECK(ss_engine_new(NULL, NULL) == SS_ERR_INVALID_ARGUMENT);
+
+  char options[4096];
+  snprintf(options, sizeof options, "{\"output_dir\": \"%s\", \"chunk_seconds\": null}",
+           argv[1]);
+  CHECK(ss_engine_new(options, &engine) == SS_OK);
+  CHECK(ss_engine_use_test_engine(engine, 4096, 2048) == SS_OK);
+
+  progress_state state = {0, 0, 0.0f};
+  CHECK(ss_engine_set_progress_callback(engine, on_progress, &state) == SS_OK);
+
+  /* Buffer input */
+  ss_stems *stems = NULL;
+  CHECK(ss_separate_buffer(engine, input, FRAMES, 2, 44100, &stems) == SS_OK);
+  if (check_quarters(stems, input, FRAMES) != 0) {
+    return 1;
+  }
+  CHECK(state.stages >= 3);
+  CHECK(state.finished == 1);
+
+  /* File input, via a stem saved by the API */
+  static float vocals[2 * FRAMES];
+  memcpy(vocals, ss_stems_data(stems, 0), sizeof vocals);
+  char path[4096];
+  snprintf(path, sizeof path, "%s/vocals.wav", argv[1]);
+  CHECK(ss_stems_save(stems, 0, path) == SS_OK);
+  CHECK(ss_stems_save(stems, 9, path) == SS_ERR_INVALID_ARGUMENT);
+  ss_stems_free(stems);
+
+  stems = NULL;
+  CHECK(ss_separate_file(engine, path, &stems) == SS_OK);
+  if (check_quarters(stems, vocals, FRAMES) != 0) {
+    return 1;
+  }
+  CHECK(state.finished == 2);
+  ss_stems_free(stems);
+
+  /* Removing the callback stops progress */
+  CHECK(ss_engine_set_progress_callback(engine, NULL, NULL) == SS_OK);
+  snprintf(path, sizeof path, "%s/missing.wav", argv[1]);
+  stems = NULL;
+  CHECK(ss_separate_file(engine, path, &stems) == SS_ERR_IO);
+  CHECK(stems == NULL);
+  CHECK(ss_last_error_message() != NULL && strlen(ss_last_error_message()) > 0);
+  CHECK(state.finished == 2);
+
+  ss_engine_free(engine);
+  ss_engine_free(NULL);
+  ss_stems_free(NULL);
+  printf("capi_test: ok\n");
+  return 0;
+}
@@ -38,6 +38,14 @@ fn main() -> anyhow::Result<()> {
         SplitProgress::Writing { ref stem, done, total, percent } => {
             eprintln!("Writing {}: {}/{} ({:.0}%)", stem, done, total, percent);
         }
+        SplitProgress::Timing(t) => {
+            eprintln!(
+                "Pipeline: {} windows in {:.1}s ({:.2}x faster than serial)",
+                t.windows,
+                t.wall_secs,
+                t.speedup()
+            );
+        }
         SplitProgress::Finished => {
             eprintln!("Finished.");
         }
@@ -49,6 +57,7 @@ fn main() -> anyhow::Result<()> {
         manifest_url_override: None,
         model_path: None,
         chunk_seconds: Some(300), // 5 minutes per chunk for long audio
+        ..Default::default()
     };
 
     // Use remove_vocals instead of split_file
@@ -43,6 +43,14 @@ fn main() -> anyhow::Result<()> {
         } => {
             eprintln!("Writing {}: {}/{} ({:.0}%)", stem, done, total, percent);
         }
+        SplitProgress::Timing(t) => {
+            eprintln!(
+                "Pipeline: {} windows in {:.1}s ({:.2}x faster than serial)",
+                t.windows,
+                t.wall_secs,
+                t.speedup()
+            );
+        }
         SplitProgress::Finished => {
             eprintln!("Split finished.");
         }
@@ -54,6 +62,7 @@ fn main() -> anyhow::Result<()> {
         manifest_url_override: None,
         model_path: None,
         chunk_seconds: Some(300), // 5 minutes per chunk for long audio
+        ..Default::default()
     };
 
     let res = stem_splitter_core::split_file(&input, opts)?;
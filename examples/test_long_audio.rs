@@ -24,6 +24,14 @@ fn main() -> anyhow::Result<()> {
         SplitProgress::Writing { ref stem, done, total, percent } => {
             eprintln!("  {}: {}/{} ({:.1}%)", stem, done, total, percent);
         }
+        SplitProgress::Timing(t) => {
+            eprintln!(
+                "Pipeline: {} windows in {:.1}s ({:.2}x faster than serial)",
+                t.windows,
+                t.wall_secs,
+                t.speedup()
+            );
+        }
         SplitProgress::Finished => {
             eprintln!("Finished!");
         }
@@ -35,6 +43,7 @@ fn main() -> anyhow::Result<()> {
         manifest_url_override: None,
         model_path: Some(model_path.to_string()),
         chunk_seconds: Some(60), // 3 minutes per chunk for testing
+        ..Default::default()
     };
 
     eprintln!("Processing: {}", input);
@@ -0,0 +1,45 @@
+//! Local HTTP job server; see `stem_splitter_core::server` for the API.
+//!
+//! ```text
+//! stem-server [--addr 127.0.0.1:8765] [--data-dir DIR] [--model NAME | --model-path PATH]
+//!             [--model-manifest FILE] [--model-cache-dir DIR] [--workers N] [--queue N]
+//! ```
+
+use stem_splitter_core::server::{Server, ServerConfig};
+
+fn main() -> anyhow::Result<()> {
+    let mut config = ServerConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--addr" => config.addr = value()?.parse()?,
+            "--data-dir" => config.data_dir = value()?.into(),
+            "--model" => config.options.model_name = value()?,
+            "--model-path" => config.options.model_path = Some(value()?),
+            "--model-manifest" => config.options.model_manifest_path = Some(value()?),
+            "--model-cache-dir" => config.options.model_cache_dir = Some(value()?),
+            "--workers" => config.workers = value()?.parse()?,
+            "--queue" => config.queue_capacity = value()?.parse()?,
+            "-h" | "--help" => {
+                eprintln!(
+                    "usage: stem-server [--addr ADDR] [--data-dir DIR] \
+                     [--model NAME | --model-path PATH] [--model-manifest FILE] \
+                     [--model-cache-dir DIR] [--workers N] [--queue N]"
+                );
+                return Ok(());
+            }
+            other => anyhow::bail!("unknown argument: {other}"),
+        }
+    }
+
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let server = Server::bind(config).await?;
+        eprintln!("Listening on http://{}", server.local_addr()?);
+        server.run().await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,469 @@
+//! C API for embedding the separator in native applications.
+//!
+//! Enabled by the `capi` feature. The `stem-splitter-capi` crate (`capi/`)
+//! builds them into a shared library; their C declarations are in
+//! `capi/include/stem_splitter.h`. Conventions:
+//!
+//! - Functions that can fail return an [`ss_status`]; on failure
+//!   [`ss_last_error_message`] describes the error of the calling thread.
+//! - Objects created by `ss_*_new` / `ss_separate_*` are released with the
+//!   matching `ss_*_free`. Passing NULL to a free function is a no-op.
+//! - Strings are NUL-terminated UTF-8.
+//! - Panics never cross the boundary; they are reported as `SS_ERR_PANIC`.
+
+#![allow(non_camel_case_types)]
+
+use crate::{
+    core::{
+        splitter::{SeparatedStems, Separator, Stem},
+        test_engine::TestEngine,
+    },
+    error::StemError,
+    io::progress::{with_split_progress_sink, SplitProgress},
+    types::{AudioData, SplitOptions},
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+/// Result of a fallible C API call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ss_status {
+    SS_OK = 0,
+    /// NULL pointer, invalid UTF-8, malformed options JSON or an index out
+    /// of range.
+    SS_ERR_INVALID_ARGUMENT = 1,
+    /// Reading or writing a file failed.
+    SS_ERR_IO = 2,
+    /// The model could not be resolved, downloaded or verified.
+    SS_ERR_MODEL = 3,
+    /// An output file could not be written (or already exists).
+    SS_ERR_OUTPUT = 4,
+    /// Decoding or separation failed.
+    SS_ERR_SEPARATION = 5,
+    /// The library panicked; this is a bug.
+    SS_ERR_PANIC = 6,
+}
+
+/// Kind of an [`ss_progress`] event.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ss_progress_kind {
+    /// A pipeline stage started; `name` is the stage.
+    SS_PROGRESS_STAGE = 0,
+    SS_PROGRESS_CHUNKS = 1,
+    /// Output is being written; `name` is the stem or chunk.
+    SS_PROGRESS_WRITING = 2,
+    SS_PROGRESS_FINISHED = 3,
+}
+
+/// A progress event. `name` is NULL for kinds without one and, like the
+/// event itself, only valid during the callback.
+#[repr(C)]
+pub struct ss_progress {
+    pub kind: ss_progress_kind,
+    pub name: *const c_char,
+    pub done: usize,
+    pub total: usize,
+    pub percent: f32,
+}
+
+/// Progress callback: `(event, user_data)`. Called on the thread running
+/// the separation.
+pub type ss_progress_fn = Option<unsafe extern "C" fn(*const ss_progress, *mut c_void)>;
+
+/// Separation settings: options and an optional progress callback.
+pub struct ss_engine {
+    opts: SplitOptions,
+    progress: ss_progress_fn,
+    user_data: *mut c_void,
+}
+
+/// Separated stems, each interleaved stereo `f32`.
+pub struct ss_stems {
+    stems: SeparatedStems,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn fail(status: ss_status, message: &str) -> ss_status {
+    set_last_error(message);
+    status
+}
+
+fn invalid(message: &str) -> ss_status {
+    fail(ss_status::SS_ERR_INVALID_ARGUMENT, message)
+}
+
+fn status_of(err: &StemError) -> ss_status {
+    match err {
+        StemError::Registry(_)
+        | StemError::Checksum { .. }
+        | StemError::CacheDirUnavailable
+        | StemError::Manifest(_)
+        | StemError::Signature(_) => ss_status::SS_ERR_MODEL,
+        StemError::Output(_) | StemError::OutputExists { .. } => ss_status::SS_ERR_OUTPUT,
+        StemError::Anyhow(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+            ss_status::SS_ERR_IO
+        }
+        StemError::Anyhow(_) | StemError::Cancelled | StemError::BatchRejected(_) => {
+            ss_status::SS_ERR_SEPARATION
+        }
+    }
+}
+
+/// Run `f`, turning errors and panics into a status and the thread's last
+/// error message.
+fn guard(f: impl FnOnce() -> Result<(), ss_status>) -> ss_status {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => ss_status::SS_OK,
+        Ok(Err(status)) => status,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            fail(ss_status::SS_ERR_PANIC, &format!("panic: {message}"))
+        }
+    }
+}
+
+fn check<T>(result: crate::error::Result<T>) -> Result<T, ss_status> {
+    result.map_err(|e| fail(status_of(&e), &e.to_string()))
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, what: &str) -> Result<&'a str, ss_status> {
+    if s.is_null() {
+        return Err(invalid(&format!("{what} is NULL")));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| invalid(&format!("{what} is not UTF-8")))
+}
+
+unsafe fn ref_arg<'a, T>(p: *const T, what: &str) -> Result<&'a T, ss_status> {
+    p.as_ref()
+        .ok_or_else(|| invalid(&format!("{what} is NULL")))
+}
+
+impl ss_engine {
+    fn separate(
+        &self,
+        f: impl FnOnce(SplitOptions) -> crate::error::Result<SeparatedStems>,
+    ) -> crate::error::Result<SeparatedStems> {
+        let opts = self.opts.clone();
+        let Some(callback) = self.progress else {
+            return f(opts);
+        };
+        let user_data = self.user_data;
+        with_split_progress_sink(
+            move |p| {
+                let (kind, name, done, total, percent) = match &p {
+                    SplitProgress::Stage(stage) => {
+                        (ss_progress_kind::SS_PROGRESS_STAGE, Some(*stage), 0, 0, 0.0)
+                    }
+                    SplitProgress::Chunks {
+                        done,
+                        total,
+                        percent,
+                    } => (
+                        ss_progress_kind::SS_PROGRESS_CHUNKS,
+                        None,
+                        *done,
+                        *total,
+                        *percent,
+                    ),
+                    SplitProgress::Writing {
+                        stem,
+                        done,
+                        total,
+                        percent,
+                    } => (
+                        ss_progress_kind::SS_PROGRESS_WRITING,
+                        Some(stem.as_str()),
+                        *done,
+                        *total,
+                        *percent,
+                    ),
+                    SplitProgress::Finished => {
+                        (ss_progress_kind::SS_PROGRESS_FINISHED, None, 0, 0, 100.0)
+                    }
+                    // No C representation (yet)
+                    SplitProgress::Timing(_) => return,
+                };
+                let name = name.and_then(|n| CString::new(n).ok());
+                let event = ss_progress {
+                    kind,
+                    name: name.as_ref().map_or(ptr::null(), |n| n.as_ptr()),
+                    done,
+                    total,
+                    percent,
+                };
+                // SAFETY: the caller registered the callback for this user data
+                unsafe { callback(&event, user_data) };
+            },
+            || f(opts),
+        )
+    }
+}
+
+/// Version of the library, e.g. `"1.1.0"`. Static; do not free.
+#[no_mangle]
+pub extern "C" fn ss_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Message of the last error on this thread, or NULL. Valid until the next
+/// failing call on this thread; do not free.
+#[no_mangle]
+pub extern "C" fn ss_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Create an engine. `options_json` is a `SplitOptions` object (fields left
+/// out keep their defaults) or NULL for the defaults.
+///
+/// # Safety
+/// `options_json` is NULL or a NUL-terminated string; `out` is writable.
+#[no_mangle]
+pub unsafe extern "C" fn ss_engine_new(
+    options_json: *const c_char,
+    out: *mut *mut ss_engine,
+) -> ss_status {
+    guard(|| {
+        if out.is_null() {
+            return Err(invalid("out is NULL"));
+        }
+        let opts = if options_json.is_null() {
+            SplitOptions::default()
+        } else {
+            let json = str_arg(options_json, "options_json")?;
+            serde_json::from_str(json)
+                .map_err(|e| invalid(&format!("invalid options JSON: {e}")))?
+        };
+        let engine = Box::new(ss_engine {
+            opts,
+            progress: None,
+            user_data: ptr::null_mut(),
+        });
+        *out = Box::into_raw(engine);
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `engine` is NULL or was returned by [`ss_engine_new`] and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn ss_engine_free(engine: *mut ss_engine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Receive progress of separations run with `engine`, passing `user_data`
+/// back to `callback`. A NULL callback removes it.
+///
+/// # Safety
+/// `engine` is a live engine; `callback` must be safe to call with
+/// `user_data` from the thread calling `ss_separate_*`.
+#[no_mangle]
+pub unsafe extern "C" fn ss_engine_set_progress_callback(
+    engine: *mut ss_engine,
+    callback: ss_progress_fn,
+    user_data: *mut c_void,
+) -> ss_status {
+    guard(|| {
+        let engine = engine.as_mut().ok_or_else(|| invalid("engine is NULL"))?;
+        engine.progress = callback;
+        engine.user_data = user_data;
+        Ok(())
+    })
+}
+
+/// Replace model inference with the test engine (see [`TestEngine`]): no
+/// model is loaded and each of the four stems is a quarter of the input.
+/// `window` and `hop` are in samples; 0 keeps the test engine's defaults.
+/// For testing applications without a model.
+///
+/// # Safety
+/// `engine` is a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn ss_engine_use_test_engine(
+    engine: *mut ss_engine,
+    window: usize,
+    hop: usize,
+) -> ss_status {
+    guard(|| {
+        let engine = engine.as_mut().ok_or_else(|| invalid("engine is NULL"))?;
+        let mut test_engine = TestEngine::new();
+        if window > 0 && hop > 0 {
+            test_engine = test_engine.with_window(window, hop);
+        }
+        engine.opts.test_engine = Some(test_engine);
+        Ok(())
+    })
+}
+
+/// Separate an audio file.
+///
+/// # Safety
+/// `engine` is a live engine, `path` a NUL-terminated string and `out`
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn ss_separate_file(
+    engine: *const ss_engine,
+    path: *const c_char,
+    out: *mut *mut ss_stems,
+) -> ss_status {
+    guard(|| {
+        let engine = ref_arg(engine, "engine")?;
+        let path = str_arg(path, "path")?;
+        if out.is_null() {
+            return Err(invalid("out is NULL"));
+        }
+        let stems = check(engine.separate(|opts| Separator::separate(path, opts)))?;
+        *out = Box::into_raw(Box::new(ss_stems { stems }));
+        Ok(())
+    })
+}
+
+/// Separate `frames` frames of interleaved audio with `channels` channels.
+/// The samples are copied; the buffer can be reused once this returns.
+///
+/// # Safety
+/// `engine` is a live engine, `samples` points to `frames * channels`
+/// floats and `out` is writable.
+#[no_mangle]
+pub unsafe extern "C" fn ss_separate_buffer(
+    engine: *const ss_engine,
+    samples: *const f32,
+    frames: usize,
+    channels: u16,
+    sample_rate: u32,
+    out: *mut *mut ss_stems,
+) -> ss_status {
+    guard(|| {
+        let engine = ref_arg(engine, "engine")?;
+        if samples.is_null() || out.is_null() {
+            return Err(invalid("samples or out is NULL"));
+        }
+        if channels == 0 {
+            return Err(invalid("channels is 0"));
+        }
+        let len = frames
+            .checked_mul(channels as usize)
+            .ok_or_else(|| invalid("frames * channels overflows"))?;
+        let audio = AudioData {
+            samples: std::slice::from_raw_parts(samples, len).to_vec(),
+            sample_rate,
+            channels,
+        };
+        let stems = check(engine.separate(|opts| Separator::separate_audio(&audio, opts)))?;
+        *out = Box::into_raw(Box::new(ss_stems { stems }));
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `stems` is NULL or was returned by `ss_separate_*` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_free(stems: *mut ss_stems) {
+    if !stems.is_null() {
+        drop(Box::from_raw(stems));
+    }
+}
+
+/// Number of stems; valid indices are `0..count`.
+///
+/// # Safety
+/// `stems` is NULL or a live stems object.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_count(stems: *const ss_stems) -> usize {
+    if stems.is_null() {
+        0
+    } else {
+        Stem::all().len()
+    }
+}
+
+/// Name of stem `index` (`"vocals"`, `"drums"`, `"bass"`, `"other"`), or
+/// NULL if out of range. Static; do not free.
+///
+/// # Safety
+/// `stems` is NULL or a live stems object.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_name(stems: *const ss_stems, index: usize) -> *const c_char {
+    if stems.is_null() {
+        return ptr::null();
+    }
+    let name: &[u8] = match Stem::all().get(index) {
+        Some(Stem::Vocals) => b"vocals\0",
+        Some(Stem::Drums) => b"drums\0",
+        Some(Stem::Bass) => b"bass\0",
+        Some(Stem::Other) => b"other\0",
+        None => return ptr::null(),
+    };
+    name.as_ptr().cast()
+}
+
+/// Frames per stem.
+///
+/// # Safety
+/// `stems` is NULL or a live stems object.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_frames(stems: *const ss_stems) -> usize {
+    stems.as_ref().map_or(0, |s| s.stems.num_samples)
+}
+
+/// # Safety
+/// `stems` is NULL or a live stems object.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_sample_rate(stems: *const ss_stems) -> u32 {
+    stems.as_ref().map_or(0, |s| s.stems.sample_rate)
+}
+
+/// Interleaved stereo samples of stem `index` (`2 * frames` floats), owned
+/// by `stems`; NULL if out of range.
+///
+/// # Safety
+/// `stems` is NULL or a live stems object. The pointer is valid until
+/// `stems` is freed.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_data(stems: *const ss_stems, index: usize) -> *const f32 {
+    match (stems.as_ref(), Stem::all().get(index)) {
+        (Some(s), Some(&stem)) => s.stems.frames(stem).as_ptr().cast(),
+        _ => ptr::null(),
+    }
+}
+
+/// Write stem `index` as a WAV file.
+///
+/// # Safety
+/// `stems` is a live stems object and `path` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ss_stems_save(
+    stems: *const ss_stems,
+    index: usize,
+    path: *const c_char,
+) -> ss_status {
+    guard(|| {
+        let stems = ref_arg(stems, "stems")?;
+        let path = str_arg(path, "path")?;
+        let &stem = Stem::all()
+            .get(index)
+            .ok_or_else(|| invalid(&format!("stem index {index} out of range")))?;
+        check(stems.stems.save(stem, path))
+    })
+}
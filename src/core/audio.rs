@@ -1,17 +1,117 @@
-use std::{fs::File, path::Path};
+use std::{
+    fs::File,
+    io::{Cursor, Seek, SeekFrom, Write},
+    path::Path,
+};
 
 use anyhow::{Context, Result};
 use symphonia::core::{
-    audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions, io::MediaSourceStream,
-    meta::MetadataOptions, probe::Hint,
+    audio::SampleBuffer,
+    codecs::DecoderOptions,
+    formats::{FormatOptions, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::TimeBase,
 };
 use symphonia::default::{get_codecs, get_probe};
 
-use crate::types::AudioData;
+use crate::{
+    core::metadata::{insert_wav_chunks, AudioMetadata},
+    types::{AudioData, TimePosition},
+};
+
+/// Audio decoded around a requested time range.
+#[derive(Clone, Debug)]
+pub struct DecodedRange {
+    /// Decoded audio, including any context padding around the requested span.
+    pub audio: AudioData,
+    /// Absolute frame index (in the source file) of the first decoded frame.
+    pub decoded_start: u64,
+    /// Requested span in absolute frames: `start..end`.
+    /// `end` is clamped to the end of the file.
+    pub start: u64,
+    pub end: u64,
+    /// Tags and cover art found in the file.
+    pub metadata: AudioMetadata,
+}
+
+impl DecodedRange {
+    /// Offset of the requested start within `audio`, in frames.
+    pub fn start_offset(&self) -> usize {
+        (self.start - self.decoded_start) as usize
+    }
+
+    /// Length of the requested span in frames.
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+}
 
 pub fn read_audio<P: AsRef<Path>>(path: P) -> Result<AudioData> {
-    let path: &Path = path.as_ref();
+    Ok(decode(path.as_ref(), None, None, 0)?.audio)
+}
+
+/// Decode only `start..end` of a file plus up to `context_frames` on either
+/// side, seeking instead of decoding from the beginning when the container
+/// supports it. Positions in samples are frames at the file's sample rate.
+pub fn read_audio_range<P: AsRef<Path>>(
+    path: P,
+    start: Option<TimePosition>,
+    end: Option<TimePosition>,
+    context_frames: u64,
+) -> Result<DecodedRange> {
+    decode(path.as_ref(), start, end, context_frames)
+}
+
+/// In-memory counterpart of [`read_audio_range`]: `start..end` of `audio`
+/// plus up to `context_frames` on either side.
+pub fn slice_audio_range(
+    audio: &AudioData,
+    start: Option<TimePosition>,
+    end: Option<TimePosition>,
+    context_frames: u64,
+) -> Result<DecodedRange> {
+    let ch = audio.channels.max(1) as usize;
+    let total = (audio.samples.len() / ch) as u64;
+
+    let start_frame = start.map(|p| p.to_frames(audio.sample_rate)).unwrap_or(0);
+    let end_frame = end.map(|p| p.to_frames(audio.sample_rate));
+    if let Some(end_frame) = end_frame {
+        if end_frame <= start_frame {
+            anyhow::bail!("Invalid range: end ({end_frame}) must be after start ({start_frame})");
+        }
+    }
+    let lo = start_frame.saturating_sub(context_frames).min(total);
+    let hi = end_frame
+        .map_or(total, |e| e.saturating_add(context_frames))
+        .clamp(lo, total);
 
+    let start = start_frame.clamp(lo, hi);
+    let end = end_frame.unwrap_or(hi).clamp(start, hi);
+    Ok(DecodedRange {
+        audio: AudioData {
+            samples: audio.samples[lo as usize * ch..hi as usize * ch].to_vec(),
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+        },
+        decoded_start: lo,
+        start,
+        end,
+        metadata: AudioMetadata::default(),
+    })
+}
+
+fn decode(
+    path: &Path,
+    start: Option<TimePosition>,
+    end: Option<TimePosition>,
+    context_frames: u64,
+) -> Result<DecodedRange> {
     let file: File =
         File::open(path).with_context(|| format!("Failed to open audio file: {:?}", path))?;
 
@@ -31,15 +131,54 @@ pub fn read_audio<P: AsRef<Path>>(path: P) -> Result<AudioData> {
     )?;
 
     let mut format = probed.format;
+
+    let mut metadata = AudioMetadata::default();
+    if let Some(rev) = format.metadata().skip_to_latest() {
+        metadata.merge_revision(rev);
+    }
+    let mut probed_meta = probed.metadata;
+    if let Some(rev) = probed_meta.get().as_mut().and_then(|m| m.skip_to_latest()) {
+        metadata.merge_revision(rev);
+    }
+
     let track = format.default_track().context("No default track found")?;
+    let track_id = track.id;
+    let codec_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let time_base = track.codec_params.time_base;
 
     let mut decoder = get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
 
+    let ranged = start.is_some() || end.is_some();
+    let start_frame = start.map(|p| p.to_frames(codec_rate)).unwrap_or(0);
+    let end_frame = end.map(|p| p.to_frames(codec_rate));
+    if let Some(end_frame) = end_frame {
+        if end_frame <= start_frame {
+            anyhow::bail!("Invalid range: end ({end_frame}) must be after start ({start_frame})");
+        }
+    }
+    let lo = start_frame.saturating_sub(context_frames);
+    let hi = end_frame.map(|e| e.saturating_add(context_frames));
+
+    if lo > 0 {
+        let ts = frames_to_ts(lo, codec_rate, time_base);
+        // Not every container can seek; packets before `lo` are skipped below.
+        if format
+            .seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id })
+            .is_ok()
+        {
+            decoder.reset();
+        }
+    }
+
     let mut samples: Vec<f32> = Vec::new();
     let mut sample_rate: u32 = 0;
     let mut channels: u16 = 0;
+    let mut decoded_start: Option<u64> = None;
 
     while let Ok(packet) = format.next_packet() {
+        if packet.track_id() != track_id {
+            continue;
+        }
         let decoded = decoder.decode(&packet)?;
         sample_rate = decoded.spec().rate;
         channels = decoded.spec().channels.count() as u16;
@@ -47,7 +186,29 @@ pub fn read_audio<P: AsRef<Path>>(path: P) -> Result<AudioData> {
         let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
         buffer.copy_interleaved_ref(decoded);
 
-        samples.extend_from_slice(buffer.samples());
+        if !ranged {
+            samples.extend_from_slice(buffer.samples());
+            continue;
+        }
+
+        let ch = channels.max(1) as usize;
+        let packet_start = ts_to_frames(packet.ts(), codec_rate, time_base);
+        let packet_frames = (buffer.samples().len() / ch) as u64;
+        let packet_end = packet_start + packet_frames;
+
+        if packet_end <= lo {
+            continue;
+        }
+        if let Some(hi) = hi {
+            if packet_start >= hi {
+                break;
+            }
+        }
+
+        let from = lo.saturating_sub(packet_start);
+        let to = hi.map_or(packet_frames, |hi| (hi - packet_start).min(packet_frames));
+        decoded_start.get_or_insert(packet_start + from);
+        samples.extend_from_slice(&buffer.samples()[from as usize * ch..to as usize * ch]);
     }
 
     println!(
@@ -57,19 +218,116 @@ pub fn read_audio<P: AsRef<Path>>(path: P) -> Result<AudioData> {
         samples.len()
     );
 
-    Ok(AudioData {
-        samples,
-        sample_rate,
-        channels,
+    let decoded_start = decoded_start.unwrap_or(lo);
+    let decoded_end = decoded_start + (samples.len() / channels.max(1) as usize) as u64;
+    let start = start_frame.clamp(decoded_start, decoded_end);
+    let end = end_frame.unwrap_or(decoded_end).clamp(start, decoded_end);
+
+    Ok(DecodedRange {
+        audio: AudioData {
+            samples,
+            sample_rate,
+            channels,
+        },
+        decoded_start,
+        start,
+        end,
+        metadata,
     })
 }
 
+/// Convert a frame index to a track timestamp.
+fn frames_to_ts(frames: u64, sample_rate: u32, time_base: Option<TimeBase>) -> u64 {
+    match time_base {
+        Some(tb) if sample_rate > 0 => {
+            (frames as u128 * tb.denom as u128 / (sample_rate as u128 * tb.numer as u128)) as u64
+        }
+        _ => frames,
+    }
+}
+
+/// Convert a track timestamp to a frame index.
+fn ts_to_frames(ts: u64, sample_rate: u32, time_base: Option<TimeBase>) -> u64 {
+    match time_base {
+        Some(tb) if sample_rate > 0 => {
+            (ts as u128 * sample_rate as u128 * tb.numer as u128 / tb.denom as u128) as u64
+        }
+        _ => ts,
+    }
+}
+
 pub fn write_audio(path: &str, audio: &AudioData) -> Result<()> {
     let path_obj = std::path::Path::new(path);
     if let Some(parent) = path_obj.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
+    let file = std::io::BufWriter::new(File::create(path)?);
+    encode_wav(file, audio)
+}
+
+/// Write a WAV file with tags and cover art (RIFF INFO and ID3 chunks).
+/// Equivalent to [`write_audio`] when `metadata` is empty.
+pub fn write_audio_with_metadata(
+    path: &str,
+    audio: &AudioData,
+    metadata: &AudioMetadata,
+) -> Result<()> {
+    let path_obj = std::path::Path::new(path);
+    if let Some(parent) = path_obj.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file = std::io::BufWriter::new(File::create(path)?);
+    write_audio_to(file, audio, metadata)
+}
+
+/// Encode a WAV file with optional tags into any seekable writer.
+///
+/// Tags go before the `data` chunk so streaming readers see them; the
+/// samples are streamed after them rather than buffered.
+pub fn write_audio_to<W: Write + Seek>(
+    mut writer: W,
+    audio: &AudioData,
+    metadata: &AudioMetadata,
+) -> Result<()> {
+    if metadata.is_empty() {
+        return encode_wav(writer, audio);
+    }
+
+    // hound's header for an empty file, with the tag chunks inserted
+    let empty = AudioData {
+        samples: Vec::new(),
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+    };
+    let mut buf = Cursor::new(Vec::new());
+    encode_wav(&mut buf, &empty)?;
+    let mut header = insert_wav_chunks(buf.into_inner(), metadata)?;
+
+    // `data` is the last chunk, its size the header's last four bytes
+    let data_len = u32::try_from(audio.samples.len() * 2)
+        .ok()
+        .and_then(|len| len.checked_add(header.len() as u32 - 8).map(|riff| (len, riff)));
+    let Some((data_len, riff_len)) = data_len else {
+        anyhow::bail!("audio too long for a WAV file");
+    };
+    let n = header.len();
+    header[n - 4..].copy_from_slice(&data_len.to_le_bytes());
+    header[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    writer.write_all(&header)?;
+    for &sample in &audio.samples {
+        writer.write_all(&to_i16(sample).to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Offset of `dwChannelMask` in hound's WAVE_FORMAT_EXTENSIBLE header.
+const CHANNEL_MASK_OFFSET: u64 = 40;
+
+fn encode_wav<W: Write + Seek>(mut writer: W, audio: &AudioData) -> Result<()> {
     let spec = hound::WavSpec {
         channels: audio.channels,
         sample_rate: audio.sample_rate,
@@ -77,12 +335,25 @@ pub fn write_audio(path: &str, audio: &AudioData) -> Result<()> {
         sample_format: hound::SampleFormat::Int,
     };
 
-    let mut writer = hound::WavWriter::create(path, spec)?;
-    for sample in &audio.samples {
-        let s = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-        writer.write_sample(s)?;
+    let mut wav = hound::WavWriter::new(&mut writer, spec)?;
+    for &sample in &audio.samples {
+        wav.write_sample(to_i16(sample))?;
     }
+    wav.finalize()?;
 
-    writer.finalize()?;
+    // hound assigns the first speaker positions (FL, FR, FC, LFE, ...) to
+    // the channels. Ours are stereo pairs of stems, not a surround layout,
+    // so mark them as unassigned.
+    if audio.channels > 2 {
+        let end = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(CHANNEL_MASK_OFFSET))?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.seek(SeekFrom::Start(end))?;
+        writer.flush()?;
+    }
     Ok(())
 }
+
+fn to_i16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
@@ -1,14 +1,68 @@
+use crate::error::Result;
+use anyhow::anyhow;
+use ndarray::{Array, Array2, Array3, ArrayView2, ArrayView3, Axis, Dimension};
 use num_complex::Complex32;
 use once_cell::sync::Lazy;
-use rustfft::{num_traits::Zero, Fft, FftPlanner};
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::num_traits::Zero;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+/// STFT analysis/synthesis window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowKind {
+    /// Periodic Hann (`torch.hann_window(n_fft)`), denominator `n_fft`.
+    #[default]
+    HannPeriodic,
+    /// Symmetric Hann (`numpy.hanning`), denominator `n_fft - 1`.
+    HannSymmetric,
+}
+
+/// How the signal is extended by `n_fft / 2` samples on each side so that
+/// frames are centered on multiples of the hop.
+///
+/// Serialized as `"reflect"`, `"zero"` or `{"constant": 0.1}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PadMode {
+    /// Mirror the signal without repeating the edge sample
+    /// (`torch.stft(pad_mode="reflect")`).
+    #[default]
+    Reflect,
+    Zero,
+    Constant(f32),
+}
+
+/// Window and padding of the STFT/iSTFT. The defaults match `torch.stft`,
+/// which HTDemucs was trained with; a model manifest can override them
+/// with an `stft` object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StftOptions {
+    pub window: WindowKind,
+    /// Only used by the forward transform.
+    pub pad_mode: PadMode,
+}
+
 /// Cached FFT components
 struct FftCacheEntry {
-    fft_forward: Arc<dyn Fft<f32>>,
-    fft_inverse: Arc<dyn Fft<f32>>,
-    hann_window: Vec<f32>,
+    /// Real-to-complex transform, `n_fft` samples to `n_fft / 2 + 1` bins
+    fft_forward: Arc<dyn RealToComplex<f32>>,
+    /// Complex-to-real inverse (unnormalized)
+    fft_inverse: Arc<dyn ComplexToReal<f32>>,
+    hann_periodic: Vec<f32>,
+    hann_symmetric: Vec<f32>,
+}
+
+impl FftCacheEntry {
+    fn window(&self, kind: WindowKind) -> &[f32] {
+        match kind {
+            WindowKind::HannPeriodic => &self.hann_periodic,
+            WindowKind::HannSymmetric => &self.hann_symmetric,
+        }
+    }
 }
 
 /// Global FFT cache supporting multiple sizes
@@ -41,11 +95,12 @@ impl FftCache {
         }
 
         // Create new entry
-        let mut planner = FftPlanner::new();
+        let mut planner = RealFftPlanner::new();
         let entry = Arc::new(FftCacheEntry {
             fft_forward: planner.plan_fft_forward(n_fft),
             fft_inverse: planner.plan_fft_inverse(n_fft),
-            hann_window: compute_hann(n_fft),
+            hann_periodic: compute_hann(n_fft, true),
+            hann_symmetric: compute_hann(n_fft, false),
         });
         
         entries.insert(n_fft, Arc::clone(&entry));
@@ -53,20 +108,46 @@ impl FftCache {
     }
 }
 
+/// Frames transformed together before being scattered to (or gathered from)
+/// the [F, Frames] spectrogram layout.
+const FRAME_BLOCK: usize = 16;
+
 /// Global FFT cache
 static FFT_CACHE: Lazy<FftCache> = Lazy::new(FftCache::new);
 
-/// Compute Hann window (called once per n_fft size)
-fn compute_hann(n_fft: usize) -> Vec<f32> {
+/// Compute Hann window (called once per n_fft size and kind)
+fn compute_hann(n_fft: usize, periodic: bool) -> Vec<f32> {
     if n_fft <= 1 {
         return vec![1.0];
     }
-    let denom = (n_fft - 1) as f32;
+    let denom = if periodic { n_fft as f64 } else { (n_fft - 1) as f64 };
     (0..n_fft)
-        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * (i as f32) / denom).cos())
+        .map(|i| (0.5 - 0.5 * (2.0 * std::f64::consts::PI * (i as f64) / denom).cos()) as f32)
         .collect()
 }
 
+/// Sample `j` of a signal of length `t` extended according to `mode`.
+/// Reflection repeats for pads longer than the signal.
+fn padded_sample(sig: &[f32], j: isize, mode: PadMode) -> f32 {
+    let t = sig.len() as isize;
+    if (0..t).contains(&j) {
+        return sig[j as usize];
+    }
+    match mode {
+        PadMode::Zero => 0.0,
+        PadMode::Constant(c) => c,
+        PadMode::Reflect => match t {
+            0 => 0.0,
+            1 => sig[0],
+            _ => {
+                let period = 2 * (t - 1);
+                let k = j.rem_euclid(period);
+                sig[(if k < t { k } else { period - k }) as usize]
+            }
+        },
+    }
+}
+
 pub fn to_planar_stereo(interleaved: &[f32], channels: u16) -> Vec<[f32; 2]> {
     if channels == 1 {
         interleaved.iter().map(|&x| [x, x]).collect()
@@ -84,63 +165,84 @@ pub fn to_planar_stereo(interleaved: &[f32], channels: u16) -> Vec<[f32; 2]> {
 /// Compute complex-as-channels spectrogram for stereo with center padding.
 /// Returns (buffer, F=n_fft/2, Frames) for given input.
 /// Layout is [1, 4, F, Frames] flattened => channels order: L.re, L.im, R.re, R.im.
+///
+/// Each channel is transformed with a real-to-complex FFT, which computes
+/// only the `n_fft / 2 + 1` non-redundant bins.
 pub fn stft_cac_stereo_centered(
     left: &[f32],
     right: &[f32],
     n_fft: usize,
     hop: usize,
+) -> (Vec<f32>, usize, usize) {
+    stft_cac_stereo_centered_with(left, right, n_fft, hop, &StftOptions::default())
+}
+
+/// [`stft_cac_stereo_centered`] with an explicit window and pad mode.
+pub fn stft_cac_stereo_centered_with(
+    left: &[f32],
+    right: &[f32],
+    n_fft: usize,
+    hop: usize,
+    opts: &StftOptions,
 ) -> (Vec<f32>, usize, usize) {
     assert_eq!(left.len(), right.len());
-    
+
     let t = left.len();
     let pad = n_fft / 2;
-
-    // Pre-allocate padded signals
-    let padded_len = pad + t + pad;
-    let mut l_sig = vec![0.0f32; padded_len];
-    let mut r_sig = vec![0.0f32; padded_len];
-    
-    // Copy with padding
-    l_sig[pad..pad + t].copy_from_slice(left);
-    r_sig[pad..pad + t].copy_from_slice(right);
-
     let frames = 1 + (t / hop);
     let f_bins = n_fft / 2;
+    let bins = n_fft / 2 + 1;
 
     // Get cached FFT and window
     let cache = FFT_CACHE.get_or_create(n_fft);
     let fft = &cache.fft_forward;
-    let window = &cache.hann_window;
+    let window = cache.window(opts.window);
 
     // Output buffer
     let mut out = vec![0.0f32; 4 * f_bins * frames];
+    let plane = f_bins * frames;
+    let mut planes: Vec<&mut [f32]> = out.chunks_mut(plane.max(1)).collect();
+
+    // Spectra of FRAME_BLOCK frames per channel, so each bin's run of
+    // frames is written to the [F, Frames] output contiguously
+    let mut frame = vec![0.0f32; n_fft];
+    let mut block = vec![Complex32::zero(); FRAME_BLOCK * bins];
+    let mut scratch = vec![Complex32::zero(); fft.get_scratch_len()];
+
+    for fr0 in (0..frames).step_by(FRAME_BLOCK) {
+        let nb = FRAME_BLOCK.min(frames - fr0);
+
+        for (ch, sig) in [left, right].into_iter().enumerate() {
+            for (b, spectrum) in block.chunks_exact_mut(bins).take(nb).enumerate() {
+                // Frame covers padded positions start..start + n_fft, i.e.
+                // input samples start - pad..; the padding is applied here.
+                let start = (fr0 + b) * hop;
+                let lo = pad.saturating_sub(start).min(n_fft);
+                let hi = (pad + t).saturating_sub(start).clamp(lo, n_fft);
+                let src = start + lo - pad;
+
+                for i in (0..lo).chain(hi..n_fft) {
+                    let j = (start + i) as isize - pad as isize;
+                    frame[i] = padded_sample(sig, j, opts.pad_mode) * window[i];
+                }
+                for ((x, &s), &w) in frame[lo..hi].iter_mut().zip(&sig[src..]).zip(&window[lo..hi]) {
+                    *x = s * w;
+                }
+                // Lengths match the plan, the only possible error
+                let _ = fft.process_with_scratch(&mut frame, spectrum, &mut scratch);
+            }
 
-    // Scratch buffers (reused across frames)
-    let mut buf_l = vec![Complex32::zero(); n_fft];
-    let mut buf_r = vec![Complex32::zero(); n_fft];
-
-    for fr in 0..frames {
-        let start = fr * hop;
-        let li = &l_sig[start..start + n_fft];
-        let ri = &r_sig[start..start + n_fft];
-
-        // Window and pack into complex
-        for i in 0..n_fft {
-            let w = window[i];
-            buf_l[i] = Complex32::new(li[i] * w, 0.0);
-            buf_r[i] = Complex32::new(ri[i] * w, 0.0);
-        }
-
-        fft.process(&mut buf_l);
-        fft.process(&mut buf_r);
-
-        // Write channels [L.re, L.im, R.re, R.im] over [F,Frames]
-        for fi in 0..f_bins {
-            let base_fr = fi * frames + fr;
-            out[0 * f_bins * frames + base_fr] = buf_l[fi].re;
-            out[1 * f_bins * frames + base_fr] = buf_l[fi].im;
-            out[2 * f_bins * frames + base_fr] = buf_r[fi].re;
-            out[3 * f_bins * frames + base_fr] = buf_r[fi].im;
+            // Channels [L.re, L.im, R.re, R.im] over [F,Frames]; the CaC
+            // layout drops the Nyquist bin
+            let [re, im] = &mut planes[2 * ch..2 * ch + 2] else { unreachable!() };
+            for fi in 0..f_bins {
+                let row = fi * frames + fr0..fi * frames + fr0 + nb;
+                let rows = re[row.clone()].iter_mut().zip(&mut im[row]);
+                for ((re, im), spectrum) in rows.zip(block.chunks_exact(bins)) {
+                    *re = spectrum[fi].re;
+                    *im = spectrum[fi].im;
+                }
+            }
         }
     }
 
@@ -150,6 +252,9 @@ pub fn stft_cac_stereo_centered(
 /// Inverse STFT for complex-as-channels stereo spectrogram
 /// Input: complex-as-channels [L.re, L.im, R.re, R.im] with shape [4, F, Frames]
 /// Returns: (left, right) stereo waveform of length target_length
+///
+/// Each channel is recovered with a complex-to-real inverse FFT of its
+/// `n_fft / 2 + 1` bins.
 pub fn istft_cac_stereo(
     spec_cac: &[f32],
     f_bins: usize,
@@ -157,102 +262,90 @@ pub fn istft_cac_stereo(
     n_fft: usize,
     hop: usize,
     target_length: usize,
+) -> (Vec<f32>, Vec<f32>) {
+    istft_cac_stereo_with(spec_cac, f_bins, frames, n_fft, hop, target_length, &StftOptions::default())
+}
+
+/// [`istft_cac_stereo`] with an explicit window (the pad mode is unused).
+pub fn istft_cac_stereo_with(
+    spec_cac: &[f32],
+    f_bins: usize,
+    frames: usize,
+    n_fft: usize,
+    hop: usize,
+    target_length: usize,
+    opts: &StftOptions,
 ) -> (Vec<f32>, Vec<f32>) {
     // Get cached IFFT and window
     let cache = FFT_CACHE.get_or_create(n_fft);
     let ifft = &cache.fft_inverse;
-    let window = &cache.hann_window;
+    let window = cache.window(opts.window);
 
     let pad = n_fft / 2;
     let padded_length = target_length + 2 * pad;
+    let bins = n_fft / 2 + 1;
 
-    // Output buffers
-    let mut left_out = vec![0.0f32; padded_length];
-    let mut right_out = vec![0.0f32; padded_length];
-    let mut window_sum = vec![0.0f32; padded_length];
+    // Scratch buffers, mirroring the forward transform's frame blocks
+    let mut block = vec![Complex32::zero(); FRAME_BLOCK * bins];
+    let mut frame = vec![0.0f32; n_fft];
+    let mut scratch = vec![Complex32::zero(); ifft.get_scratch_len()];
+    let plane = f_bins * frames;
 
-    // Scratch buffers
-    let mut buf_l = vec![Complex32::zero(); n_fft];
-    let mut buf_r = vec![Complex32::zero(); n_fft];
-    
     let scale = 1.0 / (n_fft as f32);
+    let synth: Vec<f32> = window.iter().map(|w| w * scale).collect();
 
+    // Squared-window envelope of the overlap-add
+    let mut window_sum = vec![0.0f32; padded_length];
     for fr in 0..frames {
-        // Clear buffers
-        buf_l.fill(Complex32::zero());
-        buf_r.fill(Complex32::zero());
-
-        // Fill positive frequencies [0..f_bins]
-        for fi in 0..f_bins {
-            let base_fr = fi * frames + fr;
-            buf_l[fi] = Complex32::new(
-                spec_cac[0 * f_bins * frames + base_fr],
-                spec_cac[1 * f_bins * frames + base_fr],
-            );
-            buf_r[fi] = Complex32::new(
-                spec_cac[2 * f_bins * frames + base_fr],
-                spec_cac[3 * f_bins * frames + base_fr],
-            );
-        }
-
-        // Fill negative frequencies (complex conjugate mirror)
-        for fi in 1..f_bins {
-            let neg_fi = n_fft - fi;
-            buf_l[neg_fi] = buf_l[fi].conj();
-            buf_r[neg_fi] = buf_r[fi].conj();
-        }
-
-        // Ensure DC and Nyquist are real
-        buf_l[0].im = 0.0;
-        buf_r[0].im = 0.0;
-        if n_fft % 2 == 0 && f_bins < n_fft {
-            buf_l[n_fft / 2].im = 0.0;
-            buf_r[n_fft / 2].im = 0.0;
-        }
-
-        // Apply IFFT
-        ifft.process(&mut buf_l);
-        ifft.process(&mut buf_r);
-
-        // Overlap-add with window
         let start = fr * hop;
-        for i in 0..n_fft {
-            let pos = start + i;
-            if pos < padded_length {
-                let w = window[i];
-                left_out[pos] += buf_l[i].re * w * scale;
-                right_out[pos] += buf_r[i].re * w * scale;
-                window_sum[pos] += w * w;
-            }
+        let end = (start + n_fft).min(padded_length);
+        for (s, &w) in window_sum[start..end].iter_mut().zip(window) {
+            *s += w * w;
         }
     }
 
-    // Normalize by window sum
-    for i in 0..padded_length {
-        let sum = window_sum[i];
-        if sum > 1e-10 {
-            left_out[i] /= sum;
-            right_out[i] /= sum;
+    let mut outputs = [vec![0.0f32; padded_length], vec![0.0f32; padded_length]];
+    for (ch, out) in outputs.iter_mut().enumerate() {
+        let re = &spec_cac[2 * ch * plane..(2 * ch + 1) * plane];
+        let im = &spec_cac[(2 * ch + 1) * plane..(2 * ch + 2) * plane];
+        for fr0 in (0..frames).step_by(FRAME_BLOCK) {
+            let nb = FRAME_BLOCK.min(frames - fr0);
+
+            for fi in 0..f_bins {
+                let row = fi * frames + fr0..fi * frames + fr0 + nb;
+                let rows = re[row.clone()].iter().zip(&im[row]);
+                for ((&re, &im), spectrum) in rows.zip(block.chunks_exact_mut(bins)) {
+                    spectrum[fi] = Complex32::new(re, im);
+                }
+            }
+
+            for (b, spectrum) in block.chunks_exact_mut(bins).take(nb).enumerate() {
+                // DC must be real for a real-valued signal; the CaC layout
+                // doesn't carry the Nyquist bin
+                spectrum[0].im = 0.0;
+                spectrum[f_bins..].fill(Complex32::zero());
+                let _ = ifft.process_with_scratch(spectrum, &mut frame, &mut scratch);
+
+                // Overlap-add with window
+                let start = (fr0 + b) * hop;
+                let end = (start + n_fft).min(padded_length);
+                for ((o, &x), &ws) in out[start..end].iter_mut().zip(&frame).zip(&synth) {
+                    *o += x * ws;
+                }
+            }
         }
     }
 
-    // Remove padding
-    let start = pad.min(left_out.len());
-    let end = (pad + target_length).min(left_out.len());
-
-    let left_final = if end > start {
-        left_out[start..end].to_vec()
-    } else {
-        vec![0.0; target_length]
-    };
-
-    let right_final = if end > start {
-        right_out[start..end].to_vec()
-    } else {
-        vec![0.0; target_length]
-    };
-
-    (left_final, right_final)
+    // Remove padding and normalize by window sum
+    let start = pad.min(padded_length);
+    let end = (pad + target_length).min(padded_length);
+    let gain: Vec<f32> = window_sum[start..end]
+        .iter()
+        .map(|&sum| if sum > 1e-10 { 1.0 / sum } else { 1.0 })
+        .collect();
+    let [left_out, right_out] =
+        outputs.map(|out| out[start..end].iter().zip(&gain).map(|(x, g)| x * g).collect());
+    (left_out, right_out)
 }
 
 /// Parallel iSTFT for multiple sources - processes all stems in parallel
@@ -263,13 +356,260 @@ pub fn istft_cac_stereo_parallel(
     n_fft: usize,
     hop: usize,
     target_length: usize,
+) -> Vec<(Vec<f32>, Vec<f32>)> {
+    istft_cac_stereo_parallel_with(sources_data, f_bins, frames, n_fft, hop, target_length, &StftOptions::default())
+}
+
+/// [`istft_cac_stereo_parallel`] with an explicit window.
+pub fn istft_cac_stereo_parallel_with(
+    sources_data: &[&[f32]],
+    f_bins: usize,
+    frames: usize,
+    n_fft: usize,
+    hop: usize,
+    target_length: usize,
+    opts: &StftOptions,
 ) -> Vec<(Vec<f32>, Vec<f32>)> {
     use rayon::prelude::*;
     
     sources_data
         .par_iter()
         .map(|spec_cac| {
-            istft_cac_stereo(spec_cac, f_bins, frames, n_fft, hop, target_length)
+            istft_cac_stereo_with(spec_cac, f_bins, frames, n_fft, hop, target_length, opts)
         })
         .collect()
 }
+
+/// General-purpose short-time Fourier transform over any number of
+/// channels, producing `[channels, n_fft / 2 + 1, frames]` complex
+/// spectrograms.
+///
+/// [`Stft::inverse`] undoes [`Stft::forward`]: for every configuration it
+/// accepts, `inverse(&forward(x)?, Some(len))` returns `x` up to float
+/// rounding (weighted overlap-add normalized by the squared-window
+/// envelope, like `torch.istft`). Configurations whose envelope vanishes
+/// somewhere in the output are rejected rather than reconstructed badly.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stft {
+    pub n_fft: usize,
+    pub hop: usize,
+    pub window: WindowKind,
+    /// Pad `n_fft / 2` samples on both sides so frame `i` is centered on
+    /// sample `i * hop`.
+    pub center: bool,
+    /// Padding used when `center` is set.
+    pub pad_mode: PadMode,
+}
+
+impl Stft {
+    /// Periodic Hann window, centered frames with reflect padding
+    /// (the `torch.stft` defaults).
+    pub fn new(n_fft: usize, hop: usize) -> Self {
+        Self {
+            n_fft,
+            hop,
+            window: WindowKind::default(),
+            center: true,
+            pad_mode: PadMode::default(),
+        }
+    }
+
+    pub fn with_window(mut self, window: WindowKind) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_center(mut self, center: bool) -> Self {
+        self.center = center;
+        self
+    }
+
+    pub fn with_pad_mode(mut self, pad_mode: PadMode) -> Self {
+        self.pad_mode = pad_mode;
+        self
+    }
+
+    /// Frequency bins per frame, `n_fft / 2 + 1`.
+    pub fn bins(&self) -> usize {
+        self.n_fft / 2 + 1
+    }
+
+    /// Number of frames for a signal of `len` samples.
+    pub fn frames(&self, len: usize) -> usize {
+        if self.center {
+            1 + len / self.hop.max(1)
+        } else if len < self.n_fft {
+            0
+        } else {
+            1 + (len - self.n_fft) / self.hop.max(1)
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.n_fft == 0 || self.hop == 0 {
+            return Err(anyhow!("n_fft and hop must be positive").into());
+        }
+        if self.hop > self.n_fft {
+            return Err(anyhow!("hop {} exceeds n_fft {}", self.hop, self.n_fft).into());
+        }
+        Ok(())
+    }
+
+    /// Transform `signal` (`[channels, samples]`) into a complex spectrogram
+    /// of shape `[channels, bins(), frames(samples)]`.
+    pub fn forward(&self, signal: ArrayView2<f32>) -> Result<Array3<Complex32>> {
+        self.validate()?;
+        let (channels, len) = signal.dim();
+        let frames = self.frames(len);
+        if frames == 0 {
+            return Err(anyhow!(
+                "signal of {len} samples is shorter than n_fft {} without centering",
+                self.n_fft
+            )
+            .into());
+        }
+
+        let n = self.n_fft;
+        let bins = self.bins();
+        let offset = if self.center { (n / 2) as isize } else { 0 };
+        let cache = FFT_CACHE.get_or_create(n);
+        let window = cache.window(self.window);
+        let mut scratch = vec![Complex32::zero(); cache.fft_forward.get_scratch_len()];
+        let mut buf = vec![0.0f32; n];
+        let mut spectrum = vec![Complex32::zero(); bins];
+        let mut out = Array3::<Complex32>::zeros((channels, bins, frames));
+
+        for (ch, row) in signal.outer_iter().enumerate() {
+            let sig = row.to_vec();
+            for fr in 0..frames {
+                let start = (fr * self.hop) as isize - offset;
+                for (i, x) in buf.iter_mut().enumerate() {
+                    *x = padded_sample(&sig, start + i as isize, self.pad_mode) * window[i];
+                }
+                let _ = cache
+                    .fft_forward
+                    .process_with_scratch(&mut buf, &mut spectrum, &mut scratch);
+                for (o, &z) in out.slice_mut(ndarray::s![ch, .., fr]).iter_mut().zip(&spectrum) {
+                    *o = z;
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Convenience wrapper for a single channel, returning `[bins, frames]`.
+    pub fn forward_mono(&self, signal: &[f32]) -> Result<Array2<Complex32>> {
+        let view = ArrayView2::from_shape((1, signal.len()), signal)?;
+        Ok(self.forward(view)?.index_axis_move(Axis(0), 0))
+    }
+
+    /// Inverse transform of a `[channels, bins(), frames]` spectrogram into
+    /// `[channels, samples]`. `length` trims or zero-extends the result;
+    /// it defaults to `(frames - 1) * hop` when centered and
+    /// `n_fft + (frames - 1) * hop` otherwise.
+    pub fn inverse(&self, spec: ArrayView3<Complex32>, length: Option<usize>) -> Result<Array2<f32>> {
+        self.validate()?;
+        let (channels, bins, frames) = spec.dim();
+        if bins != self.bins() {
+            return Err(anyhow!("expected {} bins, got {bins}", self.bins()).into());
+        }
+        if frames == 0 {
+            return Err(anyhow!("spectrogram has no frames").into());
+        }
+
+        let n = self.n_fft;
+        let pad = if self.center { n / 2 } else { 0 };
+        let full_len = n + (frames - 1) * self.hop;
+        let length = length.unwrap_or(full_len - 2 * pad);
+        let cache = FFT_CACHE.get_or_create(n);
+        let window = cache.window(self.window);
+
+        // Squared-window envelope; it must not vanish inside the output
+        let mut envelope = vec![0.0f32; full_len];
+        for fr in 0..frames {
+            for (e, &w) in envelope[fr * self.hop..].iter_mut().zip(window) {
+                *e += w * w;
+            }
+        }
+        let kept = pad..(pad + length).min(full_len);
+        if let Some(i) = kept.clone().find(|&i| envelope[i] < 1e-10) {
+            return Err(anyhow!(
+                "window overlap-add envelope is zero at sample {}; perfect reconstruction \
+                 needs a smaller hop or centered frames",
+                i - pad
+            )
+            .into());
+        }
+
+        let mut scratch = vec![Complex32::zero(); cache.fft_inverse.get_scratch_len()];
+        let mut spectrum = vec![Complex32::zero(); bins];
+        let mut buf = vec![0.0f32; n];
+        let mut acc = vec![vec![0.0f32; full_len]; channels];
+        let scale = 1.0 / n as f32;
+
+        for (ch, acc) in acc.iter_mut().enumerate() {
+            for fr in 0..frames {
+                for (z, &x) in spectrum.iter_mut().zip(spec.slice(ndarray::s![ch, .., fr])) {
+                    *z = x;
+                }
+                // DC and Nyquist of a real signal are real
+                spectrum[0].im = 0.0;
+                if n % 2 == 0 {
+                    spectrum[bins - 1].im = 0.0;
+                }
+                let _ = cache
+                    .fft_inverse
+                    .process_with_scratch(&mut spectrum, &mut buf, &mut scratch);
+
+                let start = fr * self.hop;
+                for ((a, &x), &w) in acc[start..start + n].iter_mut().zip(&buf).zip(window) {
+                    *a += x * scale * w;
+                }
+            }
+        }
+
+        let mut out = Array2::<f32>::zeros((channels, length));
+        for (mut row, acc) in out.outer_iter_mut().zip(&acc) {
+            for (o, i) in row.iter_mut().zip(kept.clone()) {
+                *o = acc[i] / envelope[i];
+            }
+        }
+        Ok(out)
+    }
+
+    /// Convenience wrapper for a single `[bins, frames]` spectrogram.
+    pub fn inverse_mono(&self, spec: ArrayView2<Complex32>, length: Option<usize>) -> Result<Vec<f32>> {
+        let out = self.inverse(spec.insert_axis(Axis(0)), length)?;
+        Ok(out.into_raw_vec())
+    }
+}
+
+/// Magnitude `|z|` of every bin.
+pub fn magnitude<D: Dimension>(spec: &Array<Complex32, D>) -> Array<f32, D> {
+    spec.mapv(|z| z.norm())
+}
+
+/// Phase `arg z` of every bin, in radians.
+pub fn phase<D: Dimension>(spec: &Array<Complex32, D>) -> Array<f32, D> {
+    spec.mapv(|z| z.arg())
+}
+
+/// Rebuild a spectrogram from magnitude and phase, e.g. after editing the
+/// magnitude of a stem.
+pub fn from_polar<D: Dimension>(
+    magnitude: &Array<f32, D>,
+    phase: &Array<f32, D>,
+) -> Result<Array<Complex32, D>> {
+    if magnitude.shape() != phase.shape() {
+        return Err(anyhow!(
+            "magnitude shape {:?} differs from phase shape {:?}",
+            magnitude.shape(),
+            phase.shape()
+        )
+        .into());
+    }
+    Ok(ndarray::Zip::from(magnitude)
+        .and(phase)
+        .map_collect(|&m, &p| Complex32::from_polar(m, p)))
+}
@@ -1,24 +1,33 @@
 #![cfg_attr(feature = "engine-mock", allow(dead_code, unused_imports))]
 
 use crate::{
-    core::dsp::{istft_cac_stereo_parallel, stft_cac_stereo_centered},
+    core::dsp::{istft_cac_stereo_parallel_with, stft_cac_stereo_centered_with, StftOptions},
     error::{Result, StemError},
     model::model_manager::ModelHandle,
-    types::ModelManifest,
+    types::{IODesc, ModelManifest, TensorDType},
 };
 
 use anyhow::anyhow;
 use ndarray::Array3;
 use once_cell::sync::OnceCell;
 use ort::{
-    execution_providers::ExecutionProviderDispatch,
+    execution_providers::{CPUExecutionProvider, ExecutionProviderDispatch},
     session::{
         builder::{GraphOptimizationLevel, SessionBuilder},
         Session,
     },
+    tensor::TensorElementType,
     value::{Tensor, Value},
 };
-use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex, MutexGuard,
+    },
+};
 
 // CUDA: Linux and Windows only
 #[cfg(all(feature = "cuda", any(target_os = "linux", target_os = "windows")))]
@@ -29,22 +38,149 @@ use ort::execution_providers::CoreMLExecutionProvider;
 // DirectML: Windows only
 #[cfg(all(feature = "directml", target_os = "windows"))]
 use ort::execution_providers::{DirectMLExecutionProvider, ExecutionProvider};
+// TensorRT: NVIDIA GPUs
+#[cfg(feature = "tensorrt")]
+use ort::execution_providers::TensorRTExecutionProvider;
 // oneDNN: All platforms
 #[cfg(feature = "onednn")]
 use ort::execution_providers::OneDNNExecutionProvider;
 
-static SESSION: OnceCell<Mutex<Session>> = OnceCell::new();
+static POOL: OnceCell<SessionPool> = OnceCell::new();
 static MANIFEST: OnceCell<ModelManifest> = OnceCell::new();
 static ORT_INIT: OnceCell<()> = OnceCell::new();
+static CONFIG: OnceCell<EngineConfig> = OnceCell::new();
 
-const DEMUCS_T: usize = 343_980;
-const DEMUCS_F: usize = 2048;
-const DEMUCS_FRAMES: usize = 336;
+pub(crate) const DEMUCS_T: usize = 343_980;
+pub(crate) const DEMUCS_F: usize = 2048;
+pub(crate) const DEMUCS_FRAMES: usize = 336;
 const DEMUCS_NFFT: usize = 4096;
 const DEMUCS_HOP: usize = 1024;
 
+/// Execution providers to try, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProviderKind {
+    Cuda,
+    TensorRt,
+    CoreMl,
+    DirectMl,
+    OneDnn,
+    Cpu,
+}
+
+/// Graph optimization level, see ONNX Runtime's `GraphOptimizationLevel`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizationLevel {
+    Disable,
+    Level1,
+    Level2,
+    #[default]
+    Level3,
+}
+
+impl From<OptimizationLevel> for GraphOptimizationLevel {
+    fn from(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::Disable => GraphOptimizationLevel::Disable,
+            OptimizationLevel::Level1 => GraphOptimizationLevel::Level1,
+            OptimizationLevel::Level2 => GraphOptimizationLevel::Level2,
+            OptimizationLevel::Level3 => GraphOptimizationLevel::Level3,
+        }
+    }
+}
+
+/// ONNX Runtime session settings.
+///
+/// The session pool is created by the first `preload`. Later calls must ask
+/// for the same sessions (see [`EngineConfig::same_sessions`]) or they fail,
+/// so set it on the first separation (or in [`preload_with_config`]).
+///
+/// # Example
+/// ```
+/// use stem_splitter_core::{EngineConfig, ExecutionProviderKind};
+///
+/// // Cap a job to 4 cores, CPU only
+/// let cfg = EngineConfig {
+///     providers: Some(vec![ExecutionProviderKind::Cpu]),
+///     intra_threads: Some(4),
+///     inter_threads: Some(1),
+///     ..Default::default()
+/// };
+/// assert_eq!(cfg.intra_threads(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Providers to try in order; CPU is always the final fallback.
+    /// `None` picks providers from the enabled cargo features.
+    /// Providers not compiled in are skipped with a warning.
+    pub providers: Option<Vec<ExecutionProviderKind>>,
+    /// Threads used within an operator. Default: all cores divided
+    /// among the sessions.
+    pub intra_threads: Option<usize>,
+    /// Threads used across independent operators. Default: all cores
+    /// divided among the sessions.
+    pub inter_threads: Option<usize>,
+    pub optimization_level: OptimizationLevel,
+    /// Use ONNX Runtime's memory arena and memory patterns. Disabling them
+    /// lowers peak memory at some speed cost.
+    pub memory_arena: bool,
+    /// Number of sessions in the pool. Windows of one job and concurrent
+    /// jobs are spread across them; each session holds its own copy of the
+    /// model weights.
+    pub sessions: usize,
+    /// Windows stacked into one inference run. Needs a model with a dynamic
+    /// batch axis; otherwise inference falls back to batch size 1.
+    pub batch_size: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            providers: None,
+            intra_threads: None,
+            inter_threads: None,
+            optimization_level: OptimizationLevel::Level3,
+            memory_arena: true,
+            sessions: 1,
+            batch_size: 1,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn intra_threads(&self) -> usize {
+        self.intra_threads
+            .unwrap_or_else(|| (available_threads() / self.sessions.max(1)).max(1))
+    }
+
+    pub fn inter_threads(&self) -> usize {
+        self.inter_threads
+            .unwrap_or_else(|| (available_threads() / self.sessions.max(1)).max(1))
+    }
+
+    /// Whether both configs build the same session pool. Only `batch_size`
+    /// may differ, since it is applied per separation.
+    pub fn same_sessions(&self, other: &EngineConfig) -> bool {
+        self.providers == other.providers
+            && self.intra_threads() == other.intra_threads()
+            && self.inter_threads() == other.inter_threads()
+            && self.optimization_level == other.optimization_level
+            && self.memory_arena == other.memory_arena
+            && self.sessions.max(1) == other.sessions.max(1)
+    }
+}
+
+fn available_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Providers chosen by cargo features (and `ENABLE_COREML` for CoreML).
 #[allow(unused_mut)]
-fn get_execution_providers() -> Vec<ExecutionProviderDispatch> {
+fn default_execution_providers() -> Vec<ExecutionProviderDispatch> {
     let mut providers: Vec<ExecutionProviderDispatch> = Vec::new();
 
     #[cfg(all(feature = "cuda", any(target_os = "linux", target_os = "windows")))]
@@ -98,53 +234,161 @@ fn get_execution_providers() -> Vec<ExecutionProviderDispatch> {
     providers
 }
 
+/// Build the providers requested in `config`, in order.
+fn execution_providers(config: &EngineConfig) -> Vec<ExecutionProviderDispatch> {
+    let Some(kinds) = &config.providers else {
+        return default_execution_providers();
+    };
+    kinds
+        .iter()
+        .filter_map(|&kind| {
+            let provider = build_provider(kind, config);
+            if provider.is_none() {
+                eprintln!("Execution provider {:?} is not available in this build, skipping", kind);
+            }
+            provider
+        })
+        .collect()
+}
+
+#[allow(unused_variables, unreachable_code)]
+fn build_provider(kind: ExecutionProviderKind, config: &EngineConfig) -> Option<ExecutionProviderDispatch> {
+    match kind {
+        ExecutionProviderKind::Cpu => Some(
+            CPUExecutionProvider::default()
+                .with_arena_allocator(config.memory_arena)
+                .build(),
+        ),
+        ExecutionProviderKind::Cuda => {
+            #[cfg(all(feature = "cuda", any(target_os = "linux", target_os = "windows")))]
+            return Some(CUDAExecutionProvider::default().build());
+            None
+        }
+        ExecutionProviderKind::TensorRt => {
+            #[cfg(feature = "tensorrt")]
+            return Some(TensorRTExecutionProvider::default().build());
+            None
+        }
+        ExecutionProviderKind::CoreMl => {
+            #[cfg(all(feature = "coreml", target_os = "macos"))]
+            return Some(CoreMLExecutionProvider::default().build());
+            None
+        }
+        ExecutionProviderKind::DirectMl => {
+            #[cfg(all(feature = "directml", target_os = "windows"))]
+            return Some(DirectMLExecutionProvider::default().build());
+            None
+        }
+        ExecutionProviderKind::OneDnn => {
+            #[cfg(feature = "onednn")]
+            return Some(OneDNNExecutionProvider::default().build());
+            None
+        }
+    }
+}
+
+fn build_session(
+    h: &ModelHandle,
+    config: &EngineConfig,
+    providers: Vec<ExecutionProviderDispatch>,
+    parallel: bool,
+) -> std::result::Result<Session, ort::Error> {
+    let mut builder = SessionBuilder::new()?
+        .with_optimization_level(config.optimization_level.into())?;
+    if !providers.is_empty() {
+        builder = builder.with_execution_providers(providers)?;
+    }
+    if parallel {
+        builder = builder.with_parallel_execution(true)?;
+    }
+    builder
+        .with_intra_threads(config.intra_threads())?
+        .with_inter_threads(config.inter_threads())?
+        .with_memory_pattern(config.memory_arena)?
+        .commit_from_file(&h.local_path)
+}
+
 #[cfg(not(feature = "engine-mock"))]
 pub fn preload(h: &ModelHandle) -> Result<()> {
-    ORT_INIT.get_or_try_init::<_, StemError>(|| {
-        ort::init().commit().map_err(StemError::from)?;
-        Ok(())
+    preload_with_config(h, &EngineConfig::default())
+}
+
+/// Create the inference session for `h` with the given engine settings.
+/// Does nothing if a session already exists, unless `h` is a different
+/// model or `config` asks for different sessions, which is an error: one
+/// model is loaded per process.
+#[cfg(not(feature = "engine-mock"))]
+pub fn preload_with_config(h: &ModelHandle, config: &EngineConfig) -> Result<()> {
+    check_manifest_dtypes(&h.manifest)?;
+
+    POOL.get_or_try_init(|| -> Result<SessionPool> {
+        ORT_INIT.get_or_try_init::<_, StemError>(|| {
+            ort::init().commit().map_err(StemError::from)?;
+            Ok(())
+        })?;
+
+        let count = config.sessions.max(1);
+        if count > 1 {
+            eprintln!("Creating a pool of {} sessions", count);
+        }
+        let sessions = (0..count)
+            .map(|_| create_session(h, config))
+            .collect::<Result<Vec<_>>>()?;
+
+        MANIFEST.set(h.manifest.clone()).ok();
+        CONFIG.set(config.clone()).ok();
+        Ok(SessionPool::new(h.local_path.clone(), sessions))
     })?;
 
-    let num_threads = std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(4);
-
-    let providers = get_execution_providers();
-    
-    let session = if providers.is_empty() {
-        eprintln!("Using CPU ({} threads) - no GPU features enabled", num_threads);
-        SessionBuilder::new()?
-            .with_optimization_level(GraphOptimizationLevel::Level3)?
-            .with_intra_threads(num_threads)?
-            .with_inter_threads(num_threads)?
-            .with_parallel_execution(true)?
-            .commit_from_file(&h.local_path)?
+    let pool = POOL.get().expect("pool initialized above");
+    if pool.model != h.local_path {
+        return Err(anyhow!(
+            "engine already loaded {}; cannot load {} in the same process",
+            pool.model.display(),
+            h.local_path.display()
+        )
+        .into());
+    }
+    match CONFIG.get() {
+        Some(loaded) if !loaded.same_sessions(config) => Err(anyhow!(
+            "engine already initialized with {loaded:?}; cannot switch to {config:?}"
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Number of inference sessions available for concurrent windows.
+#[cfg(not(feature = "engine-mock"))]
+pub fn session_count() -> usize {
+    POOL.get().map_or(1, |pool| pool.sessions.len())
+}
+
+#[cfg(not(feature = "engine-mock"))]
+fn create_session(h: &ModelHandle, config: &EngineConfig) -> Result<Session> {
+    let num_threads = config.intra_threads();
+
+    // Plain CPU session; the explicit CPU provider only matters when the
+    // arena is disabled.
+    let cpu_providers = || {
+        if config.memory_arena {
+            Vec::new()
+        } else {
+            build_provider(ExecutionProviderKind::Cpu, config).into_iter().collect()
+        }
+    };
+
+    let providers = execution_providers(config);
+    let only_cpu = config.providers.as_deref() == Some(&[ExecutionProviderKind::Cpu]);
+
+    let session = if providers.is_empty() || only_cpu {
+        eprintln!("Using CPU ({} threads)", num_threads);
+        build_session(h, config, cpu_providers(), true)?
     } else {
-        #[allow(unused_mut)]
-        let mut provider_names: Vec<&str> = Vec::new();
-        #[cfg(all(feature = "cuda", any(target_os = "linux", target_os = "windows")))]
-        provider_names.push("CUDA");
-        #[cfg(all(feature = "coreml", target_os = "macos"))]
-        provider_names.push("CoreML");
-        #[cfg(all(feature = "directml", target_os = "windows"))]
-        provider_names.push("DirectML");
-        #[cfg(feature = "onednn")]
-        provider_names.push("oneDNN");
-        
-        eprintln!("Trying execution providers: {:?} (with CPU fallback)", provider_names);
-        
+        eprintln!("Trying execution providers: {:?} (with CPU fallback)", providers);
+
         // Try GPU providers first, fallback to CPU on any error
-        let gpu_result = (|| -> std::result::Result<Session, ort::Error> {
-            let builder = SessionBuilder::new()?
-                .with_optimization_level(GraphOptimizationLevel::Level3)?
-                .with_execution_providers(providers)?;
-            builder
-                .with_intra_threads(num_threads)?
-                .with_inter_threads(num_threads)?
-                .commit_from_file(&h.local_path)
-        })();
-        
-        match gpu_result {
+        match build_session(h, config, providers, false) {
             Ok(session) => {
                 eprintln!("Successfully initialized session with GPU providers!");
                 session
@@ -155,19 +399,68 @@ pub fn preload(h: &ModelHandle) -> Result<()> {
                 eprintln!("  Error message: {}", e);
                 eprintln!("  Debug: {:?}", e);
                 eprintln!("Falling back to CPU ({} threads)", num_threads);
-                SessionBuilder::new()?
-                    .with_optimization_level(GraphOptimizationLevel::Level3)?
-                    .with_intra_threads(num_threads)?
-                    .with_inter_threads(num_threads)?
-                    .with_parallel_execution(true)?
-                    .commit_from_file(&h.local_path)?
+                build_session(h, config, cpu_providers(), true)?
             }
         }
     };
+    Ok(session)
+}
 
-    SESSION.set(Mutex::new(session)).ok();
-    MANIFEST.set(h.manifest.clone()).ok();
-    Ok(())
+/// Sessions over the same model. Each inference run checks out a free
+/// session, so up to `sessions.len()` windows (from one job or several)
+/// run at once.
+struct SessionPool {
+    /// Model file the sessions were created from.
+    model: PathBuf,
+    sessions: Vec<Mutex<Session>>,
+    free: Mutex<Vec<usize>>,
+    available: Condvar,
+    /// Set once the model rejects a batch; later runs use batch size 1.
+    batch_unsupported: AtomicBool,
+}
+
+impl SessionPool {
+    fn new(model: PathBuf, sessions: Vec<Session>) -> Self {
+        let free = (0..sessions.len()).rev().collect();
+        Self {
+            model,
+            sessions: sessions.into_iter().map(Mutex::new).collect(),
+            free: Mutex::new(free),
+            available: Condvar::new(),
+            batch_unsupported: AtomicBool::new(false),
+        }
+    }
+
+    /// Wait for a free session.
+    fn acquire(&self) -> PooledSession<'_> {
+        let mut free = self.free.lock().unwrap_or_else(|e| e.into_inner());
+        let idx = loop {
+            if let Some(idx) = free.pop() {
+                break idx;
+            }
+            free = self.available.wait(free).unwrap_or_else(|e| e.into_inner());
+        };
+        drop(free);
+        PooledSession {
+            pool: self,
+            idx,
+            session: self.sessions[idx].lock().expect("session poisoned"),
+        }
+    }
+}
+
+struct PooledSession<'a> {
+    pool: &'a SessionPool,
+    idx: usize,
+    session: MutexGuard<'a, Session>,
+}
+
+impl Drop for PooledSession<'_> {
+    fn drop(&mut self) {
+        let mut free = self.pool.free.lock().unwrap_or_else(|e| e.into_inner());
+        free.push(self.idx);
+        self.pool.available.notify_one();
+    }
 }
 
 #[cfg(not(feature = "engine-mock"))]
@@ -177,24 +470,89 @@ pub fn manifest() -> &'static ModelManifest {
         .expect("engine::preload() must be called once before using the engine")
 }
 
+/// Window and padding declared by the loaded model's manifest.
 #[cfg(not(feature = "engine-mock"))]
+fn stft_options() -> StftOptions {
+    MANIFEST.get().map(|m| m.stft).unwrap_or_default()
+}
+
+/// Model inputs for a batch of windows: the output of the STFT stage.
+pub struct PreparedWindows {
+    batch: usize,
+    /// Samples per window.
+    t: usize,
+    /// Time branch [B, 2, T], planar.
+    time: Vec<f32>,
+    /// Spectrogram branch [B, 4, F, Frames], complex-as-channels.
+    spec: Vec<f32>,
+}
+
+impl PreparedWindows {
+    pub fn batch(&self) -> usize {
+        self.batch
+    }
+}
+
+/// Raw model outputs for a batch of windows: the output of the inference stage.
+pub struct RawOutputs {
+    batch: usize,
+    t: usize,
+    sources: usize,
+    /// Time-domain output [B, S, 2, T].
+    time: Vec<f32>,
+    /// Frequency-domain output [B, S, 4, F, Frames].
+    freq: Vec<f32>,
+}
+
+/// Separate one window. Returns (sources, 2, T).
 pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
-    if left.len() != right.len() {
-        return Err(anyhow!("L/R length mismatch").into());
+    let mut out = run_windows_demucs(&[(left, right)])?;
+    Ok(out.pop().expect("one output per window"))
+}
+
+/// Separate several windows, stacked along the batch axis into a single
+/// inference run (see [`infer`] for the batch-size fallback).
+///
+/// This runs the three stages [`prepare_windows`], [`infer`] and
+/// [`finish_windows`] back to back; callers that want to overlap DSP with
+/// inference can run the stages on separate threads.
+pub fn run_windows_demucs(windows: &[(&[f32], &[f32])]) -> Result<Vec<Array3<f32>>> {
+    finish_windows(infer(prepare_windows(windows)?)?)
+}
+
+/// STFT stage: build the model inputs for a batch of windows.
+#[cfg(not(feature = "engine-mock"))]
+pub fn prepare_windows(windows: &[(&[f32], &[f32])]) -> Result<PreparedWindows> {
+    let batch = windows.len();
+    let t = DEMUCS_T;
+    if batch == 0 {
+        return Err(anyhow!("No windows to process").into());
     }
-    let t = left.len();
-    if t != DEMUCS_T {
-        return Err(anyhow!("Bad window length {} (expected {})", t, DEMUCS_T).into());
+    for (left, right) in windows {
+        if left.len() != right.len() {
+            return Err(anyhow!("L/R length mismatch").into());
+        }
+        if left.len() != t {
+            return Err(anyhow!("Bad window length {} (expected {})", left.len(), t).into());
+        }
     }
 
-    // Build time branch [1,2,T], planar
-    let mut planar = Vec::with_capacity(2 * t);
-    planar.extend_from_slice(left);
-    planar.extend_from_slice(right);
-    let time_value: Value = Tensor::from_array((vec![1, 2, t], planar))?.into_dyn();
+    // Build time branch [B,2,T], planar
+    let mut time = Vec::with_capacity(batch * 2 * t);
+    for (left, right) in windows {
+        time.extend_from_slice(left);
+        time.extend_from_slice(right);
+    }
 
-    // Build spec branch [1,4,F,Frames] with center padding, Hann, 4096/1024
-    let (spec_cac, f_bins, frames) = stft_cac_stereo_centered(left, right, DEMUCS_NFFT, DEMUCS_HOP);
+    // Build spec branch [B,4,F,Frames] with center padding, Hann, 4096/1024
+    let stft = stft_options();
+    let specs: Vec<(Vec<f32>, usize, usize)> = windows
+        .par_iter()
+        .map(|(left, right)| {
+            stft_cac_stereo_centered_with(left, right, DEMUCS_NFFT, DEMUCS_HOP, &stft)
+        })
+        .collect();
+    let (f_bins, frames) = (specs[0].1, specs[0].2);
     if f_bins != DEMUCS_F || frames != DEMUCS_FRAMES {
         return Err(anyhow!(
             "Spec dims mismatch: got F={},Frames={}, expected F={},Frames={}",
@@ -205,22 +563,121 @@ pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
         )
         .into());
     }
-    let spec_value: Value = Tensor::from_array((vec![1, 4, f_bins, frames], spec_cac))?.into_dyn();
+    let mut spec = Vec::with_capacity(batch * 4 * f_bins * frames);
+    for (s, _, _) in specs {
+        spec.extend_from_slice(&s);
+    }
 
-    let mut session = SESSION
-        .get()
-        .expect("engine::preload first")
-        .lock()
-        .expect("session poisoned");
+    Ok(PreparedWindows { batch, t, time, spec })
+}
+
+/// Inference stage: run the model on a prepared batch.
+///
+/// Falls back to one window per run (for this and all later calls on the
+/// loaded model) if the model has a fixed batch size or rejects the
+/// batch's dimensions. Other failures are returned as is.
+#[cfg(not(feature = "engine-mock"))]
+pub fn infer(p: PreparedWindows) -> Result<RawOutputs> {
+    let pool = POOL.get().expect("engine::preload first");
+    with_batch_fallback(
+        p.batch,
+        &pool.batch_unsupported,
+        || run_session(&p.time, &p.spec, p.batch, p.t),
+        || {
+            let time_len = p.time.len() / p.batch;
+            let spec_len = p.spec.len() / p.batch;
+            let mut out = RawOutputs {
+                batch: p.batch,
+                t: p.t,
+                sources: 0,
+                time: Vec::new(),
+                freq: Vec::new(),
+            };
+            for b in 0..p.batch {
+                let one = run_session(
+                    &p.time[b * time_len..(b + 1) * time_len],
+                    &p.spec[b * spec_len..(b + 1) * spec_len],
+                    1,
+                    p.t,
+                )?;
+                out.sources = one.sources;
+                out.time.extend_from_slice(&one.time);
+                out.freq.extend_from_slice(&one.freq);
+            }
+            Ok(out)
+        },
+    )
+}
+
+/// Run `batch` windows at once with `run_all`, or one at a time with
+/// `run_each` once `unsupported` is set. A [`StemError::BatchRejected`]
+/// from `run_all` sets `unsupported` and retries with `run_each`.
+pub(crate) fn with_batch_fallback<T>(
+    batch: usize,
+    unsupported: &AtomicBool,
+    run_all: impl FnOnce() -> Result<T>,
+    run_each: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    if batch <= 1 {
+        return run_all();
+    }
+    if !unsupported.load(Ordering::Relaxed) {
+        match run_all() {
+            Err(StemError::BatchRejected(reason)) => {
+                eprintln!("Batched inference rejected ({}), falling back to batch size 1", reason);
+                unsupported.store(true, Ordering::Relaxed);
+            }
+            result => return result,
+        }
+    }
+    run_each()
+}
+
+/// Turn ORT refusing a batch's input dimensions, which usually means the
+/// batch axis is fixed, into [`StemError::BatchRejected`].
+#[cfg(not(feature = "engine-mock"))]
+fn batch_run_error(e: ort::Error, batch: usize) -> StemError {
+    let message = e.message().to_lowercase();
+    let shape = e.code() == ort::error::ErrorCode::InvalidArgument
+        && (message.contains("dimension") || message.contains("shape"));
+    if batch > 1 && shape {
+        StemError::BatchRejected(e.to_string())
+    } else {
+        e.into()
+    }
+}
+
+#[cfg(not(feature = "engine-mock"))]
+fn run_session(time: &[f32], spec: &[f32], batch: usize, t: usize) -> Result<RawOutputs> {
+    let mut pooled = POOL.get().expect("engine::preload first").acquire();
+    let session = &mut *pooled.session;
+
+    let time_value = make_tensor(input_dtype(session, "input")?, vec![batch, 2, t], time)?;
+    let spec_value = make_tensor(
+        input_dtype(session, "x")?,
+        vec![batch, 4, DEMUCS_F, DEMUCS_FRAMES],
+        spec,
+    )?;
 
     // Get input names
     let in_time = session
         .inputs
         .iter()
         .find(|i| i.name == "input")
-        .map(|i| i.name.clone())
         .ok_or_else(|| anyhow!("Model missing input 'input'"))?;
 
+    if batch > 1 {
+        let fixed_batch = in_time
+            .input_type
+            .tensor_shape()
+            .and_then(|s| s.first().copied())
+            .filter(|&d| d > 0);
+        if let Some(d) = fixed_batch {
+            return Err(StemError::BatchRejected(format!("model has a fixed batch size of {}", d)));
+        }
+    }
+    let in_time = in_time.name.clone();
+
     let in_spec = session
         .inputs
         .iter()
@@ -229,11 +686,13 @@ pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
         .ok_or_else(|| anyhow!("Model missing input 'x'"))?;
 
     // Run inference
-    let outputs = session.run(vec![(in_time, time_value), (in_spec, spec_value)])?;
+    let outputs = session
+        .run(vec![(in_time, time_value), (in_spec, spec_value)])
+        .map_err(|e| batch_run_error(e, batch))?;
 
     // Extract both outputs from the model
-    // "output": frequency domain [1, sources, 4, F, Frames]
-    // "add_67": time domain [1, sources, 2, T]
+    // "output": frequency domain [B, sources, 4, F, Frames]
+    // "add_67": time domain [B, sources, 2, T]
     let mut output_freq: Option<Value> = None;
     let mut output_time: Option<Value> = None;
 
@@ -250,12 +709,12 @@ pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
     let out_time =
         output_time.ok_or_else(|| anyhow!("Model did not return 'add_67' (time domain)"))?;
 
-    // Extract time domain output [1, 4, 2, T] -> [4, 2, T]
-    let (shape_time, data_time) = out_time.try_extract_tensor::<f32>()?;
+    // Extract time domain output [B, 4, 2, T]
+    let (shape_time, data_time) = extract_f32(&out_time)?;
     let num_sources = shape_time[1] as usize;
 
-    // Extract frequency domain output [1, sources, 4, F, Frames]
-    let (shape_freq, data_freq) = out_freq.try_extract_tensor::<f32>()?;
+    // Extract frequency domain output [B, sources, 4, F, Frames]
+    let (shape_freq, data_freq) = extract_f32(&out_freq)?;
 
     // Debug: Check if model outputs are non-zero
     if std::env::var("DEBUG_STEMS").is_ok() {
@@ -268,59 +727,164 @@ pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
     }
 
     // Validate shapes
-    if shape_freq[0] != 1
+    if shape_time[0] != batch as i64
+        || shape_freq[0] != batch as i64
         || shape_freq[1] != num_sources as i64
         || shape_freq[2] != 4
-        || shape_freq[3] != f_bins as i64
-        || shape_freq[4] != frames as i64
+        || shape_freq[3] != DEMUCS_F as i64
+        || shape_freq[4] != DEMUCS_FRAMES as i64
     {
         return Err(anyhow!(
-            "Unexpected freq output shape: {:?}, expected [1, {}, 4, {}, {}]",
+            "Unexpected freq output shape: {:?}, expected [{}, {}, 4, {}, {}]",
             shape_freq,
+            batch,
             num_sources,
-            f_bins,
-            frames
+            DEMUCS_F,
+            DEMUCS_FRAMES
         )
         .into());
     }
 
-    let source_specs: Vec<&[f32]> = (0..num_sources)
-        .map(|src| {
-            let src_freq_offset = src * 4 * f_bins * frames;
-            &data_freq[src_freq_offset..src_freq_offset + 4 * f_bins * frames]
-        })
-        .collect();
+    Ok(RawOutputs {
+        batch,
+        t,
+        sources: num_sources,
+        time: data_time,
+        freq: data_freq,
+    })
+}
+
+/// Element type of a model input: as declared in the manifest, otherwise
+/// as reported by the session.
+#[cfg(not(feature = "engine-mock"))]
+fn input_dtype(session: &Session, name: &str) -> Result<TensorDType> {
+    let declared = MANIFEST.get().map_or(&[][..], |m| &m.inputs[..]);
+    if let Some(dtype) = declared_dtype(declared, name)? {
+        return Ok(dtype);
+    }
+    let ty = session
+        .inputs
+        .iter()
+        .find(|i| i.name == name)
+        .and_then(|i| i.input_type.tensor_type());
+    Ok(match ty {
+        Some(TensorElementType::Float16) => TensorDType::Float16,
+        _ => TensorDType::Float32,
+    })
+}
 
-    let istft_results = istft_cac_stereo_parallel(&source_specs, f_bins, frames, DEMUCS_NFFT, DEMUCS_HOP, t);
+fn declared_dtype(descs: &[IODesc], name: &str) -> Result<Option<TensorDType>> {
+    match descs.iter().find(|d| d.name == name) {
+        Some(desc) => desc.tensor_dtype().map_err(StemError::Manifest),
+        None => Ok(None),
+    }
+}
 
-    // Debug: Check iSTFT results
-    if std::env::var("DEBUG_STEMS").is_ok() {
-        for (src_idx, (left, right)) in istft_results.iter().enumerate() {
-            let left_max = left.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
-            let right_max = right.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
-            eprintln!("iSTFT result [source {}]: left_max={:.6}, right_max={:.6}", src_idx, left_max, right_max);
+/// Check the manifest's tensor types are supported by this build.
+fn check_manifest_dtypes(manifest: &ModelManifest) -> Result<()> {
+    for desc in manifest.inputs.iter().chain(&manifest.outputs) {
+        let dtype = declared_dtype(std::slice::from_ref(desc), &desc.name)?;
+        if dtype == Some(TensorDType::Float16) && !cfg!(feature = "half") {
+            return Err(half_required());
         }
     }
+    Ok(())
+}
 
-    let mut result = Vec::with_capacity(num_sources * 2 * t);
+fn half_required() -> StemError {
+    StemError::Manifest(
+        "model uses float16 tensors; enable the `half` feature of stem-splitter-core".into(),
+    )
+}
 
-    for (src, (left_freq, right_freq)) in istft_results.into_iter().enumerate() {
-        // Extract time domain for this source [2, T]
-        let src_time_offset = src * 2 * t;
-        let left_time = &data_time[src_time_offset..src_time_offset + t];
-        let right_time = &data_time[src_time_offset + t..src_time_offset + 2 * t];
+/// Build an input tensor of the given element type from f32 samples.
+#[cfg(not(feature = "engine-mock"))]
+fn make_tensor(dtype: TensorDType, shape: Vec<usize>, data: &[f32]) -> Result<Value> {
+    match dtype {
+        TensorDType::Float32 => Ok(Tensor::from_array((shape, data.to_vec()))?.into_dyn()),
+        #[cfg(feature = "half")]
+        TensorDType::Float16 => {
+            let data: Vec<half::f16> = data.iter().map(|&x| half::f16::from_f32(x)).collect();
+            Ok(Tensor::from_array((shape, data))?.into_dyn())
+        }
+        #[cfg(not(feature = "half"))]
+        TensorDType::Float16 => Err(half_required()),
+    }
+}
 
-        // Combine: output = time_domain + frequency_domain (after iSTFT)
-        for i in 0..t {
-            result.push(left_time[i] + left_freq[i]);
+/// Read an output tensor as f32, converting from float16 if needed.
+#[cfg(not(feature = "engine-mock"))]
+fn extract_f32(value: &Value) -> Result<(Vec<i64>, Vec<f32>)> {
+    match value.dtype().tensor_type() {
+        #[cfg(feature = "half")]
+        Some(TensorElementType::Float16) => {
+            let (shape, data) = value.try_extract_tensor::<half::f16>()?;
+            Ok((shape.to_vec(), data.iter().map(|x| x.to_f32()).collect()))
         }
-        for i in 0..t {
-            result.push(right_time[i] + right_freq[i]);
+        #[cfg(not(feature = "half"))]
+        Some(TensorElementType::Float16) => Err(half_required()),
+        _ => {
+            let (shape, data) = value.try_extract_tensor::<f32>()?;
+            Ok((shape.to_vec(), data.to_vec()))
         }
     }
+}
+
+/// iSTFT stage: combine both model branches into (sources, 2, T) per window.
+#[cfg(not(feature = "engine-mock"))]
+pub fn finish_windows(raw: RawOutputs) -> Result<Vec<Array3<f32>>> {
+    let RawOutputs { batch, t, sources: num_sources, time: data_time, freq: data_freq } = raw;
+    let (f_bins, frames) = (DEMUCS_F, DEMUCS_FRAMES);
+    let spec_len = 4 * f_bins * frames;
+
+    let mut results = Vec::with_capacity(batch);
+    for b in 0..batch {
+        let source_specs: Vec<&[f32]> = (0..num_sources)
+            .map(|src| {
+                let src_freq_offset = (b * num_sources + src) * spec_len;
+                &data_freq[src_freq_offset..src_freq_offset + spec_len]
+            })
+            .collect();
+
+        let istft_results = istft_cac_stereo_parallel_with(
+            &source_specs,
+            f_bins,
+            frames,
+            DEMUCS_NFFT,
+            DEMUCS_HOP,
+            t,
+            &stft_options(),
+        );
+
+        // Debug: Check iSTFT results
+        if std::env::var("DEBUG_STEMS").is_ok() {
+            for (src_idx, (left, right)) in istft_results.iter().enumerate() {
+                let left_max = left.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
+                let right_max = right.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
+                eprintln!("iSTFT result [source {}]: left_max={:.6}, right_max={:.6}", src_idx, left_max, right_max);
+            }
+        }
 
-    let out = ndarray::Array3::from_shape_vec((num_sources, 2, t), result)?;
-    Ok(out)
+        let mut result = Vec::with_capacity(num_sources * 2 * t);
+
+        for (src, (left_freq, right_freq)) in istft_results.into_iter().enumerate() {
+            // Extract time domain for this source [2, T]
+            let src_time_offset = (b * num_sources + src) * 2 * t;
+            let left_time = &data_time[src_time_offset..src_time_offset + t];
+            let right_time = &data_time[src_time_offset + t..src_time_offset + 2 * t];
+
+            // Combine: output = time_domain + frequency_domain (after iSTFT)
+            for i in 0..t {
+                result.push(left_time[i] + left_freq[i]);
+            }
+            for i in 0..t {
+                result.push(right_time[i] + right_freq[i]);
+            }
+        }
+
+        results.push(ndarray::Array3::from_shape_vec((num_sources, 2, t), result)?);
+    }
+    Ok(results)
 }
 
 #[cfg(feature = "engine-mock")]
@@ -330,28 +894,75 @@ mod _engine_mock {
     static MANIFEST: OnceCell<ModelManifest> = OnceCell::new();
 
     pub fn preload(h: &ModelHandle) -> Result<()> {
+        check_manifest_dtypes(&h.manifest)?;
         MANIFEST.set(h.manifest.clone()).ok();
         Ok(())
     }
 
+    static SESSIONS: OnceCell<usize> = OnceCell::new();
+
+    pub fn preload_with_config(h: &ModelHandle, config: &EngineConfig) -> Result<()> {
+        SESSIONS.set(config.sessions.max(1)).ok();
+        preload(h)
+    }
+
+    pub fn session_count() -> usize {
+        SESSIONS.get().copied().unwrap_or(1)
+    }
+
     pub fn manifest() -> &'static ModelManifest {
         MANIFEST.get().expect("preload first (mock)")
     }
 
-    pub fn run_window_demucs(left: &[f32], right: &[f32]) -> Result<Array3<f32>> {
-        let t = left.len().min(right.len());
+    pub fn prepare_windows(windows: &[(&[f32], &[f32])]) -> Result<PreparedWindows> {
+        let t = windows
+            .iter()
+            .map(|(l, r)| l.len().min(r.len()))
+            .min()
+            .ok_or_else(|| anyhow!("No windows to process"))?;
+        let mut time = Vec::with_capacity(windows.len() * 2 * t);
+        for (left, right) in windows {
+            time.extend_from_slice(&left[..t]);
+            time.extend_from_slice(&right[..t]);
+        }
+        Ok(PreparedWindows {
+            batch: windows.len(),
+            t,
+            time,
+            spec: Vec::new(),
+        })
+    }
+
+    pub fn infer(p: PreparedWindows) -> Result<RawOutputs> {
         let sources = 4usize;
-        let mut out = vec![0.0f32; sources * 2 * t];
-        for s in 0..sources {
-            for i in 0..t {
+        let mut time = Vec::with_capacity(p.batch * sources * 2 * p.t);
+        for window in p.time.chunks_exact(2 * p.t) {
+            for _ in 0..sources {
                 // “identity” stems: copy input
-                out[s * 2 * t + i] = left[i]; // L
-                out[s * 2 * t + t + i] = right[i]; // R
+                time.extend_from_slice(window);
             }
         }
-        Ok(ndarray::Array3::from_shape_vec((sources, 2, t), out)?)
+        Ok(RawOutputs {
+            batch: p.batch,
+            t: p.t,
+            sources,
+            time,
+            freq: Vec::new(),
+        })
+    }
+
+    pub fn finish_windows(raw: RawOutputs) -> Result<Vec<Array3<f32>>> {
+        let len = raw.sources * 2 * raw.t;
+        (0..raw.batch)
+            .map(|b| {
+                let data = raw.time[b * len..(b + 1) * len].to_vec();
+                Ok(ndarray::Array3::from_shape_vec((raw.sources, 2, raw.t), data)?)
+            })
+            .collect()
     }
 }
 
 #[cfg(feature = "engine-mock")]
-pub use _engine_mock::{manifest, preload, run_window_demucs};
+pub use _engine_mock::{
+    finish_windows, infer, manifest, preload, preload_with_config, prepare_windows, session_count,
+};
@@ -0,0 +1,82 @@
+//! Multitrack exports: a single multichannel WAV and Reaper (`.RPP`) projects.
+//!
+//! # Multichannel WAV channel order
+//!
+//! [`SeparatedStems::save_multitrack`](crate::SeparatedStems::save_multitrack)
+//! writes an 8-channel WAV with one stereo pair per stem, in [`Stem::all`]
+//! order:
+//!
+//! | Channels | Stem   |
+//! |----------|--------|
+//! | 1, 2     | vocals (L, R) |
+//! | 3, 4     | drums (L, R)  |
+//! | 5, 6     | bass (L, R)   |
+//! | 7, 8     | other (L, R)  |
+//!
+//! The header's channel mask is 0, so players don't map the pairs to
+//! surround speakers.
+
+use crate::core::splitter::Stem;
+use std::fmt::Write;
+
+/// A track in a Reaper project: one media item referencing an audio file.
+#[derive(Clone, Debug)]
+pub struct ReaperTrack {
+    pub name: String,
+    /// Audio file path, absolute or relative to the project file.
+    pub file: String,
+    /// Track color as RGB.
+    pub color: [u8; 3],
+    /// Item position on the timeline, in seconds.
+    pub position: f64,
+    /// Item length in seconds.
+    pub length: f64,
+}
+
+/// Default track color for a stem.
+pub fn stem_color(stem: Stem) -> [u8; 3] {
+    match stem {
+        Stem::Vocals => [230, 80, 80],
+        Stem::Drums => [80, 140, 230],
+        Stem::Bass => [90, 200, 110],
+        Stem::Other => [230, 190, 70],
+    }
+}
+
+/// Render a plain-text Reaper project with one track per entry.
+pub fn reaper_project(tracks: &[ReaperTrack], sample_rate: u32) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "<REAPER_PROJECT 0.1 \"6.0\" 0");
+    let _ = writeln!(out, "  SAMPLERATE {sample_rate} 0 0");
+    for track in tracks {
+        let [r, g, b] = track.color;
+        // Native color (0x00BBGGRR) with the "custom color" flag set
+        let peakcol = 0x0100_0000u32 | ((b as u32) << 16) | ((g as u32) << 8) | r as u32;
+        let _ = writeln!(out, "  <TRACK");
+        let _ = writeln!(out, "    NAME {}", rpp_string(&track.name));
+        let _ = writeln!(out, "    PEAKCOL {peakcol}");
+        let _ = writeln!(out, "    <ITEM");
+        let _ = writeln!(out, "      POSITION {:.6}", track.position);
+        let _ = writeln!(out, "      LENGTH {:.6}", track.length);
+        let _ = writeln!(out, "      NAME {}", rpp_string(&track.name));
+        let _ = writeln!(out, "      <SOURCE WAVE");
+        let _ = writeln!(out, "        FILE {}", rpp_string(&track.file));
+        let _ = writeln!(out, "      >");
+        let _ = writeln!(out, "    >");
+        let _ = writeln!(out, "  >");
+    }
+    let _ = writeln!(out, ">");
+    out
+}
+
+/// Quote a string for an RPP file. Reaper has no escape sequences, so the
+/// quote character is chosen to avoid the string's contents.
+fn rpp_string(s: &str) -> String {
+    if !s.contains('"') {
+        format!("\"{s}\"")
+    } else if !s.contains('\'') {
+        format!("'{s}'")
+    } else {
+        format!("`{}`", s.replace('`', "'"))
+    }
+}
@@ -0,0 +1,375 @@
+//! EBU R128 / ITU-R BS.1770-4 loudness measurement.
+//!
+//! Integrated loudness uses 400 ms gating blocks with 75% overlap, an absolute
+//! gate at -70 LUFS and a relative gate 10 LU below the ungated mean. Loudness
+//! range follows EBU Tech 3342 (3 s short-term blocks, -20 LU relative gate,
+//! 10th to 95th percentile). True peak is measured on a 4x oversampled signal.
+
+use serde::{Deserialize, Serialize};
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Measurement step: 100 ms sub-blocks, combined into 400 ms (momentary)
+/// and 3 s (short-term) blocks.
+const STEP_SECONDS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+const TRUE_PEAK_OVERSAMPLE: usize = 4;
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 12;
+
+/// Loudness measurements for a block of audio.
+///
+/// Values are `f64::NEG_INFINITY` when the audio is silent or too short to
+/// produce a gated measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessStats {
+    /// Gated integrated loudness in LUFS.
+    pub integrated_lufs: f64,
+    /// Highest 400 ms momentary loudness in LUFS.
+    pub momentary_max_lufs: f64,
+    /// Highest 3 s short-term loudness in LUFS.
+    pub short_term_max_lufs: f64,
+    /// Loudness range (LRA) in LU.
+    pub loudness_range_lu: f64,
+    /// Maximum true peak (4x oversampled) in dBTP.
+    pub true_peak_dbtp: f64,
+    /// Maximum sample peak in dBFS.
+    pub sample_peak_dbfs: f64,
+}
+
+impl LoudnessStats {
+    /// Stats of the same audio after a linear gain of `gain_db` has been applied.
+    pub fn with_gain(&self, gain_db: f64) -> Self {
+        Self {
+            integrated_lufs: self.integrated_lufs + gain_db,
+            momentary_max_lufs: self.momentary_max_lufs + gain_db,
+            short_term_max_lufs: self.short_term_max_lufs + gain_db,
+            loudness_range_lu: self.loudness_range_lu,
+            true_peak_dbtp: self.true_peak_dbtp + gain_db,
+            sample_peak_dbfs: self.sample_peak_dbfs + gain_db,
+        }
+    }
+}
+
+/// Loudness normalization target applied to each stem before it is written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalizeOptions {
+    /// Target integrated loudness in LUFS (-23 for EBU R128 broadcast,
+    /// -14 is common for streaming).
+    pub target_lufs: f64,
+    /// Optional true-peak ceiling in dBTP. If reaching `target_lufs` would
+    /// push the true peak above it, the gain is reduced instead (no limiting).
+    #[serde(default)]
+    pub max_true_peak_dbtp: Option<f64>,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            target_lufs: -23.0,
+            max_true_peak_dbtp: Some(-1.0),
+        }
+    }
+}
+
+/// Gain in dB that brings audio with `stats` to the normalization target.
+/// Silent audio (no gated blocks) is left untouched.
+pub fn normalization_gain_db(stats: &LoudnessStats, opts: &NormalizeOptions) -> f64 {
+    if !stats.integrated_lufs.is_finite() {
+        return 0.0;
+    }
+    let mut gain = opts.target_lufs - stats.integrated_lufs;
+    if let Some(ceiling) = opts.max_true_peak_dbtp {
+        if stats.true_peak_dbtp.is_finite() {
+            gain = gain.min(ceiling - stats.true_peak_dbtp);
+        }
+    }
+    gain
+}
+
+/// Multiply interleaved samples by `gain_db`.
+pub fn apply_gain_db(samples: &mut [f32], gain_db: f64) {
+    if gain_db == 0.0 {
+        return;
+    }
+    let g = db_to_linear(gain_db) as f32;
+    for s in samples.iter_mut() {
+        *s *= g;
+    }
+}
+
+/// Measure loudness of interleaved samples.
+pub fn measure_loudness(samples: &[f32], channels: u16, sample_rate: u32) -> LoudnessStats {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+
+    let step = ((sample_rate as f64) * STEP_SECONDS).round() as usize;
+    let step_energy = if step == 0 {
+        Vec::new()
+    } else {
+        k_weighted_step_energy(samples, channels, frames, sample_rate, step)
+    };
+
+    let momentary = block_loudness(&step_energy, MOMENTARY_STEPS);
+    let short_term = block_loudness(&step_energy, SHORT_TERM_STEPS);
+
+    let (sample_peak, true_peak) = peaks(samples, channels, frames);
+
+    LoudnessStats {
+        integrated_lufs: integrated(&momentary),
+        momentary_max_lufs: momentary
+            .iter()
+            .map(|&(l, _)| l)
+            .fold(f64::NEG_INFINITY, f64::max),
+        short_term_max_lufs: short_term
+            .iter()
+            .map(|&(l, _)| l)
+            .fold(f64::NEG_INFINITY, f64::max),
+        loudness_range_lu: loudness_range(&short_term),
+        true_peak_dbtp: linear_to_db(true_peak),
+        sample_peak_dbfs: linear_to_db(sample_peak),
+    }
+}
+
+fn db_to_linear(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+fn linear_to_db(v: f64) -> f64 {
+    if v > 0.0 {
+        20.0 * v.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+fn energy_to_lufs(z: f64) -> f64 {
+    if z > 0.0 {
+        -0.691 + 10.0 * z.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+/// Direct form I biquad.
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x1 + self.b[2] * self.x2
+            - self.a[1] * self.y1
+            - self.a[2] * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// K-weighting pre-filter (high shelf) and RLB high-pass, derived for any
+/// sample rate from the BS.1770 analog prototypes.
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+    let fs = sample_rate as f64;
+
+    let f0 = 1_681.974_450_955_533;
+    let g = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(g / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    (shelf, highpass)
+}
+
+/// Channel-summed mean square of the K-weighted signal for each 100 ms step.
+/// All channels are weighted 1.0 (mono, stereo and front channels).
+fn k_weighted_step_energy(
+    samples: &[f32],
+    channels: usize,
+    frames: usize,
+    sample_rate: u32,
+    step: usize,
+) -> Vec<f64> {
+    let steps = frames / step;
+    let mut energy = vec![0.0f64; steps];
+
+    for ch in 0..channels {
+        let (mut shelf, mut highpass) = k_weighting(sample_rate);
+        for (st, e) in energy.iter_mut().enumerate() {
+            let mut sum = 0.0f64;
+            for i in st * step..(st + 1) * step {
+                let y = highpass.process(shelf.process(samples[i * channels + ch] as f64));
+                sum += y * y;
+            }
+            *e += sum / step as f64;
+        }
+    }
+
+    energy
+}
+
+/// Sliding blocks of `len` steps advancing one step at a time.
+/// Returns (loudness, mean energy) per block.
+fn block_loudness(step_energy: &[f64], len: usize) -> Vec<(f64, f64)> {
+    if step_energy.len() < len {
+        return Vec::new();
+    }
+    step_energy
+        .windows(len)
+        .map(|w| {
+            let z = w.iter().sum::<f64>() / len as f64;
+            (energy_to_lufs(z), z)
+        })
+        .collect()
+}
+
+fn integrated(blocks: &[(f64, f64)]) -> f64 {
+    let above_abs: Vec<f64> = blocks
+        .iter()
+        .filter(|&&(l, _)| l > ABSOLUTE_GATE_LUFS)
+        .map(|&(_, z)| z)
+        .collect();
+    if above_abs.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let relative_gate =
+        energy_to_lufs(above_abs.iter().sum::<f64>() / above_abs.len() as f64) + RELATIVE_GATE_LU;
+
+    let gated: Vec<f64> = blocks
+        .iter()
+        .filter(|&&(l, _)| l > ABSOLUTE_GATE_LUFS && l > relative_gate)
+        .map(|&(_, z)| z)
+        .collect();
+    if gated.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64)
+}
+
+fn loudness_range(short_term: &[(f64, f64)]) -> f64 {
+    let above_abs: Vec<(f64, f64)> = short_term
+        .iter()
+        .copied()
+        .filter(|&(l, _)| l > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_abs.is_empty() {
+        return 0.0;
+    }
+    let relative_gate = energy_to_lufs(
+        above_abs.iter().map(|&(_, z)| z).sum::<f64>() / above_abs.len() as f64,
+    ) + LRA_RELATIVE_GATE_LU;
+
+    let mut gated: Vec<f64> = above_abs
+        .into_iter()
+        .map(|(l, _)| l)
+        .filter(|&l| l > relative_gate)
+        .collect();
+    if gated.is_empty() {
+        return 0.0;
+    }
+    gated.sort_by(|a, b| a.total_cmp(b));
+
+    let percentile = |p: f64| -> f64 {
+        let idx = ((gated.len() - 1) as f64 * p).round() as usize;
+        gated[idx]
+    };
+    percentile(0.95) - percentile(0.10)
+}
+
+/// Windowed-sinc interpolation filter for 4x oversampling, split into
+/// polyphase components: `phases[p][k]` weights input sample `x[n - k]`
+/// for output position `n + p / 4` (relative to the filter delay).
+fn true_peak_filter() -> Vec<Vec<f64>> {
+    let len = TRUE_PEAK_OVERSAMPLE * TRUE_PEAK_TAPS_PER_PHASE;
+    let center = (len - 1) as f64 / 2.0;
+    let h: Vec<f64> = (0..len)
+        .map(|i| {
+            let x = (i as f64 - center) / TRUE_PEAK_OVERSAMPLE as f64;
+            let sinc = if x.abs() < 1e-12 {
+                1.0
+            } else {
+                (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+            };
+            let hann = 0.5
+                - 0.5 * (2.0 * std::f64::consts::PI * (i as f64 + 0.5) / len as f64).cos();
+            sinc * hann
+        })
+        .collect();
+
+    (0..TRUE_PEAK_OVERSAMPLE)
+        .map(|p| {
+            (0..TRUE_PEAK_TAPS_PER_PHASE)
+                .map(|k| h[k * TRUE_PEAK_OVERSAMPLE + p])
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns (sample peak, true peak) as linear amplitudes.
+fn peaks(samples: &[f32], channels: usize, frames: usize) -> (f64, f64) {
+    let phases = true_peak_filter();
+    let taps = TRUE_PEAK_TAPS_PER_PHASE;
+
+    let mut sample_peak = 0.0f64;
+    let mut true_peak = 0.0f64;
+
+    for ch in 0..channels {
+        let mut history = vec![0.0f64; taps];
+        let mut head = 0usize;
+        // Run `taps` extra zero samples through so the tail is interpolated too.
+        for i in 0..frames + taps {
+            let x = if i < frames {
+                samples[i * channels + ch] as f64
+            } else {
+                0.0
+            };
+            sample_peak = sample_peak.max(x.abs());
+            true_peak = true_peak.max(x.abs());
+
+            head = (head + taps - 1) % taps;
+            history[head] = x;
+
+            for phase in &phases {
+                let mut acc = 0.0f64;
+                for (k, c) in phase.iter().enumerate() {
+                    acc += c * history[(head + k) % taps];
+                }
+                true_peak = true_peak.max(acc.abs());
+            }
+        }
+    }
+
+    (sample_peak, true_peak)
+}
@@ -0,0 +1,238 @@
+//! Track metadata (tags and cover art) carried from the input file to the
+//! written stems.
+//!
+//! Tags are read from whatever symphonia parses (ID3v2, Vorbis comments,
+//! RIFF INFO, ...). WAV outputs get a `LIST/INFO` chunk and an ID3v2.3
+//! `id3 ` chunk (the latter also carries the cover art), both placed before
+//! the `data` chunk so streaming readers see them.
+
+use anyhow::{bail, Result};
+use symphonia::core::meta::{MetadataRevision, StandardTagKey, StandardVisualKey};
+
+/// Embedded picture, typically the front cover.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Picture {
+    /// MIME type, e.g. `image/jpeg`.
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Common tags and cover art of an audio file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AudioMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub track_number: Option<String>,
+    pub comment: Option<String>,
+    pub copyright: Option<String>,
+    pub cover: Option<Picture>,
+}
+
+impl AudioMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Metadata for a stem file: the title becomes `"{title} ({label})"`.
+    pub fn for_stem(&self, label: &str) -> Self {
+        let title = match &self.title {
+            Some(title) => format!("{title} ({label})"),
+            None => label.to_string(),
+        };
+        Self {
+            title: Some(title),
+            ..self.clone()
+        }
+    }
+
+    /// Merge tags from a symphonia metadata revision. Values already set
+    /// are kept, so earlier (container) revisions take precedence.
+    pub(crate) fn merge_revision(&mut self, rev: &MetadataRevision) {
+        for tag in rev.tags() {
+            let slot = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::AlbumArtist) => &mut self.album_artist,
+                Some(StandardTagKey::Genre) => &mut self.genre,
+                Some(StandardTagKey::Date) | Some(StandardTagKey::ReleaseDate) => &mut self.date,
+                Some(StandardTagKey::TrackNumber) => &mut self.track_number,
+                Some(StandardTagKey::Comment) => &mut self.comment,
+                Some(StandardTagKey::Copyright) => &mut self.copyright,
+                _ => continue,
+            };
+            // RIFF INFO strings come back with their NUL terminator
+            let value = tag.value.to_string();
+            let value = value.trim_end_matches('\0');
+            if slot.is_none() && !value.trim().is_empty() {
+                *slot = Some(value.to_string());
+            }
+        }
+
+        if self.cover.is_none() {
+            let visuals = rev.visuals();
+            let front = visuals
+                .iter()
+                .find(|v| v.usage == Some(StandardVisualKey::FrontCover))
+                .or_else(|| visuals.first());
+            if let Some(v) = front {
+                self.cover = Some(Picture {
+                    media_type: v.media_type.clone(),
+                    data: v.data.to_vec(),
+                });
+            }
+        }
+    }
+
+    fn text_fields(&self) -> [(&'static [u8; 4], &'static [u8; 4], Option<&str>); 9] {
+        // (RIFF INFO id, ID3v2.3 frame id, value)
+        [
+            (b"INAM", b"TIT2", self.title.as_deref()),
+            (b"IART", b"TPE1", self.artist.as_deref()),
+            (b"IPRD", b"TALB", self.album.as_deref()),
+            (b"IAAR", b"TPE2", self.album_artist.as_deref()),
+            (b"IGNR", b"TCON", self.genre.as_deref()),
+            (b"ICRD", b"TYER", self.date.as_deref()),
+            (b"ITRK", b"TRCK", self.track_number.as_deref()),
+            (b"ICMT", b"COMM", self.comment.as_deref()),
+            (b"ICOP", b"TCOP", self.copyright.as_deref()),
+        ]
+    }
+}
+
+/// Insert INFO and ID3 chunks into an in-memory WAV file, before `data`.
+pub(crate) fn insert_wav_chunks(wav: Vec<u8>, meta: &AudioMetadata) -> Result<Vec<u8>> {
+    if wav.len() < 12 || &wav[0..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+        bail!("not a RIFF/WAVE file");
+    }
+
+    let mut pos = 12usize;
+    let data_pos = loop {
+        if pos + 8 > wav.len() {
+            bail!("WAV has no data chunk");
+        }
+        if &wav[pos..pos + 4] == b"data" {
+            break pos;
+        }
+        let len = u32::from_le_bytes(wav[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        pos += 8 + len + (len & 1);
+    };
+
+    let mut chunks = Vec::new();
+    write_chunk(&mut chunks, b"LIST", &info_list(meta));
+    write_chunk(&mut chunks, b"id3 ", &id3v23(meta));
+
+    let mut out = Vec::with_capacity(wav.len() + chunks.len());
+    out.extend_from_slice(&wav[..data_pos]);
+    out.extend_from_slice(&chunks);
+    out.extend_from_slice(&wav[data_pos..]);
+
+    let riff_len = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    Ok(out)
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn info_list(meta: &AudioMetadata) -> Vec<u8> {
+    let mut body = b"INFO".to_vec();
+    for (info_id, _, value) in meta.text_fields() {
+        if let Some(value) = value {
+            let mut text = value.as_bytes().to_vec();
+            text.push(0);
+            write_chunk(&mut body, info_id, &text);
+        }
+    }
+    body
+}
+
+/// ID3v2.3 tag with UTF-16 text frames and an APIC frame for the cover.
+fn id3v23(meta: &AudioMetadata) -> Vec<u8> {
+    let mut frames = Vec::new();
+
+    for (_, frame_id, value) in meta.text_fields() {
+        let Some(value) = value else { continue };
+        let mut body = Vec::new();
+        match frame_id {
+            b"COMM" => {
+                body.push(1); // UTF-16 with BOM
+                body.extend_from_slice(b"eng");
+                body.extend_from_slice(&utf16_with_bom(""));
+                body.extend_from_slice(&utf16_with_bom(value));
+            }
+            b"TYER" => {
+                // TYER is exactly four digits in ID3v2.3
+                let year: String = value.chars().take(4).collect();
+                if year.len() != 4 || !year.chars().all(|c| c.is_ascii_digit()) {
+                    continue;
+                }
+                body.push(0);
+                body.extend_from_slice(year.as_bytes());
+            }
+            _ => {
+                body.push(1);
+                body.extend_from_slice(&utf16_with_bom(value));
+            }
+        }
+        write_id3_frame(&mut frames, frame_id, &body);
+    }
+
+    if let Some(cover) = &meta.cover {
+        let mut body = vec![0u8]; // ISO-8859-1 description
+        body.extend_from_slice(cover.media_type.as_bytes());
+        body.push(0);
+        body.push(3); // front cover
+        body.push(0); // empty description
+        body.extend_from_slice(&cover.data);
+        write_id3_frame(&mut frames, b"APIC", &body);
+    }
+
+    // Pad to an even size so the RIFF chunk needs no pad byte; some readers
+    // skip unknown chunks without honoring it.
+    if frames.len() % 2 == 1 {
+        frames.push(0);
+    }
+
+    let mut tag = b"ID3".to_vec();
+    tag.extend_from_slice(&[3, 0, 0]);
+    tag.extend_from_slice(&syncsafe(frames.len() as u32));
+    tag.extend_from_slice(&frames);
+    tag
+}
+
+fn write_id3_frame(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(body);
+}
+
+/// Zero-terminated UTF-16LE string with byte order mark.
+fn utf16_with_bom(s: &str) -> Vec<u8> {
+    let mut out = vec![0xFF, 0xFE];
+    for unit in s.encode_utf16() {
+        out.extend_from_slice(&unit.to_le_bytes());
+    }
+    out.extend_from_slice(&[0, 0]);
+    out
+}
+
+fn syncsafe(n: u32) -> [u8; 4] {
+    [
+        ((n >> 21) & 0x7F) as u8,
+        ((n >> 14) & 0x7F) as u8,
+        ((n >> 7) & 0x7F) as u8,
+        (n & 0x7F) as u8,
+    ]
+}
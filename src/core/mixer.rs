@@ -0,0 +1,224 @@
+//! Stem mixing with per-stem gain, pan, mute/solo and an output limiter.
+
+use crate::core::splitter::Stem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Mix settings for a single stem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StemMix {
+    /// Gain in dB (0.0 = unity).
+    pub gain_db: f32,
+    /// Pan position from -1.0 (hard left) to 1.0 (hard right).
+    pub pan: f32,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl Default for StemMix {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            pan: 0.0,
+            mute: false,
+            solo: false,
+        }
+    }
+}
+
+/// Brickwall peak limiter applied to the rendered mix.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimiterSpec {
+    /// Output ceiling in dBFS.
+    pub ceiling_db: f32,
+    /// Time over which gain reduction ramps in ahead of a peak.
+    pub lookahead_ms: f32,
+    /// Time constant for gain recovery after a peak.
+    pub release_ms: f32,
+}
+
+impl Default for LimiterSpec {
+    fn default() -> Self {
+        Self {
+            ceiling_db: -1.0,
+            lookahead_ms: 5.0,
+            release_ms: 100.0,
+        }
+    }
+}
+
+/// Description of a stem mix. Serializable so mixes can be stored as presets.
+///
+/// Stems without an entry in `stems` play at unity gain, centered.
+/// If any stem is soloed, only soloed (and unmuted) stems are audible.
+///
+/// # Example
+/// ```
+/// use stem_splitter_core::{MixSpec, Stem};
+///
+/// // Karaoke with a quiet guide vocal
+/// let spec = MixSpec::default().gain(Stem::Vocals, -12.0);
+/// let json = serde_json::to_string(&spec).unwrap();
+/// assert!(json.contains("vocals"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixSpec {
+    pub stems: HashMap<Stem, StemMix>,
+    /// Gain in dB applied to the summed mix before the limiter.
+    pub master_gain_db: f32,
+    pub limiter: Option<LimiterSpec>,
+}
+
+impl MixSpec {
+    /// Mix containing only the given stems.
+    pub fn only(stems: &[Stem]) -> Self {
+        let mut spec = Self::default();
+        for &stem in Stem::all() {
+            if !stems.contains(&stem) {
+                spec = spec.mute(stem);
+            }
+        }
+        spec
+    }
+
+    /// Mix containing every stem except the given ones.
+    pub fn except(exclude: &[Stem]) -> Self {
+        let mut spec = Self::default();
+        for &stem in exclude {
+            spec = spec.mute(stem);
+        }
+        spec
+    }
+
+    /// Set a stem's gain in dB.
+    pub fn gain(mut self, stem: Stem, gain_db: f32) -> Self {
+        self.stems.entry(stem).or_default().gain_db = gain_db;
+        self
+    }
+
+    /// Set a stem's pan position (-1.0 left .. 1.0 right).
+    pub fn pan(mut self, stem: Stem, pan: f32) -> Self {
+        self.stems.entry(stem).or_default().pan = pan;
+        self
+    }
+
+    /// Mute a stem.
+    pub fn mute(mut self, stem: Stem) -> Self {
+        self.stems.entry(stem).or_default().mute = true;
+        self
+    }
+
+    /// Solo a stem.
+    pub fn solo(mut self, stem: Stem) -> Self {
+        self.stems.entry(stem).or_default().solo = true;
+        self
+    }
+
+    /// Enable the output limiter.
+    pub fn with_limiter(mut self, limiter: LimiterSpec) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Linear [left, right] gains for a stem, including master gain,
+    /// or `None` if the stem is muted or not soloed.
+    ///
+    /// Panning uses a constant-power (sin/cos) law scaled so the center
+    /// position is unity gain on both channels.
+    pub fn stem_gains(&self, stem: Stem) -> Option<[f32; 2]> {
+        let default = StemMix::default();
+        let mix = self.stems.get(&stem).unwrap_or(&default);
+        let any_solo = self.stems.values().any(|m| m.solo);
+        if mix.mute || (any_solo && !mix.solo) {
+            return None;
+        }
+
+        let gain = db_to_linear(mix.gain_db + self.master_gain_db);
+        let theta = (mix.pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        let sqrt2 = std::f32::consts::SQRT_2;
+        Some([gain * sqrt2 * theta.cos(), gain * sqrt2 * theta.sin()])
+    }
+}
+
+/// Render stems into an interleaved stereo buffer according to `spec`.
+pub(crate) fn render<'a>(
+    sources: impl IntoIterator<Item = (Stem, &'a [[f32; 2]])>,
+    num_samples: usize,
+    sample_rate: u32,
+    spec: &MixSpec,
+) -> Vec<f32> {
+    let mut out = vec![0.0f32; num_samples * 2];
+    for (stem, data) in sources {
+        let Some([gl, gr]) = spec.stem_gains(stem) else {
+            continue;
+        };
+        for (frame, s) in out.chunks_exact_mut(2).zip(data) {
+            frame[0] += s[0] * gl;
+            frame[1] += s[1] * gr;
+        }
+    }
+    if let Some(limiter) = &spec.limiter {
+        apply_limiter(&mut out, 2, sample_rate, limiter);
+    }
+    out
+}
+
+/// Limit interleaved samples so no sample exceeds the ceiling.
+///
+/// Gain reduction ramps in linearly over the lookahead before each peak and
+/// recovers exponentially with the release time. Processing is offline, so
+/// the lookahead adds no latency.
+pub fn apply_limiter(samples: &mut [f32], channels: u16, sample_rate: u32, spec: &LimiterSpec) {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return;
+    }
+    let ceiling = db_to_linear(spec.ceiling_db);
+
+    // Gain required at each frame to keep its peak under the ceiling.
+    let mut gain: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            if peak > ceiling {
+                ceiling / peak
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    // Backward pass: limit how fast gain may rise going backwards in time,
+    // which makes the reduction ramp in ahead of the peak.
+    let lookahead = ((spec.lookahead_ms / 1000.0) * sample_rate as f32).max(1.0);
+    let attack_step = 1.0 / lookahead;
+    for i in (0..frames - 1).rev() {
+        gain[i] = gain[i].min(gain[i + 1] + attack_step);
+    }
+
+    // Forward pass: exponential release.
+    let release_samples = (spec.release_ms / 1000.0) * sample_rate as f32;
+    let release = if release_samples > 0.0 {
+        1.0 - (-1.0 / release_samples).exp()
+    } else {
+        1.0
+    };
+    for i in 1..frames {
+        let recovered = gain[i - 1] + (1.0 - gain[i - 1]) * release;
+        gain[i] = gain[i].min(recovered);
+    }
+
+    for (frame, g) in samples.chunks_exact_mut(channels).zip(gain) {
+        for s in frame.iter_mut() {
+            *s *= g;
+        }
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
@@ -1,21 +1,42 @@
 use crate::{
     core::{
-        audio::{read_audio, write_audio},
+        audio::{
+            read_audio_range, slice_audio_range, write_audio, write_audio_to,
+            write_audio_with_metadata, DecodedRange,
+        },
         dsp::to_planar_stereo,
         engine,
+        export::{reaper_project, stem_color, ReaperTrack},
+        loudness::{LoudnessStats, NormalizeOptions},
+        metadata::AudioMetadata,
+        mixer::{self, MixSpec},
+        test_engine::{self, TestEngine},
     },
     error::Result,
-    io::progress::{emit_split_progress, SplitProgress},
-    model::model_manager::{ensure_model, load_model_from_path},
-    types::{AudioData, SplitOptions, SplitResult},
+    io::{
+        checkpoint::Checkpoint,
+        output::{self, OverwritePolicy},
+        paths::models_dir,
+        progress::{emit_split_progress, PipelineTiming, SplitProgress},
+        result_cache::{self, CachedStems, ResultCache},
+    },
+    model::model_manager::{ensure_model_in, load_model_from_path_with_manifest, ModelHandle},
+    types::{AudioData, CancelToken, ModelManifest, SplitOptions, SplitResult},
 };
 
+use ndarray::Array3;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::sync_channel,
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
-use tempfile::tempdir;
 
 /// Result for vocal removal operation
 #[derive(Clone, Debug)]
@@ -24,10 +45,23 @@ pub struct VocalRemovalResult {
     pub instrumental_path: String,
     /// Path to isolated vocals track
     pub vocals_path: String,
+    /// Loudness of the written tracks keyed by "instrumental" / "vocals".
+    /// Empty unless `measure_loudness` or `normalize` was set.
+    pub loudness: HashMap<String, LoudnessStats>,
+}
+
+/// Files written by [`SeparatedStems::export_reaper_project`]
+#[derive(Clone, Debug)]
+pub struct ReaperExport {
+    /// Path to the `.RPP` project file
+    pub project_path: String,
+    /// Stem WAVs referenced by the project, in track order
+    pub stem_paths: Vec<(Stem, String)>,
 }
 
 /// Available stem types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Stem {
     Vocals,
     Drums,
@@ -50,6 +84,16 @@ impl Stem {
             Stem::Other => "other",
         }
     }
+
+    /// Get stem name for display, e.g. in track titles
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Stem::Vocals => "Vocals",
+            Stem::Drums => "Drums",
+            Stem::Bass => "Bass",
+            Stem::Other => "Other",
+        }
+    }
 }
 
 /// Separated audio stems - the result of audio separation.
@@ -88,9 +132,20 @@ pub struct SeparatedStems {
     pub sample_rate: u32,
     /// Number of samples per channel
     pub num_samples: usize,
+    /// Position of the first sample within the input file, in frames.
+    /// Non-zero when only a range of the input was separated.
+    pub start_sample: u64,
+    /// Tags and cover art of the input file. Written to stems saved with
+    /// [`SeparatedStems::save`], with the stem name appended to the title.
+    pub metadata: AudioMetadata,
 }
 
 impl SeparatedStems {
+    /// Start time of the stems within the input file, in seconds
+    pub fn start_seconds(&self) -> f64 {
+        self.start_sample as f64 / self.sample_rate as f64
+    }
+
     /// Get a single stem's audio data as interleaved stereo samples
     pub fn get(&self, stem: Stem) -> Vec<f32> {
         self.stems
@@ -106,6 +161,12 @@ impl SeparatedStems {
             .unwrap_or_default()
     }
 
+    /// Stereo frames of a stem, without copying.
+    #[cfg(feature = "capi")]
+    pub(crate) fn frames(&self, stem: Stem) -> &[[f32; 2]] {
+        self.stems.get(&stem).map_or(&[], |data| data.as_slice())
+    }
+
     /// Get a single stem as AudioData
     pub fn get_audio(&self, stem: Stem) -> AudioData {
         AudioData {
@@ -157,10 +218,73 @@ impl SeparatedStems {
         }
     }
 
-    /// Save a single stem to a WAV file
+    /// Render a mix with per-stem gain, pan, mute/solo and optional limiter
+    pub fn render_mix(&self, spec: &MixSpec) -> AudioData {
+        let sources = Stem::all()
+            .iter()
+            .filter_map(|&stem| self.stems.get(&stem).map(|d| (stem, d.as_slice())));
+        AudioData {
+            samples: mixer::render(sources, self.num_samples, self.sample_rate, spec),
+            sample_rate: self.sample_rate,
+            channels: 2,
+        }
+    }
+
+    /// Interleave all stems into one 8-channel buffer, one stereo pair per
+    /// stem in [`Stem::all`] order (see [`crate::core::export`])
+    pub fn multitrack_audio(&self) -> AudioData {
+        let stems = Stem::all();
+        let channels = stems.len() * 2;
+        let mut samples = vec![0.0f32; self.num_samples * channels];
+        for (k, stem) in stems.iter().enumerate() {
+            if let Some(data) = self.stems.get(stem) {
+                for (frame, s) in samples.chunks_exact_mut(channels).zip(data) {
+                    frame[k * 2] = s[0];
+                    frame[k * 2 + 1] = s[1];
+                }
+            }
+        }
+        AudioData {
+            samples,
+            sample_rate: self.sample_rate,
+            channels: channels as u16,
+        }
+    }
+
+    /// Measure EBU R128 loudness and true peak of a single stem
+    pub fn loudness(&self, stem: Stem) -> LoudnessStats {
+        self.get_audio(stem).loudness()
+    }
+
+    /// Measure loudness of every stem
+    pub fn loudness_all(&self) -> HashMap<Stem, LoudnessStats> {
+        Stem::all()
+            .iter()
+            .map(|&stem| (stem, self.loudness(stem)))
+            .collect()
+    }
+
+    /// Normalize every stem independently to the loudness target.
+    /// Returns the gain in dB applied to each stem.
+    pub fn normalize(&mut self, opts: &NormalizeOptions) -> HashMap<Stem, f64> {
+        let mut gains = HashMap::new();
+        for &stem in Stem::all() {
+            let mut audio = self.get_audio(stem);
+            let (_, gain_db) = audio.normalize(opts);
+            if let Some(data) = self.stems.get_mut(&stem) {
+                for (s, lr) in data.iter_mut().zip(audio.samples.chunks_exact(2)) {
+                    *s = [lr[0], lr[1]];
+                }
+            }
+            gains.insert(stem, gain_db);
+        }
+        gains
+    }
+
+    /// Save a single stem to a WAV file, tagged with the input's metadata
     pub fn save(&self, stem: Stem, path: &str) -> Result<()> {
         let audio = self.get_audio(stem);
-        write_audio(path, &audio)?;
+        write_audio_with_metadata(path, &audio, &self.metadata.for_stem(stem.display_name()))?;
         Ok(())
     }
 
@@ -177,6 +301,55 @@ impl SeparatedStems {
         write_audio(path, &audio)?;
         Ok(())
     }
+
+    /// Save all stems to a single multichannel WAV file (see
+    /// [`SeparatedStems::multitrack_audio`] for the channel order)
+    pub fn save_multitrack(&self, path: &str) -> Result<()> {
+        let audio = self.multitrack_audio();
+        write_audio_with_metadata(path, &audio, &self.metadata.for_stem("Stems"))?;
+        Ok(())
+    }
+
+    /// Save every stem as `{name}_{stem}.wav` in `dir` together with a
+    /// Reaper project `{name}.RPP` that has one named, colored track per
+    /// stem. Items are placed at the stems' position in the input, so
+    /// stems of a separated range line up with the original song.
+    pub fn export_reaper_project(&self, dir: &str, name: &str) -> Result<ReaperExport> {
+        fs::create_dir_all(dir)?;
+        let dir = Path::new(dir);
+
+        let mut tracks = Vec::new();
+        let mut stem_paths = Vec::new();
+        for &stem in Stem::all() {
+            let file = format!("{name}_{}.wav", stem.name());
+            let path = dir.join(&file).to_string_lossy().into_owned();
+            self.save(stem, &path)?;
+
+            tracks.push(ReaperTrack {
+                name: stem.display_name().to_string(),
+                file,
+                color: stem_color(stem),
+                position: self.start_seconds(),
+                length: self.num_samples as f64 / self.sample_rate as f64,
+            });
+            stem_paths.push((stem, path));
+        }
+
+        let project_path = dir.join(format!("{name}.RPP"));
+        fs::write(&project_path, reaper_project(&tracks, self.sample_rate))?;
+
+        Ok(ReaperExport {
+            project_path: project_path.to_string_lossy().into_owned(),
+            stem_paths,
+        })
+    }
+
+    /// Render a mix described by `spec` and save it to a WAV file
+    pub fn save_render(&self, spec: &MixSpec, path: &str) -> Result<()> {
+        let audio = self.render_mix(spec);
+        write_audio(path, &audio)?;
+        Ok(())
+    }
 }
 
 /// High-level separator for complete control over audio separation.
@@ -210,7 +383,30 @@ impl Separator {
     /// Returns `SeparatedStems` which provides full control over
     /// accessing, mixing, and saving the separated audio.
     pub fn separate(input_path: &str, opts: SplitOptions) -> Result<SeparatedStems> {
-        let stem_data = separate_stems_internal(input_path, &opts)?;
+        Self::separate_with_model(input_path, opts, None)
+    }
+
+    /// Separate audio that is already in memory, e.g. from an editor's
+    /// buffer. `opts.start` / `opts.end` select a range of it as for files.
+    pub fn separate_audio(audio: &AudioData, opts: SplitOptions) -> Result<SeparatedStems> {
+        Self::separate_input(Input::Audio(audio), opts, None)
+    }
+
+    /// [`Separator::separate`] with an already resolved model.
+    pub(crate) fn separate_with_model(
+        input_path: &str,
+        opts: SplitOptions,
+        model: Option<ModelHandle>,
+    ) -> Result<SeparatedStems> {
+        Self::separate_input(Input::Path(input_path), opts, model)
+    }
+
+    fn separate_input(
+        input: Input<'_>,
+        opts: SplitOptions,
+        model: Option<ModelHandle>,
+    ) -> Result<SeparatedStems> {
+        let stem_data = separate_stems_internal(input, &opts, model)?;
         
         let mut stems = HashMap::new();
         
@@ -232,10 +428,19 @@ impl Separator {
             stems,
             sample_rate: stem_data.sample_rate,
             num_samples: stem_data.n,
+            start_sample: stem_data.start_sample,
+            metadata: stem_data.metadata,
         })
     }
 }
 
+/// Audio to separate: a file, or samples already in memory.
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    Path(&'a str),
+    Audio(&'a AudioData),
+}
+
 /// Internal struct holding separated stem data
 struct StemDataInternal {
     acc: Vec<Vec<[f32; 2]>>,
@@ -243,52 +448,231 @@ struct StemDataInternal {
     name_idx: HashMap<String, usize>,
     sample_rate: u32,
     n: usize,
+    start_sample: u64,
+    metadata: AudioMetadata,
+}
+
+/// Windows in flight between two pipeline stages.
+const PIPELINE_DEPTH: usize = 2;
+
+/// What runs the separation stages: the model loaded by `engine::preload`,
+/// or a scripted [`TestEngine`] from `SplitOptions::test_engine`.
+#[derive(Clone, Copy)]
+enum Backend<'a> {
+    Model,
+    Test { engine: &'a TestEngine, sessions: usize },
+}
+
+enum Prepared {
+    Model(engine::PreparedWindows),
+    Test(test_engine::Batch),
+}
+
+enum Raw {
+    Model(engine::RawOutputs),
+    Test(Vec<Array3<f32>>),
+}
+
+impl Backend<'_> {
+    fn manifest(&self) -> &ModelManifest {
+        match self {
+            Backend::Model => engine::manifest(),
+            Backend::Test { engine, .. } => engine.manifest(),
+        }
+    }
+
+    fn session_count(&self) -> usize {
+        match self {
+            Backend::Model => engine::session_count(),
+            Backend::Test { sessions, .. } => *sessions,
+        }
+    }
+
+    /// `starts` are the windows' offsets in the decoded input.
+    fn prepare(&self, windows: &[(&[f32], &[f32])], starts: &[usize]) -> Result<Prepared> {
+        Ok(match self {
+            Backend::Model => Prepared::Model(engine::prepare_windows(windows)?),
+            Backend::Test { engine, .. } => Prepared::Test(engine.prepare(windows, starts)),
+        })
+    }
+
+    fn infer(&self, prepared: Prepared) -> Result<Raw> {
+        Ok(match (self, prepared) {
+            (Backend::Test { engine, .. }, Prepared::Test(batch)) => Raw::Test(engine.run(batch)?),
+            (_, Prepared::Model(p)) => Raw::Model(engine::infer(p)?),
+            (Backend::Model, Prepared::Test(_)) => unreachable!("test batch on the model backend"),
+        })
+    }
+
+    fn finish(&self, raw: Raw) -> Result<Vec<Array3<f32>>> {
+        match raw {
+            Raw::Model(raw) => engine::finish_windows(raw),
+            Raw::Test(outs) => Ok(outs),
+        }
+    }
 }
 
-/// Process a single chunk of audio through the model
+/// Process a single chunk of audio through the model, `batch_size`
+/// windows per inference run.
+///
+/// Windows flow through a three-stage pipeline connected by bounded
+/// queues: STFT of batch N+1 and iSTFT of batch N-1 run on their own
+/// threads while batch N is in the model. With a session pool, one
+/// inference thread per session runs batches concurrently.
+#[allow(clippy::too_many_arguments)]
 fn process_chunk(
+    backend: Backend<'_>,
     stereo: &[[f32; 2]],
     chunk_start: usize,
     chunk_len: usize,
     win: usize,
     hop: usize,
     stems_count: usize,
+    batch_size: usize,
+    cancel: &CancelToken,
+    timing: &mut PipelineTiming,
 ) -> Result<Vec<Vec<[f32; 2]>>> {
-    let mut left_raw = vec![0f32; win];
-    let mut right_raw = vec![0f32; win];
-    
+    let wall = Instant::now();
     let mut chunk_acc: Vec<Vec<[f32; 2]>> = vec![vec![[0f32; 2]; chunk_len]; stems_count];
-    let mut pos = 0usize;
 
+    // Window offsets within the chunk
+    let mut positions = Vec::new();
+    let mut pos = 0usize;
     while pos < chunk_len {
-        // Fill window buffer
-        for i in 0..win {
-            let global_idx = chunk_start + pos + i;
-            if global_idx < stereo.len() {
-                left_raw[i] = stereo[global_idx][0];
-                right_raw[i] = stereo[global_idx][1];
-            } else {
-                left_raw[i] = 0.0;
-                right_raw[i] = 0.0;
-            }
+        positions.push(pos);
+        if pos + hop >= chunk_len {
+            break;
         }
+        pos += hop;
+    }
+    let groups: Vec<&[usize]> = positions.chunks(batch_size.max(1)).collect();
+
+    let (prep_tx, prep_rx) = sync_channel::<(usize, Prepared)>(PIPELINE_DEPTH);
+    let (raw_tx, raw_rx) = sync_channel::<(usize, Raw)>(PIPELINE_DEPTH);
+    // Shared by the inference threads
+    let prep_rx = Mutex::new(prep_rx);
+    // Set when a later stage fails so the preparer stops producing windows
+    let aborted = AtomicBool::new(false);
+
+    let (prepare_time, infer_time, finish_time) = std::thread::scope(|scope| -> Result<_> {
+        let groups = &groups;
+        let aborted = &aborted;
+
+        let preparer = scope.spawn(move || -> Result<Duration> {
+            let mut busy = Duration::ZERO;
+            for (gi, group) in groups.iter().enumerate() {
+                if aborted.load(Ordering::Relaxed) || cancel.is_cancelled() {
+                    break;
+                }
+                let started = Instant::now();
+                // Fill window buffers, zero-padded past the end of the input
+                let buffers: Vec<(Vec<f32>, Vec<f32>)> = group
+                    .iter()
+                    .map(|&pos| {
+                        let mut left_raw = vec![0f32; win];
+                        let mut right_raw = vec![0f32; win];
+                        for i in 0..win {
+                            let global_idx = chunk_start + pos + i;
+                            if global_idx < stereo.len() {
+                                left_raw[i] = stereo[global_idx][0];
+                                right_raw[i] = stereo[global_idx][1];
+                            }
+                        }
+                        (left_raw, right_raw)
+                    })
+                    .collect();
+                let windows: Vec<(&[f32], &[f32])> = buffers
+                    .iter()
+                    .map(|(l, r)| (l.as_slice(), r.as_slice()))
+                    .collect();
+                let starts: Vec<usize> = group.iter().map(|&pos| chunk_start + pos).collect();
+                let prepared = backend.prepare(&windows, &starts)?;
+                busy += started.elapsed();
+                if prep_tx.send((gi, prepared)).is_err() {
+                    break;
+                }
+            }
+            Ok(busy)
+        });
 
-        let out = engine::run_window_demucs(&left_raw, &right_raw)?;
-        let (s_count, _, t_out) = (out.shape()[0], out.shape()[1], out.shape()[2]);
-
-        let copy_len = hop.min(t_out).min(chunk_len - pos);
-        for st in 0..s_count.min(stems_count) {
-            for i in 0..copy_len {
-                chunk_acc[st][pos + i][0] = out[(st, 0, i)];
-                chunk_acc[st][pos + i][1] = out[(st, 1, i)];
+        // One inference thread per pooled session
+        let runners: Vec<_> = (0..backend.session_count())
+            .map(|_| {
+                let prep_rx = &prep_rx;
+                let raw_tx = raw_tx.clone();
+                scope.spawn(move || -> Result<Duration> {
+                    let mut busy = Duration::ZERO;
+                    let result = loop {
+                        let next = prep_rx.lock().expect("pipeline queue poisoned").recv();
+                        let Ok((gi, prepared)) = next else { break Ok(busy) };
+                        let started = Instant::now();
+                        let raw = match backend.infer(prepared) {
+                            Ok(raw) => raw,
+                            Err(e) => break Err(e),
+                        };
+                        busy += started.elapsed();
+                        if raw_tx.send((gi, raw)).is_err() {
+                            // The final stage failed
+                            break Ok(busy);
+                        }
+                    };
+                    if result.is_err() || aborted.load(Ordering::Relaxed) {
+                        // Unblock the preparer, which may be waiting on a full queue
+                        aborted.store(true, Ordering::Relaxed);
+                        while prep_rx.lock().expect("pipeline queue poisoned").recv().is_ok() {}
+                    }
+                    result
+                })
+            })
+            .collect();
+        drop(raw_tx);
+
+        // Final stage on this thread, writing straight into the chunk
+        let mut finish_busy = Duration::ZERO;
+        for (gi, raw) in raw_rx {
+            let started = Instant::now();
+            let outs = match backend.finish(raw) {
+                Ok(outs) => outs,
+                Err(e) => {
+                    aborted.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            };
+            for (&pos, out) in groups[gi].iter().zip(&outs) {
+                let (s_count, _, t_out) = (out.shape()[0], out.shape()[1], out.shape()[2]);
+
+                let copy_len = hop.min(t_out).min(chunk_len - pos);
+                for st in 0..s_count.min(stems_count) {
+                    for i in 0..copy_len {
+                        chunk_acc[st][pos + i][0] = out[(st, 0, i)];
+                        chunk_acc[st][pos + i][1] = out[(st, 1, i)];
+                    }
+                }
             }
+            finish_busy += started.elapsed();
         }
 
-        if pos + hop >= chunk_len {
-            break;
+        let join = |h: std::thread::ScopedJoinHandle<'_, Result<Duration>>| {
+            h.join()
+                .map_err(|_| anyhow::anyhow!("separation pipeline thread panicked"))?
+        };
+        let prepare_busy = join(preparer)?;
+        let mut infer_busy = Duration::ZERO;
+        for runner in runners {
+            infer_busy += join(runner)?;
         }
-        pos += hop;
-    }
+        Ok((prepare_busy, infer_busy, finish_busy))
+    })?;
+    // The preparer stops early when cancelled, leaving the chunk incomplete
+    cancel.check()?;
+
+    timing.add(&PipelineTiming {
+        prepare_secs: prepare_time.as_secs_f64(),
+        infer_secs: infer_time.as_secs_f64(),
+        finish_secs: finish_time.as_secs_f64(),
+        wall_secs: wall.elapsed().as_secs_f64(),
+        windows: positions.len(),
+    });
 
     Ok(chunk_acc)
 }
@@ -327,27 +711,68 @@ fn apply_crossfade(
 
 /// Core separation logic - shared between all public APIs
 /// Supports chunked processing for long audio files to reduce memory usage.
-fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<StemDataInternal> {
-    emit_split_progress(SplitProgress::Stage("resolve_model"));
-    
-    // Use custom model path if provided, otherwise download/cache model
-    let handle = if let Some(ref model_path) = opts.model_path {
-        load_model_from_path(model_path)?
+/// `model` skips resolving the model from `opts` when already known.
+fn separate_stems_internal(
+    input: Input<'_>,
+    opts: &SplitOptions,
+    model: Option<ModelHandle>,
+) -> Result<StemDataInternal> {
+    if model.is_none() {
+        emit_split_progress(SplitProgress::Stage("resolve_model"));
+    }
+
+    let (backend, handle) = if let Some(test_engine) = &opts.test_engine {
+        emit_split_progress(SplitProgress::Stage("engine_preload"));
+        let sessions = opts.engine.sessions.max(1);
+        (Backend::Test { engine: test_engine, sessions }, None)
     } else {
-        ensure_model(&opts.model_name, opts.manifest_url_override.as_deref())?
-    };
+        // Use custom model path if provided, otherwise download/cache model
+        let handle = if let Some(handle) = model {
+            handle
+        } else if let Some(ref model_path) = opts.model_path {
+            load_model_from_path_with_manifest(model_path, opts.model_manifest_path.as_deref())?
+        } else {
+            let cache_dir = models_dir(opts.model_cache_dir.as_deref())?;
+            ensure_model_in(&opts.model_name, opts.manifest_url_override.as_deref(), &cache_dir)?
+        };
 
-    emit_split_progress(SplitProgress::Stage("engine_preload"));
-    engine::preload(&handle)?;
+        (Backend::Model, Some(handle))
+    };
 
-    let mf = engine::manifest();
+    // The engine is only loaded after the result cache missed; until then
+    // the handle describes the model
+    let mf = match &handle {
+        Some(handle) => &handle.manifest,
+        None => backend.manifest(),
+    };
 
     if mf.sample_rate != 44100 {
         return Err(anyhow::anyhow!("Currently expecting 44.1k model").into());
     }
 
     emit_split_progress(SplitProgress::Stage("read_audio"));
-    let audio = read_audio(input_path)?;
+    let ranged = opts.start.is_some() || opts.end.is_some();
+    // For ranges, decode half a model window of context on each side so the
+    // edges are separated with their real surroundings.
+    let context = if ranged { (mf.window / 2) as u64 } else { 0 };
+    let decoded = match input {
+        Input::Path(path) => read_audio_range(path, opts.start, opts.end, context)?,
+        Input::Audio(audio) => slice_audio_range(audio, opts.start, opts.end, context)?,
+    };
+    if ranged && decoded.is_empty() {
+        return Err(anyhow::anyhow!("Requested range is empty or past the end of the input").into());
+    }
+    let range = ranged.then(|| (decoded.start_offset(), decoded.len(), decoded.start));
+    let DecodedRange { audio, mut metadata, .. } = decoded;
+    if !opts.copy_metadata {
+        metadata = AudioMetadata::default();
+    } else if let (None, Input::Path(path)) = (&metadata.title, input) {
+        // Untagged input: name stems after the file
+        metadata.title = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned());
+    }
+
     let stereo = to_planar_stereo(&audio.samples, audio.channels);
     let n = stereo.len();
 
@@ -365,6 +790,37 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
     let stems_names = mf.stems.clone();
     let stems_count = stems_names.len().max(4);
 
+    // Opt-in result cache, keyed by the decoded input, the model and
+    // everything else that shapes the output samples. Test engines are
+    // scripted per run and never cached.
+    let cache = match (&opts.cache, &handle) {
+        (Some(cache_opts), Some(handle)) => {
+            let cache = ResultCache::open(cache_opts)?;
+            let params = (win, hop, &mf.stft, opts.chunk_seconds, range, &stems_names);
+            let key = result_cache::cache_key(&stereo, &result_cache::model_sha256(handle)?, &params)?;
+            if let Some(hit) = cache.get(&key) {
+                emit_split_progress(SplitProgress::Stage("cache_hit"));
+                let n = hit.acc[0].len();
+                return Ok(StemDataInternal {
+                    stems_count: hit.acc.len(),
+                    name_idx: stem_name_index(&hit.names),
+                    acc: hit.acc,
+                    sample_rate: hit.sample_rate,
+                    n,
+                    start_sample: hit.start_sample,
+                    metadata,
+                });
+            }
+            Some((cache, key))
+        }
+        _ => None,
+    };
+
+    if let Some(handle) = &handle {
+        emit_split_progress(SplitProgress::Stage("engine_preload"));
+        engine::preload_with_config(handle, &opts.engine)?;
+    }
+
     // Calculate chunk size based on chunk_seconds option
     // Default is 5 minutes (300 seconds) = 13,230,000 samples at 44100Hz
     let chunk_seconds = opts.chunk_seconds.unwrap_or(300);
@@ -388,6 +844,67 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
     let mut acc: Vec<Vec<[f32; 2]>> = vec![vec![[0f32; 2]; n]; stems_count];
     
     emit_split_progress(SplitProgress::Stage("infer"));
+    let mut timing = PipelineTiming::default();
+    let cancel = opts.cancel.clone().unwrap_or_default();
+
+    // Finished chunks of an interrupted run are replayed from the job
+    // directory; the test engine is keyed by its manifest and stem scripts
+    let mut checkpoint = match &opts.checkpoint_dir {
+        Some(dir) => {
+            let model_id = match &handle {
+                Some(handle) => result_cache::model_sha256(handle)?,
+                None => mf.name.clone(),
+            };
+            let params = (
+                "checkpoint",
+                win,
+                hop,
+                &mf.stft,
+                opts.chunk_seconds,
+                range,
+                &stems_names,
+                opts.engine.batch_size,
+                opts.test_engine.as_ref().map(TestEngine::script_key),
+            );
+            let key = result_cache::cache_key(&stereo, &model_id, &params)?;
+            Some(Checkpoint::open(Path::new(dir), &key, stems_count)?)
+        }
+        None => None,
+    };
+    let mut resumed = false;
+    let mut run_chunk = |index: usize,
+                         start: usize,
+                         len: usize,
+                         timing: &mut PipelineTiming|
+     -> Result<Vec<Vec<[f32; 2]>>> {
+        if let Some(stored) = checkpoint.as_ref().and_then(|c| c.load(index, start, len)) {
+            if !resumed {
+                emit_split_progress(SplitProgress::Stage("resume_checkpoint"));
+                resumed = true;
+            }
+            return Ok(stored);
+        }
+        let chunk_acc = process_chunk(
+            backend,
+            &stereo,
+            start,
+            len,
+            win,
+            hop,
+            stems_count,
+            opts.engine.batch_size,
+            &cancel,
+            timing,
+        )?;
+        if let Some(checkpoint) = &mut checkpoint {
+            // Like the result cache, a failing checkpoint must not fail the
+            // separation
+            if let Err(e) = checkpoint.store(index, start, len, &chunk_acc) {
+                eprintln!("Failed to checkpoint chunk {index}: {e}");
+            }
+        }
+        Ok(chunk_acc)
+    };
 
     // Process in chunks if audio is long
     if n > chunk_samples {
@@ -415,7 +932,7 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
             });
 
             // Process this chunk
-            let chunk_acc = process_chunk(&stereo, chunk_start, chunk_len, win, hop, stems_count)?;
+            let chunk_acc = run_chunk(chunk_idx, chunk_start, chunk_len, &mut timing)?;
             
             // Apply to main accumulator with crossfade
             apply_crossfade(&mut acc, &chunk_acc, chunk_start, chunk_len, overlap_samples, stems_count);
@@ -428,7 +945,7 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
         }
     } else {
         // Short audio: process in one go (original logic)
-        let chunk_acc = process_chunk(&stereo, 0, n, win, hop, stems_count)?;
+        let chunk_acc = run_chunk(1, 0, n, &mut timing)?;
         for st in 0..stems_count {
             for i in 0..n {
                 acc[st][i] = chunk_acc[st][i];
@@ -436,15 +953,57 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
         }
     }
 
+    if let Some(checkpoint) = checkpoint {
+        if let Err(e) = checkpoint.finish() {
+            eprintln!("Failed to remove separation checkpoint: {e}");
+        }
+    }
+
+    if std::env::var("DEBUG_STEMS").is_ok() {
+        eprintln!(
+            "Pipeline: {} windows, prepare {:.2}s + infer {:.2}s + finish {:.2}s in {:.2}s wall ({:.2}x speedup)",
+            timing.windows,
+            timing.prepare_secs,
+            timing.infer_secs,
+            timing.finish_secs,
+            timing.wall_secs,
+            timing.speedup()
+        );
+    }
+    emit_split_progress(SplitProgress::Timing(timing));
+
+    // Drop the context padding so stems cover exactly the requested range
+    let (n, start_sample) = match range {
+        Some((offset, len, start)) => {
+            for stem in acc.iter_mut() {
+                stem.truncate(offset + len);
+                stem.drain(..offset);
+            }
+            (len, start)
+        }
+        None => (n, 0),
+    };
+
     let names = if stems_names.is_empty() {
         vec!["vocals".into(), "drums".into(), "bass".into(), "other".into()]
     } else {
         stems_names
     };
 
-    let mut name_idx: HashMap<String, usize> = HashMap::new();
-    for (i, name) in names.iter().enumerate() {
-        name_idx.insert(name.to_lowercase(), i);
+    let name_idx = stem_name_index(&names);
+
+    if let Some((cache, key)) = cache {
+        let entry = CachedStems {
+            names,
+            sample_rate: mf.sample_rate,
+            start_sample,
+            acc,
+        };
+        // A failing cache must not fail the separation
+        if let Err(e) = cache.put(&key, &entry) {
+            eprintln!("Failed to store separation in result cache: {e}");
+        }
+        acc = entry.acc;
     }
 
     if std::env::var("DEBUG_STEMS").is_ok() {
@@ -462,22 +1021,56 @@ fn separate_stems_internal(input_path: &str, opts: &SplitOptions) -> Result<Stem
         name_idx,
         sample_rate: mf.sample_rate,
         n,
+        start_sample,
+        metadata,
     })
 }
 
+/// Map lowercase stem names to their index in the model output.
+fn stem_name_index(names: &[String]) -> HashMap<String, usize> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_lowercase(), i))
+        .collect()
+}
+
 /// Split an audio file into 4 separate stems: vocals, drums, bass, other
 pub fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
-    let stem_data = separate_stems_internal(input_path, &opts)?;
-    let StemDataInternal { acc, stems_count, name_idx, sample_rate, n } = stem_data;
+    split_file_with_model(input_path, opts, None)
+}
+
+/// [`split_file`] with an already resolved model.
+pub(crate) fn split_file_with_model(
+    input_path: &str,
+    opts: SplitOptions,
+    model: Option<ModelHandle>,
+) -> Result<SplitResult> {
+    let names: Vec<&str> = Stem::all().iter().map(|s| s.name()).collect();
+    let dests = output_paths(input_path, &opts, &names)?;
+    if opts.overwrite == OverwritePolicy::Skip && dests.iter().all(|p| p.exists()) {
+        emit_split_progress(SplitProgress::Finished);
+        let [v, d, b, o] = [0, 1, 2, 3].map(|i| dests[i].to_string_lossy().into_owned());
+        return Ok(SplitResult {
+            vocals_path: v,
+            drums_path: d,
+            bass_path: b,
+            other_path: o,
+            loudness: HashMap::new(),
+        });
+    }
 
-    let tmp = tempdir()?;
-    let tmp_dir = tmp.path().to_path_buf();
+    let stem_data = separate_stems_internal(Input::Path(input_path), &opts, model)?;
+    let StemDataInternal { acc, stems_count, name_idx, sample_rate, n, metadata, .. } = stem_data;
 
     fs::create_dir_all(&opts.output_dir)?;
 
     emit_split_progress(SplitProgress::Stage("write_stems"));
 
-    let stem_to_wav = |st: usize, base: &str| -> Result<String> {
+    let mut loudness = HashMap::new();
+
+    let mut stem_to_wav = |st: usize, stem: Stem| -> Result<String> {
+        let base = stem.name();
         let mut inter = Vec::with_capacity(n * 2);
         for sample in &acc[st][..n] {
             inter.push(sample[0]);
@@ -491,15 +1084,18 @@ pub fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
             percent: 100.0,
         });
 
-        let data = AudioData {
+        let mut data = AudioData {
             samples: inter,
             sample_rate,
             channels: 2,
         };
+        if let Some(stats) = prepare_for_write(&mut data, &opts) {
+            loudness.insert(base.to_string(), stats);
+        }
 
-        let p = tmp_dir.join(format!("{base}.wav"));
-        write_audio(p.to_str().unwrap(), &data)?;
-        Ok(p.to_string_lossy().into())
+        let tags = metadata.for_stem(stem.display_name());
+        let dest = &dests[Stem::all().iter().position(|&s| s == stem).unwrap()];
+        write_output(dest, &data, &tags, opts.overwrite)
     };
 
     let get_idx = |key: &str, fallback: usize| -> usize {
@@ -509,24 +1105,13 @@ pub fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
             .unwrap_or(fallback.min(stems_count.saturating_sub(1)))
     };
 
-    let v_path = stem_to_wav(get_idx("vocals", 0), "vocals")?;
-    let d_path = stem_to_wav(get_idx("drums", 1), "drums")?;
-    let b_path = stem_to_wav(get_idx("bass", 2), "bass")?;
-    let o_path = stem_to_wav(get_idx("other", 3), "other")?;
+    let vocals_out = stem_to_wav(get_idx("vocals", 0), Stem::Vocals)?;
+    let drums_out = stem_to_wav(get_idx("drums", 1), Stem::Drums)?;
+    let bass_out = stem_to_wav(get_idx("bass", 2), Stem::Bass)?;
+    let other_out = stem_to_wav(get_idx("other", 3), Stem::Other)?;
 
     emit_split_progress(SplitProgress::Stage("finalize"));
 
-    let file_stem = Path::new(input_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("output");
-    let base = PathBuf::from(&opts.output_dir).join(file_stem);
-
-    let vocals_out = copy_to(&v_path, &format!("{}_vocals.wav", base.to_string_lossy()))?;
-    let drums_out = copy_to(&d_path, &format!("{}_drums.wav", base.to_string_lossy()))?;
-    let bass_out = copy_to(&b_path, &format!("{}_bass.wav", base.to_string_lossy()))?;
-    let other_out = copy_to(&o_path, &format!("{}_other.wav", base.to_string_lossy()))?;
-
     emit_split_progress(SplitProgress::Finished);
 
     Ok(SplitResult {
@@ -534,6 +1119,7 @@ pub fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
         drums_path: drums_out,
         bass_path: bass_out,
         other_path: other_out,
+        loudness,
     })
 }
 
@@ -553,11 +1139,27 @@ pub fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
 /// # Ok::<(), anyhow::Error>(())
 /// ```
 pub fn remove_vocals(input_path: &str, opts: SplitOptions) -> Result<VocalRemovalResult> {
-    let stem_data = separate_stems_internal(input_path, &opts)?;
-    let StemDataInternal { acc, stems_count, name_idx, sample_rate, n } = stem_data;
+    remove_vocals_with_model(input_path, opts, None)
+}
+
+/// [`remove_vocals`] with an already resolved model.
+pub(crate) fn remove_vocals_with_model(
+    input_path: &str,
+    opts: SplitOptions,
+    model: Option<ModelHandle>,
+) -> Result<VocalRemovalResult> {
+    let dests = output_paths(input_path, &opts, &["vocals", "instrumental"])?;
+    if opts.overwrite == OverwritePolicy::Skip && dests.iter().all(|p| p.exists()) {
+        emit_split_progress(SplitProgress::Finished);
+        return Ok(VocalRemovalResult {
+            instrumental_path: dests[1].to_string_lossy().into_owned(),
+            vocals_path: dests[0].to_string_lossy().into_owned(),
+            loudness: HashMap::new(),
+        });
+    }
 
-    let tmp = tempdir()?;
-    let tmp_dir = tmp.path().to_path_buf();
+    let stem_data = separate_stems_internal(Input::Path(input_path), &opts, model)?;
+    let StemDataInternal { acc, stems_count, name_idx, sample_rate, n, metadata, .. } = stem_data;
 
     fs::create_dir_all(&opts.output_dir)?;
 
@@ -586,28 +1188,42 @@ pub fn remove_vocals(input_path: &str, opts: SplitOptions) -> Result<VocalRemova
         percent: 100.0,
     });
 
-    let vocals_data = AudioData {
+    let mut loudness = HashMap::new();
+
+    let mut vocals_data = AudioData {
         samples: vocals_samples,
         sample_rate,
         channels: 2,
     };
-    let vocals_tmp = tmp_dir.join("vocals.wav");
-    write_audio(vocals_tmp.to_str().unwrap(), &vocals_data)?;
-
-    // Create instrumental (everything except vocals)
-    let mut instrumental = Vec::with_capacity(n * 2);
-    for i in 0..n {
-        let mut left = 0.0f32;
-        let mut right = 0.0f32;
-        for st in 0..stems_count {
-            if st != vocals_idx {
-                left += acc[st][i][0];
-                right += acc[st][i][1];
+    if let Some(stats) = prepare_for_write(&mut vocals_data, &opts) {
+        loudness.insert("vocals".to_string(), stats);
+    }
+    let vocals_tags = metadata.for_stem(Stem::Vocals.display_name());
+    let vocals_out = write_output(&dests[0], &vocals_data, &vocals_tags, opts.overwrite)?;
+
+    // Create instrumental (everything except vocals, or the requested mix)
+    let instrumental = if let Some(spec) = &opts.instrumental_mix {
+        let sources = Stem::all()
+            .iter()
+            .enumerate()
+            .map(|(i, &stem)| (stem, &acc[get_idx(stem.name(), i)][..n]));
+        mixer::render(sources, n, sample_rate, spec)
+    } else {
+        let mut instrumental = Vec::with_capacity(n * 2);
+        for i in 0..n {
+            let mut left = 0.0f32;
+            let mut right = 0.0f32;
+            for st in 0..stems_count {
+                if st != vocals_idx {
+                    left += acc[st][i][0];
+                    right += acc[st][i][1];
+                }
             }
+            instrumental.push(left);
+            instrumental.push(right);
         }
-        instrumental.push(left);
-        instrumental.push(right);
-    }
+        instrumental
+    };
 
     emit_split_progress(SplitProgress::Writing {
         stem: "instrumental".to_string(),
@@ -616,40 +1232,82 @@ pub fn remove_vocals(input_path: &str, opts: SplitOptions) -> Result<VocalRemova
         percent: 100.0,
     });
 
-    let instrumental_data = AudioData {
+    let mut instrumental_data = AudioData {
         samples: instrumental,
         sample_rate,
         channels: 2,
     };
-    let instrumental_tmp = tmp_dir.join("instrumental.wav");
-    write_audio(instrumental_tmp.to_str().unwrap(), &instrumental_data)?;
+    if let Some(stats) = prepare_for_write(&mut instrumental_data, &opts) {
+        loudness.insert("instrumental".to_string(), stats);
+    }
+    let instrumental_tags = metadata.for_stem("Instrumental");
+    let instrumental_out =
+        write_output(&dests[1], &instrumental_data, &instrumental_tags, opts.overwrite)?;
 
     emit_split_progress(SplitProgress::Stage("finalize"));
 
-    let file_stem = Path::new(input_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("output");
-    let base = PathBuf::from(&opts.output_dir).join(file_stem);
-
-    let vocals_out = copy_to(
-        vocals_tmp.to_str().unwrap(),
-        &format!("{}_vocals.wav", base.to_string_lossy()),
-    )?;
-    let instrumental_out = copy_to(
-        instrumental_tmp.to_str().unwrap(),
-        &format!("{}_instrumental.wav", base.to_string_lossy()),
-    )?;
-
     emit_split_progress(SplitProgress::Finished);
 
     Ok(VocalRemovalResult {
         instrumental_path: instrumental_out,
         vocals_path: vocals_out,
+        loudness,
     })
 }
 
-fn copy_to(src: &str, dst: &str) -> Result<String> {
-    fs::copy(src, dst)?;
-    Ok(dst.to_string())
+/// Normalize and/or measure audio about to be written, as requested in `opts`.
+fn prepare_for_write(data: &mut AudioData, opts: &SplitOptions) -> Option<LoudnessStats> {
+    if let Some(norm) = &opts.normalize {
+        Some(data.normalize(norm).0)
+    } else if opts.measure_loudness {
+        Some(data.loudness())
+    } else {
+        None
+    }
+}
+
+/// Destinations of the named outputs for `input_path`, expanded from the
+/// filename template. Fails before any work is done if the overwrite
+/// policy would reject one of them.
+fn output_paths(input_path: &str, opts: &SplitOptions, names: &[&str]) -> Result<Vec<PathBuf>> {
+    let input = Path::new(input_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let model = opts
+        .model_path
+        .as_deref()
+        .and_then(|p| Path::new(p).file_stem())
+        .and_then(|s| s.to_str())
+        .unwrap_or(&opts.model_name);
+
+    let paths: Vec<PathBuf> = names
+        .iter()
+        .map(|name| {
+            output::render_filename(
+                &opts.output_dir,
+                &opts.filename_template,
+                input,
+                name,
+                model,
+                "wav",
+            )
+        })
+        .collect::<Result<_>>()?;
+    output::check_destinations(&paths, opts.overwrite)?;
+    if opts.overwrite == OverwritePolicy::UniqueSuffix {
+        return output::unique_destinations(&paths);
+    }
+    Ok(paths)
+}
+
+/// Write a WAV straight to its destination via a temp file and rename.
+fn write_output(
+    dest: &Path,
+    data: &AudioData,
+    tags: &AudioMetadata,
+    policy: OverwritePolicy,
+) -> Result<String> {
+    let written = output::write_atomic(dest, policy, |w| Ok(write_audio_to(w, data, tags)?))?;
+    Ok(written.to_string_lossy().into_owned())
 }
@@ -0,0 +1,369 @@
+//! Scriptable stand-in for the ONNX model, for testing applications built
+//! on the splitter without downloading or running a real model.
+//!
+//! Set [`SplitOptions::test_engine`](crate::SplitOptions::test_engine) and
+//! `split_file`, `remove_vocals` and `Separator::separate` run their usual
+//! pipeline (windowing, batching, sessions, progress events, output
+//! writing) with this engine in place of model inference:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use stem_splitter_core::{split_file, SplitOptions, StemScript, TestEngine};
+//!
+//! let engine = TestEngine::new()
+//!     .with_stem("vocals", StemScript::Gain(0.5))
+//!     .with_stem("bass", StemScript::LowPass { cutoff_hz: 200.0 })
+//!     .with_latency(Duration::from_millis(20))
+//!     .with_failure_at(3, "GPU lost");
+//! let opts = SplitOptions {
+//!     test_engine: Some(engine.clone()),
+//!     ..Default::default()
+//! };
+//! assert!(split_file("song.wav", opts).is_err());
+//! for request in engine.requests() {
+//!     println!("window {} at sample {}", request.index, request.start);
+//! }
+//! ```
+
+use crate::{
+    core::engine,
+    error::{Result, StemError},
+    types::ModelManifest,
+};
+use anyhow::anyhow;
+use ndarray::Array3;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
+
+/// Custom stem output: `(request, left, right) -> (left, right)`.
+pub type StemFn =
+    dyn Fn(&WindowRequest, &[f32], &[f32]) -> (Vec<f32>, Vec<f32>) + Send + Sync;
+
+/// How the test engine derives a stem from an input window. Scripts run on
+/// each window independently (filters and delays restart at every window).
+#[derive(Clone)]
+pub enum StemScript {
+    /// Input scaled by a linear gain.
+    Gain(f32),
+    /// Input delayed by `samples` within the window (zero-filled) and scaled.
+    Delay { samples: usize, gain: f32 },
+    /// One-pole low-pass filtered input.
+    LowPass { cutoff_hz: f32 },
+    /// Input minus its one-pole low-pass.
+    HighPass { cutoff_hz: f32 },
+    Silence,
+    Custom(Arc<StemFn>),
+}
+
+impl fmt::Debug for StemScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StemScript::Gain(g) => f.debug_tuple("Gain").field(g).finish(),
+            StemScript::Delay { samples, gain } => f
+                .debug_struct("Delay")
+                .field("samples", samples)
+                .field("gain", gain)
+                .finish(),
+            StemScript::LowPass { cutoff_hz } => {
+                f.debug_struct("LowPass").field("cutoff_hz", cutoff_hz).finish()
+            }
+            StemScript::HighPass { cutoff_hz } => {
+                f.debug_struct("HighPass").field("cutoff_hz", cutoff_hz).finish()
+            }
+            StemScript::Silence => f.write_str("Silence"),
+            StemScript::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// A window the splitter asked the engine to separate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowRequest {
+    /// Position in the engine's request log (see [`TestEngine::requests`]).
+    pub index: usize,
+    /// First sample of the window in the decoded input (which includes
+    /// the context around a requested range).
+    pub start: usize,
+    /// Window length in samples; windows past the end are zero-padded.
+    pub len: usize,
+    /// Number of windows in the inference batch this window was part of.
+    pub batch: usize,
+}
+
+/// Scriptable engine. Clones share the request log, so keep a clone to
+/// inspect after passing one to [`SplitOptions`](crate::SplitOptions).
+#[derive(Clone)]
+pub struct TestEngine {
+    manifest: ModelManifest,
+    scripts: HashMap<String, StemScript>,
+    latency: Duration,
+    latency_at: HashMap<usize, Duration>,
+    failures: HashMap<usize, String>,
+    max_batch: Option<usize>,
+    requests: Arc<Mutex<Vec<WindowRequest>>>,
+    batch_unsupported: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for TestEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestEngine")
+            .field("stems", &self.manifest.stems)
+            .field("window", &self.manifest.window)
+            .field("hop", &self.manifest.hop)
+            .field("scripts", &self.scripts)
+            .field("latency", &self.latency)
+            .field("latency_at", &self.latency_at)
+            .field("failures", &self.failures)
+            .field("max_batch", &self.max_batch)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for TestEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestEngine {
+    /// Four stems (vocals, drums, bass, other), each a quarter of the input
+    /// so they sum back to the mix; one-second windows with half-window hop.
+    pub fn new() -> Self {
+        Self {
+            manifest: ModelManifest {
+                name: "test_engine".into(),
+                version: String::new(),
+                backend: "test".into(),
+                format: String::new(),
+                opset: None,
+                sample_rate: 44100,
+                window: 44100,
+                hop: 22050,
+                stft: Default::default(),
+                stems: vec!["vocals".into(), "drums".into(), "bass".into(), "other".into()],
+                input_layout: String::new(),
+                output_layout: String::new(),
+                inputs: vec![],
+                outputs: vec![],
+                artifacts: vec![],
+                entry: String::new(),
+                url: String::new(),
+                sha256: String::new(),
+                filesize: 0,
+            },
+            scripts: HashMap::new(),
+            latency: Duration::ZERO,
+            latency_at: HashMap::new(),
+            failures: HashMap::new(),
+            max_batch: None,
+            requests: Arc::default(),
+            batch_unsupported: Arc::default(),
+        }
+    }
+
+    /// Stem names in output order.
+    pub fn with_stems<S: AsRef<str>>(mut self, stems: &[S]) -> Self {
+        self.manifest.stems = stems.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Window and hop in samples, as a model manifest would declare them.
+    pub fn with_window(mut self, window: usize, hop: usize) -> Self {
+        self.manifest.window = window;
+        self.manifest.hop = hop;
+        self
+    }
+
+    /// Script one stem's output. Stems without a script get
+    /// `Gain(1 / number of stems)`.
+    pub fn with_stem(mut self, stem: &str, script: StemScript) -> Self {
+        self.scripts.insert(stem.to_lowercase(), script);
+        self
+    }
+
+    /// Sleep this long for every window, simulating inference time.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Extra latency for the window with request index `index`.
+    pub fn with_latency_at(mut self, index: usize, latency: Duration) -> Self {
+        self.latency_at.insert(index, latency);
+        self
+    }
+
+    /// Fail inference of the batch containing request index `index`.
+    pub fn with_failure_at(mut self, index: usize, message: &str) -> Self {
+        self.failures.insert(index, message.to_string());
+        self
+    }
+
+    /// Reject runs of more than `max` windows the way a model with a fixed
+    /// batch axis does, so the splitter falls back to one window per run.
+    pub fn with_max_batch(mut self, max: usize) -> Self {
+        self.max_batch = Some(max);
+        self
+    }
+
+    /// Every window requested so far, in the order the splitter prepared
+    /// them. Indices keep counting across separations until [`clear_requests`](Self::clear_requests).
+    pub fn requests(&self) -> Vec<WindowRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn clear_requests(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+
+    /// The manifest the splitter sees for this engine.
+    pub fn manifest(&self) -> &ModelManifest {
+        &self.manifest
+    }
+
+    /// The script each stem runs, in stem order, for keying checkpoints.
+    /// `Custom` scripts are told apart by their closure's address, so their
+    /// checkpoints only resume within the same process.
+    pub(crate) fn script_key(&self) -> Vec<String> {
+        let default = StemScript::Gain(1.0 / self.manifest.stems.len().max(1) as f32);
+        self.manifest
+            .stems
+            .iter()
+            .map(|name| match self.scripts.get(&name.to_lowercase()).unwrap_or(&default) {
+                StemScript::Custom(f) => format!("Custom({:p})", Arc::as_ptr(f)),
+                script => format!("{script:?}"),
+            })
+            .collect()
+    }
+
+    /// Record a batch of windows starting at `starts` in the decoded input.
+    pub(crate) fn prepare(&self, windows: &[(&[f32], &[f32])], starts: &[usize]) -> Batch {
+        let mut log = self.requests.lock().unwrap();
+        let requests: Vec<WindowRequest> = windows
+            .iter()
+            .zip(starts)
+            .map(|((left, _), &start)| {
+                let request = WindowRequest {
+                    index: log.len(),
+                    start,
+                    len: left.len(),
+                    batch: windows.len(),
+                };
+                log.push(request.clone());
+                request
+            })
+            .collect();
+        Batch {
+            requests,
+            windows: windows.iter().map(|(l, r)| (l.to_vec(), r.to_vec())).collect(),
+        }
+    }
+
+    /// Run the scripts over a batch, returning (stems, 2, T) per window.
+    pub(crate) fn run(&self, batch: Batch) -> Result<Vec<Array3<f32>>> {
+        let Batch { requests, windows } = &batch;
+        engine::with_batch_fallback(
+            requests.len(),
+            &self.batch_unsupported,
+            || self.run_windows(requests, windows),
+            || {
+                let mut outs = Vec::with_capacity(requests.len());
+                for i in 0..requests.len() {
+                    outs.extend(self.run_windows(&requests[i..=i], &windows[i..=i])?);
+                }
+                Ok(outs)
+            },
+        )
+    }
+
+    fn run_windows(
+        &self,
+        requests: &[WindowRequest],
+        windows: &[(Vec<f32>, Vec<f32>)],
+    ) -> Result<Vec<Array3<f32>>> {
+        if let Some(max) = self.max_batch.filter(|&max| requests.len() > max) {
+            return Err(StemError::BatchRejected(format!(
+                "test engine runs at most {max} windows, got {}",
+                requests.len()
+            )));
+        }
+        for request in requests {
+            let extra = self.latency_at.get(&request.index).copied().unwrap_or_default();
+            std::thread::sleep(self.latency + extra);
+            if let Some(message) = self.failures.get(&request.index) {
+                return Err(anyhow!("test engine failure at window {}: {message}", request.index).into());
+            }
+        }
+
+        let stems = &self.manifest.stems;
+        let default = StemScript::Gain(1.0 / stems.len().max(1) as f32);
+        let sample_rate = self.manifest.sample_rate as f32;
+        requests
+            .iter()
+            .zip(windows)
+            .map(|(request, (left, right))| {
+                let t = left.len();
+                let mut out = Array3::<f32>::zeros((stems.len(), 2, t));
+                for (s, name) in stems.iter().enumerate() {
+                    let script = self.scripts.get(&name.to_lowercase()).unwrap_or(&default);
+                    let (l, r) = apply_script(script, request, left, right, sample_rate);
+                    for (c, data) in [l, r].iter().enumerate() {
+                        for (i, &x) in data.iter().take(t).enumerate() {
+                            out[(s, c, i)] = x;
+                        }
+                    }
+                }
+                Ok(out)
+            })
+            .collect()
+    }
+}
+
+/// Windows prepared by [`TestEngine::prepare`].
+pub(crate) struct Batch {
+    requests: Vec<WindowRequest>,
+    windows: Vec<(Vec<f32>, Vec<f32>)>,
+}
+
+fn apply_script(
+    script: &StemScript,
+    request: &WindowRequest,
+    left: &[f32],
+    right: &[f32],
+    sample_rate: f32,
+) -> (Vec<f32>, Vec<f32>) {
+    let each = |f: &dyn Fn(&[f32]) -> Vec<f32>| (f(left), f(right));
+    match script {
+        StemScript::Gain(g) => each(&|x| x.iter().map(|s| s * g).collect()),
+        StemScript::Delay { samples, gain } => each(&|x| {
+            let d = (*samples).min(x.len());
+            let mut y = vec![0.0; x.len()];
+            for (o, s) in y[d..].iter_mut().zip(x) {
+                *o = s * gain;
+            }
+            y
+        }),
+        StemScript::LowPass { cutoff_hz } => each(&|x| low_pass(x, *cutoff_hz, sample_rate)),
+        StemScript::HighPass { cutoff_hz } => each(&|x| {
+            let low = low_pass(x, *cutoff_hz, sample_rate);
+            x.iter().zip(low).map(|(s, l)| s - l).collect()
+        }),
+        StemScript::Silence => (vec![0.0; left.len()], vec![0.0; right.len()]),
+        StemScript::Custom(f) => f(request, left, right),
+    }
+}
+
+fn low_pass(x: &[f32], cutoff_hz: f32, sample_rate: f32) -> Vec<f32> {
+    let alpha = 1.0 - (-2.0 * std::f32::consts::PI * cutoff_hz / sample_rate).exp();
+    let mut state = 0.0f32;
+    x.iter()
+        .map(|&s| {
+            state += alpha * (s - state);
+            state
+        })
+        .collect()
+}
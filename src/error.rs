@@ -19,6 +19,21 @@ pub enum StemError {
 
     #[error("manifest error: {0}")]
     Manifest(String),
+
+    #[error("Manifest signature error: {0}")]
+    Signature(String),
+
+    #[error("Output error: {0}")]
+    Output(String),
+
+    #[error("Output file already exists: {path}")]
+    OutputExists { path: String },
+
+    #[error("Separation cancelled")]
+    Cancelled,
+
+    #[error("Batch rejected: {0}")]
+    BatchRejected(String),
 }
 
 // --- Implement From conversions for common errors ---
@@ -0,0 +1,206 @@
+//! Checkpoints of long separations, so an interrupted job can resume.
+//!
+//! With [`SplitOptions::checkpoint_dir`](crate::SplitOptions::checkpoint_dir)
+//! set, the model output of every finished chunk is written to the job
+//! directory as `chunk-<n>.f32` and recorded in `journal.jsonl`. A rerun
+//! with the same input, model and options replays those chunks instead of
+//! running inference again. Chunks are stored before they are crossfaded
+//! into the result, so a resumed run is bit-identical to an uninterrupted
+//! one.
+//!
+//! The journal's first line holds a key over the decoded input and the
+//! settings that shape the output; a journal written for another key is
+//! discarded along with its chunks. Each chunk line carries the SHA-256 of
+//! its file, and a torn last line (a crash while appending) is ignored.
+
+use crate::{
+    error::Result,
+    io::output::{write_atomic, OverwritePolicy},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+const JOURNAL: &str = "journal.jsonl";
+const CHUNK_EXT: &str = "f32";
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    key: String,
+    stems: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkRecord {
+    chunk: usize,
+    start: usize,
+    len: usize,
+    sha256: String,
+}
+
+/// An open job directory.
+pub(crate) struct Checkpoint {
+    dir: PathBuf,
+    stems: usize,
+    chunks: HashMap<usize, ChunkRecord>,
+    journal: File,
+}
+
+impl Checkpoint {
+    /// Open the job directory `dir`, resuming its journal if it was written
+    /// for `key` and starting a new one otherwise.
+    pub(crate) fn open(dir: &Path, key: &str, stems: usize) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(JOURNAL);
+        let resumed = match fs::read_to_string(&path) {
+            Ok(text) => read_journal(&text, key, stems),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let chunks = match resumed {
+            Some(chunks) => chunks,
+            None => {
+                remove_chunks(dir)?;
+                HashMap::new()
+            }
+        };
+
+        // Rewrite the journal without a torn last line before appending
+        let mut records: Vec<&ChunkRecord> = chunks.values().collect();
+        records.sort_by_key(|r| r.chunk);
+        let header = Header {
+            key: key.to_string(),
+            stems,
+        };
+        write_atomic(&path, OverwritePolicy::Replace, |w| {
+            writeln!(w, "{}", serde_json::to_string(&header)?)?;
+            for record in records {
+                writeln!(w, "{}", serde_json::to_string(record)?)?;
+            }
+            Ok(())
+        })?;
+        let journal = File::options().append(true).open(&path)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            stems,
+            chunks,
+            journal,
+        })
+    }
+
+    /// Stored output of chunk `index`, if it finished with the same bounds
+    /// and its file is intact.
+    pub(crate) fn load(&self, index: usize, start: usize, len: usize) -> Option<Vec<Vec<[f32; 2]>>> {
+        let record = self.chunks.get(&index)?;
+        if (record.start, record.len) != (start, len) {
+            return None;
+        }
+        let bytes = fs::read(self.chunk_path(index)).ok()?;
+        if bytes.len() != self.stems * len * 8 || hex::encode(Sha256::digest(&bytes)) != record.sha256 {
+            return None;
+        }
+
+        let stems = bytes
+            .chunks_exact(len * 8)
+            .map(|stem| {
+                stem.chunks_exact(8)
+                    .map(|f| {
+                        [
+                            f32::from_le_bytes([f[0], f[1], f[2], f[3]]),
+                            f32::from_le_bytes([f[4], f[5], f[6], f[7]]),
+                        ]
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(stems)
+    }
+
+    /// Persist the first `len` frames of every stem of chunk `index`, then
+    /// record it in the journal.
+    pub(crate) fn store(
+        &mut self,
+        index: usize,
+        start: usize,
+        len: usize,
+        stems: &[Vec<[f32; 2]>],
+    ) -> Result<()> {
+        let mut bytes = Vec::with_capacity(self.stems * len * 8);
+        for stem in &stems[..self.stems] {
+            for s in &stem[..len] {
+                bytes.extend_from_slice(&s[0].to_le_bytes());
+                bytes.extend_from_slice(&s[1].to_le_bytes());
+            }
+        }
+        write_atomic(&self.chunk_path(index), OverwritePolicy::Replace, |w| {
+            w.write_all(&bytes)?;
+            Ok(())
+        })?;
+
+        let record = ChunkRecord {
+            chunk: index,
+            start,
+            len,
+            sha256: hex::encode(Sha256::digest(&bytes)),
+        };
+        writeln!(self.journal, "{}", serde_json::to_string(&record)?)?;
+        self.journal.sync_data()?;
+        self.chunks.insert(index, record);
+        Ok(())
+    }
+
+    /// Delete the journal and chunks after the separation finished, and the
+    /// job directory if nothing else is in it.
+    pub(crate) fn finish(self) -> Result<()> {
+        let Self { dir, journal, .. } = self;
+        drop(journal);
+        remove_chunks(&dir)?;
+        fs::remove_file(dir.join(JOURNAL))?;
+        let _ = fs::remove_dir(&dir);
+        Ok(())
+    }
+
+    fn chunk_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("chunk-{index:04}.{CHUNK_EXT}"))
+    }
+}
+
+/// Chunk records of a journal written for `key`, or `None` if it belongs
+/// to another job.
+fn read_journal(text: &str, key: &str, stems: usize) -> Option<HashMap<usize, ChunkRecord>> {
+    let mut lines = text.lines();
+    let header: Header = serde_json::from_str(lines.next()?).ok()?;
+    if header.key != key || header.stems != stems {
+        return None;
+    }
+    let mut chunks = HashMap::new();
+    for line in lines {
+        let Ok(record) = serde_json::from_str::<ChunkRecord>(line) else {
+            break;
+        };
+        chunks.insert(record.chunk, record);
+    }
+    Some(chunks)
+}
+
+fn remove_chunks(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_chunk = path.extension().and_then(|e| e.to_str()) == Some(CHUNK_EXT)
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("chunk-"));
+        if is_chunk {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
@@ -3,6 +3,12 @@ use sha2::{Digest, Sha256};
 use std::{fs::File, io::Read, path::Path};
 
 pub fn verify_sha256(path: &Path, expected_hex: &str) -> Result<bool> {
+    let got = sha256_file(path)?;
+    Ok(got.eq_ignore_ascii_case(expected_hex))
+}
+
+/// Hex SHA-256 of a file's contents.
+pub fn sha256_file(path: &Path) -> Result<String> {
     let mut f = File::open(path)?;
     let mut hasher = Sha256::new();
     let mut buf = [0u8; 64 * 1024];
@@ -13,6 +19,24 @@ pub fn verify_sha256(path: &Path, expected_hex: &str) -> Result<bool> {
         }
         hasher.update(&buf[..n]);
     }
-    let got = hex::encode(hasher.finalize());
-    Ok(got.eq_ignore_ascii_case(expected_hex))
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hex SHA-256 identifying a set of files by `(name, hex SHA-256)`,
+/// independent of the order they are given in. A single file is
+/// identified by its own hash.
+pub fn sha256_of_files(files: &[(String, String)]) -> String {
+    if let [(_, sha)] = files {
+        return sha.to_ascii_lowercase();
+    }
+    let mut sorted: Vec<_> = files.iter().collect();
+    sorted.sort();
+    let mut hasher = Sha256::new();
+    for (name, sha) in sorted {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(sha.to_ascii_lowercase().as_bytes());
+        hasher.update([b'\n']);
+    }
+    hex::encode(hasher.finalize())
 }
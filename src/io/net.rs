@@ -1,27 +1,197 @@
-use crate::{error::Result, io::progress::emit_download_progress};
-use reqwest::blocking::Client;
+use crate::{
+    error::{Result, StemError},
+    io::progress::emit_download_progress,
+    model::signing::signature_url,
+};
+use reqwest::{
+    blocking::{Client, RequestBuilder},
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION},
+    Proxy, StatusCode, Url,
+};
 use std::{
     fs,
     fs::File,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
     time::Duration,
 };
 
+/// HTTP settings for manifest and model downloads, set with
+/// [`set_net_config`].
+#[derive(Debug, Clone)]
+pub struct NetConfig {
+    /// Extra headers sent to [`auth_hosts`](Self::auth_hosts).
+    pub headers: Vec<(String, String)>,
+    /// Sent as `Authorization: Bearer <token>` to
+    /// [`auth_hosts`](Self::auth_hosts). Without it, the `HF_TOKEN`
+    /// environment variable is sent to Hugging Face hosts only.
+    pub bearer_token: Option<String>,
+    /// Hosts (and their subdomains) that get `headers` and `bearer_token`,
+    /// e.g. `models.example.com`. Mirrors and other hosts get neither.
+    pub auth_hosts: Vec<String>,
+    /// Proxy for all requests, e.g. `http://proxy:3128`. Without it the
+    /// `HTTPS_PROXY` / `HTTP_PROXY` environment variables apply.
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
+    /// Limit for a whole request, including downloading the body.
+    pub timeout: Duration,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            bearer_token: None,
+            auth_hosts: Vec::new(),
+            proxy: None,
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+fn net_config_slot() -> &'static Mutex<NetConfig> {
+    static CONFIG: OnceLock<Mutex<NetConfig>> = OnceLock::new();
+    CONFIG.get_or_init(Default::default)
+}
+
+/// Use `config` for all later downloads. Fails on an invalid proxy URL or
+/// header, or on headers or a token without `auth_hosts` to send them to.
+pub fn set_net_config(config: NetConfig) -> Result<()> {
+    let has_credentials = !config.headers.is_empty() || config.bearer_token.is_some();
+    if has_credentials && config.auth_hosts.iter().all(|h| h.is_empty()) {
+        return Err(net_error(
+            "headers and bearer_token need auth_hosts to send them to".into(),
+        ));
+    }
+    if let Some(proxy) = &config.proxy {
+        Proxy::all(proxy).map_err(|e| net_error(format!("invalid proxy {proxy}: {e}")))?;
+    }
+    for (name, value) in &config.headers {
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| net_error(format!("invalid header name {name}: {e}")))?;
+        HeaderValue::from_str(value)
+            .map_err(|e| net_error(format!("invalid value for header {name}: {e}")))?;
+    }
+    *net_config_slot().lock().unwrap() = config;
+    Ok(())
+}
+
+pub(crate) fn net_config() -> NetConfig {
+    net_config_slot().lock().unwrap().clone()
+}
+
+fn net_error(msg: String) -> StemError {
+    StemError::Anyhow(anyhow::anyhow!(msg))
+}
+
 pub fn http_client() -> Client {
-    Client::builder()
-        .connect_timeout(Duration::from_secs(10))
-        .timeout(Duration::from_secs(60 * 60))
-        .build()
-        .expect("reqwest client build failed")
+    let config = net_config();
+    let mut builder = Client::builder()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.timeout);
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(Proxy::all(proxy).expect("proxy validated by set_net_config"));
+    }
+    builder.build().expect("reqwest client build failed")
+}
+
+/// Whether `host` is one of `domains` or a subdomain of one.
+fn host_matches<S: AsRef<str>>(host: &str, domains: &[S]) -> bool {
+    domains.iter().any(|d| {
+        let d = d.as_ref().trim_end_matches('.').to_ascii_lowercase();
+        !d.is_empty() && (host == d || host.ends_with(&format!(".{d}")))
+    })
+}
+
+/// Headers to send to `url`: the configured ones and token for
+/// `auth_hosts`, `HF_TOKEN` for Hugging Face.
+pub(crate) fn request_headers(url: &str) -> HeaderMap {
+    let config = net_config();
+    let mut headers = HeaderMap::new();
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+    else {
+        return headers;
+    };
+
+    let trusted = host_matches(&host, &config.auth_hosts);
+    if trusted {
+        for (name, value) in &config.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+    }
+
+    let token = config.bearer_token.filter(|_| trusted).or_else(|| {
+        host_matches(&host, &["huggingface.co", "hf.co"])
+            .then(|| std::env::var("HF_TOKEN").ok())
+            .flatten()
+            .filter(|t| !t.is_empty())
+    });
+    if let Some(value) = token.and_then(|t| HeaderValue::from_str(&format!("Bearer {t}")).ok()) {
+        headers.insert(AUTHORIZATION, value);
+    }
+    headers
+}
+
+/// Local path of a `file://` URL.
+pub(crate) fn file_url_path(url: &str) -> Option<PathBuf> {
+    let url = Url::parse(url).ok()?;
+    (url.scheme() == "file").then(|| url.to_file_path().ok())?
+}
+
+fn get(client: &Client, url: &str) -> RequestBuilder {
+    client.get(url).headers(request_headers(url))
+}
+
+/// Body of `url` (`http(s)://` or `file://`).
+pub(crate) fn fetch_bytes(client: &Client, url: &str) -> Result<Vec<u8>> {
+    if let Some(path) = file_url_path(url) {
+        return Ok(fs::read(path)?);
+    }
+    Ok(get(client, url).send()?.error_for_status()?.bytes()?.to_vec())
+}
+
+/// Detached signature of the manifest at `manifest_url`, or `None` if
+/// there is none (404 or a missing file).
+pub(crate) fn fetch_manifest_signature(client: &Client, manifest_url: &str) -> Result<Option<String>> {
+    let url = signature_url(manifest_url)?;
+    if let Some(path) = file_url_path(&url) {
+        return match fs::read_to_string(path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+    let resp = get(client, &url).send()?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(resp.error_for_status()?.text()?))
 }
 
 pub fn download_with_progress(client: &Client, url: &str, dest: &Path) -> Result<()> {
     let tmp = dest.with_extension("part");
 
-    let mut resp = client.get(url).send()?.error_for_status()?; // both now convert automatically
-
-    let total = resp.content_length().unwrap_or(0);
+    let (mut source, total): (Box<dyn Read>, u64) = match file_url_path(url) {
+        Some(path) => {
+            let file = File::open(path)?;
+            let len = file.metadata()?.len();
+            (Box::new(file), len)
+        }
+        None => {
+            let resp = get(client, url).send()?.error_for_status()?;
+            let len = resp.content_length().unwrap_or(0);
+            (Box::new(resp), len)
+        }
+    };
 
     emit_download_progress(0, total);
 
@@ -29,7 +199,7 @@ pub fn download_with_progress(client: &Client, url: &str, dest: &Path) -> Result
     let mut downloaded: u64 = 0;
     let mut buf = [0u8; 64 * 1024];
     loop {
-        let n = resp.read(&mut buf)?;
+        let n = source.read(&mut buf)?;
         if n == 0 {
             break;
         }
@@ -49,3 +219,93 @@ pub fn download_with_progress(client: &Client, url: &str, dest: &Path) -> Result
 
     Ok(())
 }
+
+/// Async counterparts of the fetch and download functions, used by
+/// [`crate::nonblocking`]. They send the same headers and honor the same
+/// [`NetConfig`].
+#[cfg(feature = "async")]
+pub(crate) mod nonblocking {
+    use super::{file_url_path, net_config, request_headers, signature_url};
+    use crate::{error::Result, io::progress::emit_download_progress};
+    use reqwest::{Client, Proxy, RequestBuilder, StatusCode};
+    use std::path::Path;
+    use tokio::{fs, io::AsyncWriteExt};
+
+    pub(crate) fn http_client() -> Client {
+        let config = net_config();
+        let mut builder = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout);
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(Proxy::all(proxy).expect("proxy validated by set_net_config"));
+        }
+        builder.build().expect("reqwest client build failed")
+    }
+
+    fn get(client: &Client, url: &str) -> RequestBuilder {
+        client.get(url).headers(request_headers(url))
+    }
+
+    /// Body of `url` (`http(s)://` or `file://`).
+    pub(crate) async fn fetch_bytes(client: &Client, url: &str) -> Result<Vec<u8>> {
+        if let Some(path) = file_url_path(url) {
+            return Ok(fs::read(path).await?);
+        }
+        let resp = get(client, url).send().await?.error_for_status()?;
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// Detached signature of the manifest at `manifest_url`, or `None` if
+    /// there is none (404 or a missing file).
+    pub(crate) async fn fetch_manifest_signature(
+        client: &Client,
+        manifest_url: &str,
+    ) -> Result<Option<String>> {
+        let url = signature_url(manifest_url)?;
+        if let Some(path) = file_url_path(&url) {
+            return match fs::read_to_string(path).await {
+                Ok(text) => Ok(Some(text)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            };
+        }
+        let resp = get(client, &url).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(resp.error_for_status()?.text().await?))
+    }
+
+    /// Stream `url` into `dest` through a `.part` file, reporting progress
+    /// like [`download_with_progress`](super::download_with_progress).
+    pub(crate) async fn download_with_progress(client: &Client, url: &str, dest: &Path) -> Result<()> {
+        let tmp = dest.with_extension("part");
+
+        if let Some(path) = file_url_path(url) {
+            let total = fs::metadata(&path).await?.len();
+            emit_download_progress(0, total);
+            fs::copy(&path, &tmp).await?;
+            emit_download_progress(total, total);
+        } else {
+            let mut resp = get(client, url).send().await?.error_for_status()?;
+            let total = resp.content_length().unwrap_or(0);
+            emit_download_progress(0, total);
+
+            let mut file = fs::File::create(&tmp).await?;
+            let mut downloaded: u64 = 0;
+            while let Some(chunk) = resp.chunk().await? {
+                file.write_all(&chunk).await?;
+                downloaded += chunk.len() as u64;
+                emit_download_progress(downloaded, total);
+            }
+            file.flush().await?;
+            emit_download_progress(total.max(downloaded), total.max(downloaded));
+        }
+
+        if fs::try_exists(dest).await.unwrap_or(false) {
+            fs::remove_file(dest).await.ok();
+        }
+        fs::rename(&tmp, dest).await?;
+        Ok(())
+    }
+}
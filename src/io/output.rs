@@ -0,0 +1,179 @@
+use crate::error::{Result, StemError};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Component, Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+/// Default output file name: `{input}_{stem}.{ext}` in `output_dir`.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{input}_{stem}.{ext}";
+
+/// What to do when an output file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// Fail before separating if any output file exists.
+    Error,
+    /// Keep the existing file and don't write the stem.
+    Skip,
+    /// Atomically replace the existing file.
+    #[default]
+    Replace,
+    /// Write to `name_1.wav`, `name_2.wav`, ... instead. All outputs of a
+    /// job share the same suffix.
+    UniqueSuffix,
+}
+
+/// Expand a filename template. Supported placeholders are `{input}`,
+/// `{stem}`, `{model}` and `{ext}`; `/` in the template creates
+/// subdirectories under `output_dir`. Fails if the result would leave
+/// `output_dir` (a `..` or drive component).
+pub fn render_filename(
+    output_dir: &str,
+    template: &str,
+    input: &str,
+    stem: &str,
+    model: &str,
+    ext: &str,
+) -> Result<PathBuf> {
+    let rendered = template
+        .replace("{input}", input)
+        .replace("{stem}", stem)
+        .replace("{model}", model)
+        .replace("{ext}", ext);
+    let mut path = PathBuf::from(output_dir);
+    for part in rendered.split(['/', '\\']).filter(|p| !p.is_empty()) {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => path.push(part),
+            (Some(Component::CurDir), None) => {}
+            _ => {
+                return Err(StemError::Output(format!(
+                    "output file name {rendered:?} leaves the output directory"
+                )))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Fail early if the policy is `Error` and a destination already exists.
+pub fn check_destinations(paths: &[PathBuf], policy: OverwritePolicy) -> Result<()> {
+    for (i, p) in paths.iter().enumerate() {
+        if paths[..i].contains(p) {
+            return Err(StemError::Output(format!(
+                "filename template maps several stems to {}",
+                p.display()
+            )));
+        }
+        if policy == OverwritePolicy::Error && p.exists() {
+            return Err(StemError::OutputExists {
+                path: p.display().to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Write `dest` atomically: `write` fills a temporary file in the same
+/// directory, which is then renamed into place according to `policy`.
+///
+/// Returns the path actually written (which differs from `dest` for
+/// `UniqueSuffix`), or `dest` unchanged if the policy is `Skip` and it exists.
+pub fn write_atomic(
+    dest: &Path,
+    policy: OverwritePolicy,
+    write: impl FnOnce(&mut BufWriter<&File>) -> Result<()>,
+) -> Result<PathBuf> {
+    if policy == OverwritePolicy::Skip && dest.exists() {
+        return Ok(dest.to_path_buf());
+    }
+
+    let dir = match dest.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    let tmp = NamedTempFile::new_in(dir)?;
+    {
+        let mut w = BufWriter::new(tmp.as_file());
+        write(&mut w)?;
+        w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    }
+
+    match policy {
+        OverwritePolicy::Replace => {
+            tmp.persist(dest).map_err(|e| e.error)?;
+            Ok(dest.to_path_buf())
+        }
+        OverwritePolicy::Error | OverwritePolicy::Skip => match tmp.persist_noclobber(dest) {
+            Ok(_) => Ok(dest.to_path_buf()),
+            Err(e) if e.error.kind() == std::io::ErrorKind::AlreadyExists => {
+                if policy == OverwritePolicy::Skip {
+                    Ok(dest.to_path_buf())
+                } else {
+                    Err(StemError::OutputExists {
+                        path: dest.display().to_string(),
+                    })
+                }
+            }
+            Err(e) => Err(e.error.into()),
+        },
+        OverwritePolicy::UniqueSuffix => {
+            let mut tmp = tmp;
+            for n in 0..=MAX_UNIQUE_SUFFIX {
+                let candidate = with_suffix(dest, n);
+                match tmp.persist_noclobber(&candidate) {
+                    Ok(_) => return Ok(candidate),
+                    Err(e) if e.error.kind() == std::io::ErrorKind::AlreadyExists => {
+                        tmp = e.file;
+                    }
+                    Err(e) => return Err(e.error.into()),
+                }
+            }
+            Err(no_free_suffix(dest))
+        }
+    }
+}
+
+/// Highest `_n` suffix tried by [`OverwritePolicy::UniqueSuffix`].
+pub const MAX_UNIQUE_SUFFIX: u32 = 9999;
+
+/// Apply one `_n` suffix to all `paths`: the lowest one (none at all if
+/// possible) for which none of the suffixed paths exists, so the files of
+/// one job keep matching names.
+pub fn unique_destinations(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    for n in 0..=MAX_UNIQUE_SUFFIX {
+        let candidates: Vec<PathBuf> = paths.iter().map(|p| with_suffix(p, n)).collect();
+        if !candidates.iter().any(|p| p.exists()) {
+            return Ok(candidates);
+        }
+    }
+    Err(no_free_suffix(paths.first().map_or(Path::new(""), |p| p.as_path())))
+}
+
+fn no_free_suffix(dest: &Path) -> StemError {
+    StemError::Output(format!(
+        "no free name for {}: suffixes up to _{MAX_UNIQUE_SUFFIX} are taken",
+        dest.display()
+    ))
+}
+
+/// `dir/name.ext` -> `dir/name_{n}.ext`; `n == 0` leaves the path as is
+fn with_suffix(path: &Path, n: u32) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}_{n}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{n}"),
+    };
+    path.with_file_name(name)
+}
@@ -2,10 +2,35 @@ use crate::error::{Result, StemError};
 use directories::ProjectDirs;
 use std::path::PathBuf;
 
-pub fn models_cache_dir() -> Result<PathBuf> {
+/// Environment variable overriding the cache root (models and results).
+pub const CACHE_DIR_ENV: &str = "STEM_SPLITTER_CACHE_DIR";
+
+fn cache_root() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
     let proj = ProjectDirs::from("dev", "StemSplitter", "stem-splitter-core")
         .ok_or(StemError::CacheDirUnavailable)?;
-    let mut p = PathBuf::from(proj.cache_dir());
+    Ok(PathBuf::from(proj.cache_dir()))
+}
+
+pub fn models_cache_dir() -> Result<PathBuf> {
+    let mut p = cache_root()?;
     p.push("models");
     Ok(p)
 }
+
+/// `custom` if set, otherwise [`models_cache_dir`].
+pub(crate) fn models_dir(custom: Option<&str>) -> Result<PathBuf> {
+    match custom {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => models_cache_dir(),
+    }
+}
+
+/// Default location of the separation result cache.
+pub fn results_cache_dir() -> Result<PathBuf> {
+    let mut p = cache_root()?;
+    p.push("results");
+    Ok(p)
+}
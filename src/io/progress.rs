@@ -20,9 +20,51 @@ pub enum SplitProgress {
         total: usize,
         percent: f32,
     },
+    /// Time spent in each stage of the window pipeline, sent after inference.
+    Timing(PipelineTiming),
     Finished,
 }
 
+/// Busy time of each window pipeline stage and the wall-clock time of the
+/// whole inference. The stages run concurrently, so their sum is what a
+/// serial loop would have taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct PipelineTiming {
+    /// STFT and input tensor preparation.
+    pub prepare_secs: f64,
+    /// Model inference.
+    pub infer_secs: f64,
+    /// iSTFT and combining the model branches.
+    pub finish_secs: f64,
+    pub wall_secs: f64,
+    /// Number of windows processed.
+    pub windows: usize,
+}
+
+impl PipelineTiming {
+    /// Estimated time of the same work without pipelining.
+    pub fn serial_secs(&self) -> f64 {
+        self.prepare_secs + self.infer_secs + self.finish_secs
+    }
+
+    /// Speedup of the pipeline over a serial loop.
+    pub fn speedup(&self) -> f64 {
+        if self.wall_secs > 0.0 {
+            self.serial_secs() / self.wall_secs
+        } else {
+            1.0
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: &PipelineTiming) {
+        self.prepare_secs += other.prepare_secs;
+        self.infer_secs += other.infer_secs;
+        self.finish_secs += other.finish_secs;
+        self.wall_secs += other.wall_secs;
+        self.windows += other.windows;
+    }
+}
+
 pub fn set_download_progress_callback(cb: impl Fn(u64, u64) + Send + 'static) {
     let _ = DOWNLOAD_PROGRESS_CB.set(Mutex::new(Some(Box::new(cb))));
 }
@@ -41,7 +83,38 @@ pub fn set_split_progress_callback(cb: impl Fn(SplitProgress) + Send + 'static)
     let _ = SPLIT_PROGRESS_CB.set(Mutex::new(Some(Box::new(cb))));
 }
 
+type SplitSink = Box<dyn Fn(SplitProgress)>;
+
+thread_local! {
+    /// Receiver of the split progress of the job running on this thread.
+    static SPLIT_SINK: std::cell::RefCell<Option<SplitSink>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Run `f` with `sink` receiving the split progress emitted on this thread
+/// (in addition to the global callback).
+#[cfg(any(feature = "async", feature = "capi"))]
+pub(crate) fn with_split_progress_sink<R>(
+    sink: impl Fn(SplitProgress) + 'static,
+    f: impl FnOnce() -> R,
+) -> R {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            SPLIT_SINK.with(|s| *s.borrow_mut() = None);
+        }
+    }
+
+    SPLIT_SINK.with(|s| *s.borrow_mut() = Some(Box::new(sink)));
+    let _reset = Reset;
+    f()
+}
+
 pub fn emit_split_progress(p: SplitProgress) {
+    SPLIT_SINK.with(|s| {
+        if let Some(sink) = &*s.borrow() {
+            sink(p.clone());
+        }
+    });
     if let Some(m) = SPLIT_PROGRESS_CB.get() {
         if let Ok(g) = m.lock() {
             if let Some(cb) = &*g {
@@ -0,0 +1,343 @@
+//! Content-addressed cache of separation results.
+//!
+//! Entries are keyed by a SHA-256 over the decoded input audio, the model's
+//! SHA-256 and the options that affect the separated samples, so a renamed
+//! or re-tagged copy of a track still hits. Each entry is one `<key>.stems`
+//! file; the least recently used entries are evicted once the cache grows
+//! past its size limit.
+
+use crate::{
+    error::{Result, StemError},
+    io::{
+        crypto::{sha256_file, sha256_of_files},
+        output::{write_atomic, OverwritePolicy},
+        paths::results_cache_dir,
+    },
+    model::{model_manager::ModelHandle, onnx_info::read_onnx_info},
+};
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+const MAGIC: &[u8; 8] = b"SSRC\x01\0\0\0";
+/// Version of the signal processing (STFT/iSTFT, overlap-add, handling of
+/// the model outputs), part of every key. Bump it with any change that
+/// alters separated samples, so results of an older build are recomputed.
+const DSP_VERSION: u32 = 2;
+const EXT: &str = "stems";
+
+/// Result cache settings. Caching is enabled by setting
+/// [`SplitOptions::cache`](crate::SplitOptions::cache).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheOptions {
+    /// Total size limit in bytes. Least recently used entries are evicted
+    /// to stay below it.
+    pub max_bytes: u64,
+    /// Cache directory. Defaults to `results` next to the model cache.
+    pub dir: Option<String>,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 2 * 1024 * 1024 * 1024,
+            dir: None,
+        }
+    }
+}
+
+/// Separated stems as stored in the cache.
+pub(crate) struct CachedStems {
+    pub names: Vec<String>,
+    pub sample_rate: u32,
+    pub start_sample: u64,
+    pub acc: Vec<Vec<[f32; 2]>>,
+}
+
+/// Handle to a result cache directory.
+pub struct ResultCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ResultCache {
+    pub fn open(opts: &CacheOptions) -> Result<Self> {
+        let dir = match &opts.dir {
+            Some(d) => PathBuf::from(d),
+            None => results_cache_dir()?,
+        };
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes: opts.max_bytes,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Total size of all entries in bytes.
+    pub fn size_bytes(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|(_, len, _)| len).sum())
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> Result<()> {
+        for (path, _, _) in self.entries()? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Look up an entry, marking it as recently used. Unreadable or
+    /// truncated entries are removed and reported as a miss.
+    pub(crate) fn get(&self, key: &str) -> Option<CachedStems> {
+        let path = self.entry_path(key);
+        let bytes = fs::read(&path).ok()?;
+        match decode(&bytes) {
+            Some(stems) => {
+                let _ = filetime::set_file_mtime(&path, FileTime::now());
+                Some(stems)
+            }
+            None => {
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Store an entry and evict old ones to respect the size limit.
+    /// Entries larger than the whole cache are not stored.
+    pub(crate) fn put(&self, key: &str, stems: &CachedStems) -> Result<()> {
+        let bytes = encode(stems)?;
+        if bytes.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        write_atomic(&self.entry_path(key), OverwritePolicy::Replace, |w| {
+            w.write_all(&bytes)?;
+            Ok(())
+        })?;
+        self.evict(key)
+    }
+
+    /// Delete least recently used entries (never `keep`) until the cache
+    /// fits in `max_bytes`.
+    fn evict(&self, keep: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(_, _, used)| *used);
+        let keep = self.entry_path(keep);
+        for (path, len, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path != keep && fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+
+    /// (path, size, last use) of every entry.
+    fn entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut out = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXT) {
+                continue;
+            }
+            let Ok(meta) = fs::metadata(&path) else { continue };
+            let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            out.push((path, meta.len(), used));
+        }
+        Ok(out)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{EXT}"))
+    }
+}
+
+/// Cache key for a separation: decoded input samples, model identity and
+/// the parameters that influence the output (`params`, serialized as JSON).
+pub(crate) fn cache_key<P: Serialize>(
+    samples: &[[f32; 2]],
+    model_sha256: &str,
+    params: &P,
+) -> Result<String> {
+    let mut h = Sha256::new();
+    h.update(MAGIC);
+    h.update(DSP_VERSION.to_le_bytes());
+    h.update(model_sha256.as_bytes());
+    h.update(serde_json::to_vec(params)?);
+    h.update((samples.len() as u64).to_le_bytes());
+    for s in samples {
+        h.update(s[0].to_le_bytes());
+        h.update(s[1].to_le_bytes());
+    }
+    Ok(hex::encode(h.finalize()))
+}
+
+/// SHA-256 identifying a model: over the name and hash of every file it
+/// is made of (see [`sha256_of_files`]), so replacing any of them, e.g. the
+/// weights next to an ONNX graph, changes it. Hashes the manifest declares
+/// are used as is. A local model without them is identified by its file
+/// and the external data files its graph refers to, hashed from disk.
+pub(crate) fn model_sha256(handle: &ModelHandle) -> Result<String> {
+    let artifacts = handle.manifest.resolve_artifacts().unwrap_or_default();
+    if !artifacts.is_empty() && artifacts.iter().all(|a| !a.sha256.is_empty()) {
+        let files: Vec<_> = artifacts.into_iter().map(|a| (a.file, a.sha256)).collect();
+        return Ok(sha256_of_files(&files));
+    }
+
+    let path = &handle.local_path;
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+    let mut files = vec![(name, memoized_sha256(path)?)];
+
+    // Artifacts are relative to the directory the entry file is in
+    let depth = artifacts.first().map_or(1, |entry| {
+        Path::new(&entry.file)
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .count()
+    });
+    let root = path.ancestors().nth(depth).unwrap_or(Path::new(""));
+    for a in artifacts.iter().skip(1) {
+        let sha = match a.sha256.as_str() {
+            "" => memoized_sha256(&root.join(&a.file))?,
+            sha => sha.to_string(),
+        };
+        files.push((a.file.clone(), sha));
+    }
+
+    // Weights stored outside the graph; ORT format models have none to read
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let external = read_onnx_info(path)
+        .map(|info| info.external_data)
+        .unwrap_or_default();
+    for location in external {
+        if !files
+            .iter()
+            .any(|(file, _)| Path::new(file) == Path::new(&location))
+        {
+            let sha = memoized_sha256(&dir.join(&location))?;
+            files.push((location, sha));
+        }
+    }
+    Ok(sha256_of_files(&files))
+}
+
+/// SHA-256 of a file, memoized per path, size and mtime.
+fn memoized_sha256(path: &Path) -> Result<String> {
+    type Memo = HashMap<PathBuf, (u64, Option<SystemTime>, String)>;
+    static MEMO: OnceLock<Mutex<Memo>> = OnceLock::new();
+
+    let meta = fs::metadata(path)?;
+    let stamp = (meta.len(), meta.modified().ok());
+    let memo = MEMO.get_or_init(Default::default);
+    if let Some((len, mtime, sha)) = memo.lock().unwrap().get(path) {
+        if (*len, *mtime) == stamp {
+            return Ok(sha.clone());
+        }
+    }
+    let sha = sha256_file(path)?;
+    memo.lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (stamp.0, stamp.1, sha.clone()));
+    Ok(sha)
+}
+
+fn encode(stems: &CachedStems) -> Result<Vec<u8>> {
+    let names = serde_json::to_vec(&stems.names)?;
+    let n = stems.acc.first().map_or(0, |s| s.len());
+    if stems.acc.iter().any(|s| s.len() != n) {
+        return Err(StemError::Anyhow(anyhow::anyhow!(
+            "stems have different lengths"
+        )));
+    }
+
+    let mut out = Vec::with_capacity(36 + names.len() + stems.acc.len() * n * 8);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&stems.sample_rate.to_le_bytes());
+    out.extend_from_slice(&(stems.acc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(n as u64).to_le_bytes());
+    out.extend_from_slice(&stems.start_sample.to_le_bytes());
+    out.extend_from_slice(&(names.len() as u32).to_le_bytes());
+    out.extend_from_slice(&names);
+    for stem in &stems.acc {
+        for s in stem {
+            out.extend_from_slice(&s[0].to_le_bytes());
+            out.extend_from_slice(&s[1].to_le_bytes());
+        }
+    }
+    Ok(out)
+}
+
+fn decode(bytes: &[u8]) -> Option<CachedStems> {
+    let mut r = Reader(bytes);
+    if r.take(8)? != MAGIC {
+        return None;
+    }
+    let sample_rate = u32::from_le_bytes(r.take(4)?.try_into().ok()?);
+    let count = u32::from_le_bytes(r.take(4)?.try_into().ok()?) as usize;
+    let n = u64::from_le_bytes(r.take(8)?.try_into().ok()?) as usize;
+    let start_sample = u64::from_le_bytes(r.take(8)?.try_into().ok()?);
+    let names_len = u32::from_le_bytes(r.take(4)?.try_into().ok()?) as usize;
+    let names: Vec<String> = serde_json::from_slice(r.take(names_len)?).ok()?;
+    if n == 0 {
+        return None;
+    }
+
+    let data = r.take(count.checked_mul(n)?.checked_mul(8)?)?;
+    if !r.0.is_empty() {
+        return None;
+    }
+    let acc = data
+        .chunks_exact(n * 8)
+        .map(|stem| {
+            stem.chunks_exact(8)
+                .map(|f| {
+                    [
+                        f32::from_le_bytes([f[0], f[1], f[2], f[3]]),
+                        f32::from_le_bytes([f[4], f[5], f[6], f[7]]),
+                    ]
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    if acc.len() != count {
+        return None;
+    }
+
+    Some(CachedStems {
+        names,
+        sample_rate,
+        start_sample,
+        acc,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+}
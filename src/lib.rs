@@ -5,34 +5,79 @@ pub mod core {
     pub mod audio;
     pub mod dsp;
     pub mod engine;
+    pub mod export;
+    pub mod loudness;
+    pub mod metadata;
+    pub mod mixer;
     pub mod splitter;
+    pub mod test_engine;
 }
 
 pub mod model {
+    pub mod cache;
     pub mod model_manager;
+    pub mod onnx_info;
     pub mod registry;
+    pub mod signing;
 }
 
 pub mod io {
+    pub mod checkpoint;
     pub mod crypto;
     pub mod net;
+    pub mod output;
     pub mod paths;
     pub mod progress;
+    pub mod result_cache;
 }
 
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "server")]
+pub mod server;
+
 // Public API
 pub use crate::core::splitter::{
     split_file, remove_vocals, VocalRemovalResult,
-    Separator, SeparatedStems, Stem,
+    Separator, SeparatedStems, Stem, ReaperExport,
 };
+pub use crate::core::dsp::{PadMode, Stft, StftOptions, WindowKind};
+pub use crate::core::engine::{EngineConfig, ExecutionProviderKind, OptimizationLevel};
+pub use crate::core::test_engine::{StemScript, TestEngine, WindowRequest};
+pub use crate::core::loudness::{LoudnessStats, NormalizeOptions};
+pub use crate::core::metadata::{AudioMetadata, Picture};
+pub use crate::core::mixer::{LimiterSpec, MixSpec, StemMix};
+pub use crate::io::net::{set_net_config, NetConfig};
+pub use crate::io::output::OverwritePolicy;
+pub use crate::io::result_cache::{CacheOptions, ResultCache};
 pub use crate::io::progress::{
-    set_download_progress_callback, set_split_progress_callback, SplitProgress,
+    set_download_progress_callback, set_split_progress_callback, PipelineTiming, SplitProgress,
+};
+pub use crate::model::model_manager::{
+    ensure_model, ensure_model_in, load_model_from_path, load_model_from_path_with_manifest,
+    ModelHandle,
+};
+pub use crate::model::cache::{CachedModel, ModelCache, PruneReport};
+pub use crate::model::registry::{list_models, RegistryEntry};
+pub use crate::model::signing::{set_strict_manifest_verification, trust_manifest_key};
+pub use crate::types::{
+    AudioData, CancelToken, ModelManifest, SplitOptions, SplitResult, TensorDType, TimePosition,
 };
-pub use crate::model::model_manager::{ensure_model, load_model_from_path, ModelHandle};
-pub use crate::types::{AudioData, ModelManifest, SplitOptions, SplitResult};
 
 pub fn prepare_model(model_name: &str, manifest_url_override: Option<&str>) -> error::Result<()> {
+    prepare_model_with_config(model_name, manifest_url_override, &EngineConfig::default())
+}
+
+/// Like [`prepare_model`], creating the inference session with `config`.
+pub fn prepare_model_with_config(
+    model_name: &str,
+    manifest_url_override: Option<&str>,
+    config: &EngineConfig,
+) -> error::Result<()> {
     let handle = ensure_model(model_name, manifest_url_override)?;
-    crate::core::engine::preload(&handle)?;
+    crate::core::engine::preload_with_config(&handle, config)?;
     Ok(())
 }
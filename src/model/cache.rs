@@ -0,0 +1,304 @@
+//! Management of the downloaded model cache.
+//!
+//! [`ensure_model`](crate::ensure_model) caches each model version as
+//! `{name}-{sha8}.{ext}`, or as a `{name}-{sha8}` directory when it has
+//! several artifacts, with its manifest in `{file}.manifest.json`, whose
+//! modification time tracks when the model was last used, and a
+//! `{file}.lock` file that downloads take. The manifest is written once a
+//! download is complete, so only entries that have one are listed. Nothing
+//! is ever deleted automatically; [`ModelCache`] lists, verifies and prunes
+//! entries, taking the same lock so a model isn't deleted while it is
+//! being downloaded. Other files in the directory are left alone.
+
+use crate::{
+    error::Result,
+    io::{crypto::sha256_file, paths::models_dir},
+    model::model_manager::{lock_model, manifest_sidecar},
+    types::ModelManifest,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const PARTIAL_EXT: &str = "part";
+const LOCK_EXT: &str = "lock";
+const SIDECAR_SUFFIX: &str = ".manifest.json";
+
+/// A model file (or multi-file model directory) in the cache.
+#[derive(Debug, Clone)]
+pub struct CachedModel {
+    pub path: PathBuf,
+    /// Model name, from the file name.
+    pub name: String,
+    /// First 8 hex digits of the model's (entry artifact's) SHA-256, from
+    /// the file name.
+    pub sha256_prefix: String,
+    /// Size of the file, or of all files in the directory.
+    pub size_bytes: u64,
+    /// Last time `ensure_model` returned this file.
+    pub last_used: SystemTime,
+    /// Manifest the model was downloaded with, or `None` if its file can't
+    /// be parsed.
+    pub manifest: Option<ModelManifest>,
+}
+
+impl CachedModel {
+    /// Whether the file's SHA-256 matches its manifest (or, when that can't
+    /// be parsed, the prefix in its file name). A directory is intact when every
+    /// artifact in its manifest is; without a manifest it can't be checked
+    /// and fails.
+    pub fn verify(&self) -> Result<bool> {
+        if self.path.is_dir() {
+            let Some(artifacts) = self.manifest.as_ref().and_then(|m| m.resolve_artifacts().ok())
+            else {
+                return Ok(false);
+            };
+            for a in artifacts {
+                let path = self.path.join(&a.file);
+                if !path.is_file() || sha256_file(&path)? != a.sha256.to_ascii_lowercase() {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        let sha = sha256_file(&self.path)?;
+        let expected = self
+            .manifest
+            .as_ref()
+            .and_then(|m| m.resolve_primary_artifact().ok())
+            .map(|a| a.sha256.to_ascii_lowercase());
+        Ok(match expected {
+            Some(expected) => sha == expected,
+            None => sha.starts_with(&self.sha256_prefix.to_ascii_lowercase()),
+        })
+    }
+}
+
+/// Files removed by a prune and the space they took.
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+/// Handle to a model cache directory.
+pub struct ModelCache {
+    dir: PathBuf,
+}
+
+impl ModelCache {
+    /// Open `dir`, or the default models directory when `None` (the one
+    /// `ensure_model` uses, honoring `STEM_SPLITTER_CACHE_DIR`).
+    pub fn open(dir: Option<&str>) -> Result<Self> {
+        let dir = models_dir(dir)?;
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached models, by name and then most recently used first.
+    pub fn list(&self) -> Result<Vec<CachedModel>> {
+        let mut models = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if let Some(model) = read_entry(&path) {
+                models.push(model);
+            }
+        }
+        models.sort_by(|a, b| a.name.cmp(&b.name).then(b.last_used.cmp(&a.last_used)));
+        Ok(models)
+    }
+
+    /// Total size of the cached models in bytes.
+    pub fn size_bytes(&self) -> Result<u64> {
+        Ok(self.list()?.iter().map(|m| m.size_bytes).sum())
+    }
+
+    /// Every cached model with whether its checksum is intact.
+    pub fn verify(&self) -> Result<Vec<(CachedModel, bool)>> {
+        self.list()?
+            .into_iter()
+            .map(|m| {
+                let ok = m.verify()?;
+                Ok((m, ok))
+            })
+            .collect()
+    }
+
+    /// Delete a cached model and its manifest, waiting for a download of
+    /// it to finish first.
+    pub fn remove(&self, model: &CachedModel) -> Result<()> {
+        let _lock = lock_model(&model.path)?;
+        delete_entry(model)
+    }
+
+    /// Keep the `keep` most recently used versions of each model and
+    /// delete the rest. Models used while pruning are kept.
+    pub fn prune_versions(&self, keep: usize) -> Result<PruneReport> {
+        let mut report = PruneReport::default();
+        let mut seen: Option<(String, usize)> = None;
+        for model in self.list()? {
+            let count = match &mut seen {
+                Some((name, count)) if *name == model.name => {
+                    *count += 1;
+                    *count
+                }
+                _ => {
+                    seen = Some((model.name.clone(), 1));
+                    1
+                }
+            };
+            if count > keep {
+                self.remove_into(&model, &mut report)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Delete least recently used models until the cache fits in
+    /// `max_bytes`. Models used while pruning are kept.
+    pub fn prune_to_size(&self, max_bytes: u64) -> Result<PruneReport> {
+        let mut report = PruneReport::default();
+        let mut models = self.list()?;
+        let mut total: u64 = models.iter().map(|m| m.size_bytes).sum();
+        models.sort_by_key(|m| m.last_used);
+        for model in models {
+            if total <= max_bytes {
+                break;
+            }
+            total -= model.size_bytes;
+            self.remove_into(&model, &mut report)?;
+        }
+        Ok(report)
+    }
+
+    /// Delete `.part` files left by interrupted downloads. Don't call this
+    /// while another process is downloading into the cache.
+    pub fn remove_partial_downloads(&self) -> Result<PruneReport> {
+        let mut report = PruneReport::default();
+        remove_partial_in(&self.dir, true, &mut report)?;
+        Ok(report)
+    }
+
+    fn remove_into(&self, model: &CachedModel, report: &mut PruneReport) -> Result<()> {
+        let _lock = lock_model(&model.path)?;
+        // Gone, or used by `ensure_model` since it was listed
+        match read_entry(&model.path) {
+            Some(current) if current.last_used <= model.last_used => {}
+            _ => return Ok(()),
+        }
+        delete_entry(model)?;
+        report.freed_bytes += model.size_bytes;
+        report.removed.push(model.path.clone());
+        Ok(())
+    }
+}
+
+/// Delete a model's file or directory and its manifest. The caller holds
+/// its lock, which is left in place for anyone waiting on it.
+fn delete_entry(model: &CachedModel) -> Result<()> {
+    if model.path.is_dir() {
+        fs::remove_dir_all(&model.path)?;
+    } else {
+        fs::remove_file(&model.path)?;
+    }
+    let _ = fs::remove_file(manifest_sidecar(&model.path));
+    Ok(())
+}
+
+/// Delete `.part` files in `dir` and, from the cache's top level, in the
+/// model directories inside it.
+fn remove_partial_in(dir: &Path, top: bool, report: &mut PruneReport) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !top || is_model_dir(&path) {
+                remove_partial_in(&path, false, report)?;
+            }
+        } else if path.extension().and_then(|e| e.to_str()) == Some(PARTIAL_EXT) {
+            let len = fs::metadata(&path)?.len();
+            fs::remove_file(&path)?;
+            report.freed_bytes += len;
+            report.removed.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `(name, sha8)` from a `{name}-{sha8}` file stem or directory name.
+fn split_name(stem: &str) -> Option<(&str, &str)> {
+    let (name, sha) = stem.rsplit_once('-')?;
+    if name.is_empty() || sha.len() != 8 || !sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((name, sha))
+}
+
+fn is_model_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(split_name)
+        .is_some()
+}
+
+/// Total size of the files under `dir`.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&e.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// A `{name}-{sha8}.{ext}` model file or `{name}-{sha8}` model directory
+/// with a manifest next to it, or `None` for anything else (including a
+/// model still being downloaded).
+fn read_entry(path: &Path) -> Option<CachedModel> {
+    let file_name = path.file_name()?.to_str()?;
+    let ext = path.extension().and_then(|e| e.to_str());
+    if file_name.ends_with(SIDECAR_SUFFIX) || ext == Some(PARTIAL_EXT) || ext == Some(LOCK_EXT) {
+        return None;
+    }
+    let meta = fs::metadata(path).ok()?;
+    // Model names may contain dots, so directory names are used whole
+    let stem = if meta.is_dir() {
+        file_name
+    } else {
+        path.file_stem()?.to_str()?
+    };
+    let (name, sha) = split_name(stem)?;
+    let size_bytes = if meta.is_dir() {
+        dir_size(path)
+    } else {
+        meta.len()
+    };
+    let sidecar = manifest_sidecar(path);
+    let last_used = fs::metadata(&sidecar)
+        .ok()?
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let manifest = fs::read(&sidecar)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+    Some(CachedModel {
+        path: path.to_path_buf(),
+        name: name.to_string(),
+        sha256_prefix: sha.to_string(),
+        size_bytes,
+        last_used,
+        manifest,
+    })
+}
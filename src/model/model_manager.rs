@@ -1,24 +1,51 @@
 use crate::{
+    core::engine::{DEMUCS_F, DEMUCS_FRAMES, DEMUCS_T},
     error::{Result, StemError},
     io::{
-        crypto::verify_sha256,
-        net::{download_with_progress, http_client},
+        crypto::{sha256_of_files, verify_sha256},
+        net::{download_with_progress, fetch_bytes, fetch_manifest_signature, http_client},
+        output::{write_atomic, OverwritePolicy},
         paths::models_cache_dir,
     },
-    model::registry::resolve_manifest_url,
-    types::ModelManifest,
+    model::{
+        onnx_info::{read_onnx_info, Dim, OnnxInfo},
+        registry::resolve_manifest_url,
+        signing,
+    },
+    types::{IODesc, ModelManifest, ResolvedArtifact},
 };
 
-use std::{fs, path::PathBuf};
+use filetime::FileTime;
+use fs2::FileExt;
+use reqwest::blocking::Client;
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
 
+#[derive(Clone)]
 pub struct ModelHandle {
     pub manifest: ModelManifest,
     pub local_path: PathBuf,
 }
 
 /// Load a model from a custom local path.
-/// Creates a default manifest with htdemucs settings.
+///
+/// The manifest is read from `<file>.manifest.json`, or from a
+/// `manifest.json` in the same directory whose entry artifact is this file.
+/// Without one it is inferred from the ONNX graph (see
+/// [`manifest_from_onnx`]); ORT format models must have one.
 pub fn load_model_from_path(model_path: &str) -> Result<ModelHandle> {
+    load_model_from_path_with_manifest(model_path, None)
+}
+
+/// Like [`load_model_from_path`], reading the manifest from
+/// `manifest_path` if given.
+pub fn load_model_from_path_with_manifest(
+    model_path: &str,
+    manifest_path: Option<&str>,
+) -> Result<ModelHandle> {
     let path = PathBuf::from(model_path);
     if !path.exists() {
         return Err(StemError::Anyhow(anyhow::anyhow!(
@@ -27,16 +54,82 @@ pub fn load_model_from_path(model_path: &str) -> Result<ModelHandle> {
         )));
     }
 
-    // Create a default manifest for htdemucs model
-    let manifest = ModelManifest {
-        name: "htdemucs_custom".to_string(),
-        version: "1.0.0".to_string(),
+    let manifest = match manifest_path {
+        Some(manifest_path) => read_manifest_file(Path::new(manifest_path))?,
+        None => match local_manifest(&path)? {
+            Some(manifest) => manifest,
+            None if is_ort_format(&path) => {
+                // The flatbuffer graph can't be inspected without a session
+                return Err(StemError::Manifest(format!(
+                    "{} has no manifest and is in ORT format, whose inputs and outputs can't be read; describe it in {}",
+                    path.display(),
+                    manifest_sidecar(&path).display()
+                )));
+            }
+            None => manifest_from_onnx(&path, &read_onnx_info(&path)?)?,
+        },
+    };
+
+    Ok(ModelHandle {
+        manifest,
+        local_path: path,
+    })
+}
+
+fn read_manifest_file(path: &Path) -> Result<ModelManifest> {
+    let bytes = fs::read(path)
+        .map_err(|e| StemError::Manifest(format!("reading {}: {e}", path.display())))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| StemError::Manifest(format!("{}: {e}", path.display())))
+}
+
+/// Manifest stored next to a local model, if there is one for it.
+fn local_manifest(model_path: &Path) -> Result<Option<ModelManifest>> {
+    let sidecar = manifest_sidecar(model_path);
+    if sidecar.is_file() {
+        return read_manifest_file(&sidecar).map(Some);
+    }
+    let shared = model_path.with_file_name("manifest.json");
+    if !shared.is_file() {
+        return Ok(None);
+    }
+    // A directory may hold several models; only take a manifest for this one
+    let manifest = read_manifest_file(&shared)?;
+    let describes_this = (manifest.artifacts.is_empty() && manifest.url.is_empty())
+        || manifest
+            .resolve_primary_artifact()
+            .is_ok_and(|a| Path::new(&a.file).file_name() == model_path.file_name());
+    Ok(describes_this.then_some(manifest))
+}
+
+/// Whether `path` is an ORT format (flatbuffer) model rather than ONNX.
+fn is_ort_format(path: &Path) -> bool {
+    let mut header = [0u8; 8];
+    let identified = File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .is_ok_and(|()| &header[4..] == b"ORTM");
+    identified || path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ort"))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("custom")
+        .to_string()
+}
+
+/// Settings of the bundled HTDemucs models, the interface the engine runs.
+fn htdemucs_manifest(name: String) -> ModelManifest {
+    ModelManifest {
+        name,
+        version: String::new(),
         backend: "onnx".to_string(),
         format: "onnx".to_string(),
-        opset: Some(17),
+        opset: None,
         sample_rate: 44100,
-        window: 343980,
-        hop: 171990,
+        window: DEMUCS_T,
+        hop: DEMUCS_T / 2,
+        stft: Default::default(),
         stems: vec!["drums".into(), "bass".into(), "other".into(), "vocals".into()],
         input_layout: String::new(),
         output_layout: String::new(),
@@ -47,64 +140,327 @@ pub fn load_model_from_path(model_path: &str) -> Result<ModelHandle> {
         url: String::new(),
         sha256: String::new(),
         filesize: 0,
-    };
+    }
+}
 
-    Ok(ModelHandle {
-        manifest,
-        local_path: path,
+/// Describe a model without a manifest from its graph.
+///
+/// The graph must have the engine's HTDemucs interface: inputs `input`
+/// `[batch, 2, T]` and `x` `[batch, 4, F, frames]`, outputs `add_67`
+/// `[batch, stems, 2, T]` and `output` `[batch, stems, 4, F, frames]`, in
+/// float32 or float16. The metadata properties `name`, `version`,
+/// `sample_rate` and `stems` (comma-separated) are used if present; stems
+/// are otherwise named in Demucs order for 4 or 6 sources. On failure the
+/// error lists the graph's actual inputs and outputs.
+pub fn manifest_from_onnx(model_path: &Path, info: &OnnxInfo) -> Result<ModelManifest> {
+    infer_manifest(model_path, info).map_err(|reason| {
+        StemError::Manifest(format!(
+            "{} has no manifest and can't be run as an HTDemucs model: {reason}. The model has {info}; describe it in {}",
+            model_path.display(),
+            manifest_sidecar(model_path).display()
+        ))
     })
 }
 
+fn infer_manifest(model_path: &Path, info: &OnnxInfo) -> std::result::Result<ModelManifest, String> {
+    let (t, f, frames) = (DEMUCS_T as i64, DEMUCS_F as i64, DEMUCS_FRAMES as i64);
+    let expected: [(&str, bool, &[Option<i64>]); 4] = [
+        ("input", true, &[None, Some(2), Some(t)]),
+        ("x", true, &[None, Some(4), Some(f), Some(frames)]),
+        ("add_67", false, &[None, None, Some(2), Some(t)]),
+        ("output", false, &[None, None, Some(4), Some(f), Some(frames)]),
+    ];
+    let mut descs = (Vec::new(), Vec::new());
+    for (name, is_input, dims) in expected {
+        let kind = if is_input { "input" } else { "output" };
+        let tensor = if is_input { info.input(name) } else { info.output(name) }
+            .ok_or_else(|| format!("missing {kind} `{name}`"))?;
+        if !matches!(tensor.dtype.as_str(), "float32" | "float16") {
+            return Err(format!("{kind} `{tensor}` is not float32 or float16"));
+        }
+        if let Some(shape) = &tensor.shape {
+            let fits = shape.len() == dims.len()
+                && shape.iter().zip(dims).all(|(d, want)| match (d, want) {
+                    (Dim::Fixed(n), Some(want)) => n == want,
+                    _ => true,
+                });
+            if !fits {
+                let want: Vec<String> = dims
+                    .iter()
+                    .map(|d| d.map_or("*".to_string(), |n| n.to_string()))
+                    .collect();
+                return Err(format!("{kind} `{tensor}` should be [{}]", want.join(", ")));
+            }
+        }
+        let desc = IODesc {
+            name: name.to_string(),
+            layout: String::new(),
+            dtype: tensor.dtype.clone(),
+            shape: tensor
+                .shape
+                .iter()
+                .flatten()
+                .map(|d| match d {
+                    Dim::Fixed(n) => n.to_string(),
+                    Dim::Symbolic(s) => s.clone(),
+                    Dim::Unknown => "?".to_string(),
+                })
+                .collect(),
+        };
+        if is_input {
+            descs.0.push(desc);
+        } else {
+            descs.1.push(desc);
+        }
+    }
+
+    let sources = [info.output("add_67"), info.output("output")]
+        .into_iter()
+        .flatten()
+        .find_map(|t| t.fixed_dim(1));
+    let stems: Vec<String> = match (info.metadata("stems"), sources) {
+        (Some(list), _) => {
+            let stems: Vec<String> = list
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            if let Some(n) = sources.filter(|&n| n != stems.len() as i64) {
+                return Err(format!(
+                    "metadata names {} stems ({list}) but the model outputs {n}",
+                    stems.len()
+                ));
+            }
+            stems
+        }
+        (None, Some(4)) => ["drums", "bass", "other", "vocals"].map(String::from).to_vec(),
+        (None, Some(6)) => ["drums", "bass", "other", "vocals", "guitar", "piano"]
+            .map(String::from)
+            .to_vec(),
+        (None, Some(n)) => {
+            return Err(format!("{n} stems without a `stems` metadata entry naming them"))
+        }
+        (None, None) => {
+            return Err("the number of stems isn't fixed and no `stems` metadata entry names them".into())
+        }
+    };
+    let sample_rate = match info.metadata("sample_rate") {
+        Some(rate) => rate
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid `sample_rate` metadata `{rate}`"))?,
+        None => 44100,
+    };
+
+    let mut manifest = htdemucs_manifest(
+        info.metadata("name")
+            .map(String::from)
+            .unwrap_or_else(|| file_stem(model_path)),
+    );
+    manifest.version = info.metadata("version").unwrap_or_default().to_string();
+    manifest.sample_rate = sample_rate;
+    manifest.stems = stems;
+    (manifest.inputs, manifest.outputs) = descs;
+    Ok(manifest)
+}
+
 pub fn ensure_model(model_name: &str, manifest_url_override: Option<&str>) -> Result<ModelHandle> {
-    let manifest_url = manifest_url_override
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| resolve_manifest_url(model_name).expect("resolve_manifest_url failed"));
+    ensure_model_in(model_name, manifest_url_override, &models_cache_dir()?)
+}
+
+/// Like [`ensure_model`], caching the model in `cache_dir` instead of the
+/// default models directory.
+pub fn ensure_model_in(
+    model_name: &str,
+    manifest_url_override: Option<&str>,
+    cache_dir: &Path,
+) -> Result<ModelHandle> {
+    let manifest_url = match manifest_url_override {
+        Some(url) => url.to_string(),
+        None => resolve_manifest_url(model_name)?,
+    };
 
     let client = http_client();
-    let manifest: ModelManifest = client
-        .get(&manifest_url)
-        .send()?
-        .error_for_status()?
-        .json()?;
-
-    let a = manifest
-        .resolve_primary_artifact()
-        .map_err(|msg| StemError::Manifest(msg))?;
-
-    let cache_dir = models_cache_dir()?;
-    fs::create_dir_all(&cache_dir)?;
-    let ext = a
-        .file
-        .rsplit('.')
-        .next()
-        .map(|s| format!(".{s}"))
-        .unwrap_or_default();
-    let file_name = format!("{}-{}{}", manifest.name, &a.sha256[..8], ext);
-    let local_path = cache_dir.join(file_name);
-
-    let need_download = !matches!(verify_sha256(&local_path, &a.sha256), Ok(true));
-    if need_download {
-        download_with_progress(&client, &a.url, &local_path)?;
-        if !verify_sha256(&local_path, &a.sha256)? {
-            return Err(StemError::Checksum {
-                path: local_path.display().to_string(),
-            });
-        }
-        if a.size_bytes > 0 {
-            let size = fs::metadata(&local_path).map(|m| m.len()).unwrap_or(0);
-            if size != a.size_bytes {
-                eprintln!(
-                    "warn: size mismatch for {}, expected {}, got {}",
-                    local_path.display(),
-                    a.size_bytes,
-                    size
-                );
-            }
+    let bytes = fetch_bytes(&client, &manifest_url)?;
+    // Before trusting any URL or checksum in it
+    if signing::verification_enabled() {
+        let signature = fetch_manifest_signature(&client, &manifest_url)?;
+        signing::verify_manifest(&manifest_url, &bytes, signature.as_deref())?;
+    }
+    let manifest: ModelManifest = serde_json::from_slice(&bytes)?;
+
+    let layout = cache_layout(&manifest, cache_dir)?;
+
+    // Concurrent jobs wait for one download instead of racing on the files
+    let _lock = lock_model(&layout.root)?;
+
+    for (a, path) in &layout.files {
+        if !matches!(verify_sha256(path, &a.sha256), Ok(true)) {
+            download_artifact(&client, a, path)?;
         }
     }
+    record_use(&layout.root, &manifest);
 
     Ok(ModelHandle {
         manifest,
-        local_path,
+        local_path: layout.entry_path(),
     })
 }
+
+/// Download `a` to `path`, from its URL and then each mirror until one
+/// serves the right file.
+fn download_artifact(client: &Client, a: &ResolvedArtifact, path: &Path) -> Result<()> {
+    let mut result = Ok(());
+    for url in a.urls() {
+        result = download_with_progress(client, url, path).and_then(|()| {
+            if verify_sha256(path, &a.sha256)? {
+                Ok(())
+            } else {
+                Err(StemError::Checksum {
+                    path: path.display().to_string(),
+                })
+            }
+        });
+        match &result {
+            Ok(()) => break,
+            Err(e) => eprintln!("warn: downloading {url} failed: {e}"),
+        }
+    }
+    result?;
+    warn_on_size_mismatch(path, a.size_bytes);
+    Ok(())
+}
+
+/// Where a model's artifacts are cached.
+pub(crate) struct CacheLayout {
+    /// The model file, or the directory holding a multi-file model. Its
+    /// manifest is recorded next to it.
+    pub root: PathBuf,
+    /// Each artifact with its path, the entry file first.
+    pub files: Vec<(ResolvedArtifact, PathBuf)>,
+}
+
+impl CacheLayout {
+    /// The file to load the model from.
+    pub fn entry_path(&self) -> PathBuf {
+        self.files[0].1.clone()
+    }
+}
+
+/// A single-file model is cached as `{name}-{sha8}.{ext}` in `cache_dir`;
+/// a multi-file model in a `{name}-{sha8}` directory (`sha8` over every
+/// artifact's name and hash, so a new version of any file gets a new
+/// directory) keeping the artifacts' relative file names, so an ONNX graph
+/// finds its external data next to it. The directories for the files are
+/// created.
+pub(crate) fn cache_layout(manifest: &ModelManifest, cache_dir: &Path) -> Result<CacheLayout> {
+    let artifacts = manifest
+        .resolve_artifacts()
+        .map_err(StemError::Manifest)?;
+    fs::create_dir_all(cache_dir)?;
+
+    let entry = &artifacts[0];
+    if artifacts.len() == 1 {
+        let ext = entry
+            .file
+            .rsplit('.')
+            .next()
+            .map(|s| format!(".{s}"))
+            .unwrap_or_default();
+        let root = cache_dir.join(format!("{}-{}{}", manifest.name, &entry.sha256[..8], ext));
+        return Ok(CacheLayout {
+            files: vec![(entry.clone(), root.clone())],
+            root,
+        });
+    }
+
+    let hashes: Vec<(String, String)> = artifacts
+        .iter()
+        .map(|a| (a.file.clone(), a.sha256.clone()))
+        .collect();
+    let root = cache_dir.join(format!(
+        "{}-{}",
+        manifest.name,
+        &sha256_of_files(&hashes)[..8]
+    ));
+    let mut files = Vec::with_capacity(artifacts.len());
+    for a in artifacts {
+        let path = root.join(relative_path(&a.file)?);
+        if files.iter().any(|(_, p)| *p == path) {
+            return Err(StemError::Manifest(format!("artifact '{}' is listed twice", a.file)));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        files.push((a, path));
+    }
+    Ok(CacheLayout { root, files })
+}
+
+/// `file` as a path that stays inside the model directory.
+fn relative_path(file: &str) -> Result<PathBuf> {
+    let path = Path::new(file);
+    let normal = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if file.is_empty() || !normal {
+        return Err(StemError::Manifest(format!(
+            "artifact file '{file}' must be a relative path inside the model directory"
+        )));
+    }
+    Ok(path.components().collect())
+}
+
+/// Keep the manifest next to a cached model (file or directory) and mark
+/// it as used now, for [`ModelCache`](crate::model::cache::ModelCache).
+/// A recorded manifest that differs from `manifest` is replaced, so the
+/// cache verifies the files against the hashes they were fetched with.
+/// Best effort.
+pub(crate) fn record_use(model_path: &Path, manifest: &ModelManifest) {
+    let sidecar = manifest_sidecar(model_path);
+    let Ok(json) = serde_json::to_vec_pretty(manifest) else {
+        return;
+    };
+    if fs::read(&sidecar).is_ok_and(|recorded| recorded == json) {
+        let _ = filetime::set_file_mtime(&sidecar, FileTime::now());
+    } else {
+        let _ = write_atomic(&sidecar, OverwritePolicy::Replace, |w| Ok(w.write_all(&json)?));
+    }
+}
+
+/// `<model file or directory>.manifest.json`
+pub(crate) fn manifest_sidecar(model_path: &Path) -> PathBuf {
+    let mut name = model_path.as_os_str().to_owned();
+    name.push(".manifest.json");
+    PathBuf::from(name)
+}
+
+pub(crate) fn warn_on_size_mismatch(path: &Path, expected: u64) {
+    if expected > 0 {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size != expected {
+            eprintln!(
+                "warn: size mismatch for {}, expected {}, got {}",
+                path.display(),
+                expected,
+                size
+            );
+        }
+    }
+}
+
+/// Exclusive lock on the model cached at `root`, held while it is
+/// downloaded or removed; released when the file is dropped. The lock is
+/// taken on a `<root>.lock` file, so sync and async callers and other
+/// processes sharing the cache wait for each other.
+pub(crate) fn lock_model(root: &Path) -> Result<File> {
+    let mut name = root.as_os_str().to_owned();
+    name.push(".lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(PathBuf::from(name))?;
+    file.lock_exclusive()?;
+    Ok(file)
+}
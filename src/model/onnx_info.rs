@@ -0,0 +1,408 @@
+//! Inputs, outputs and metadata of an ONNX model, read straight from the
+//! protobuf without creating a session.
+//!
+//! Only the fields needed to describe the graph's interface are decoded;
+//! nodes and initializer data are skipped with seeks, so large models are
+//! never read into memory.
+
+use crate::error::{Result, StemError};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, Cursor, ErrorKind, Read, Seek},
+    path::Path,
+};
+
+/// One dimension of a tensor shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dim {
+    Fixed(i64),
+    /// Named dynamic dimension, e.g. `batch`.
+    Symbolic(String),
+    Unknown,
+}
+
+/// A graph input or output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorInfo {
+    pub name: String,
+    /// ONNX element type name, e.g. `float32` or `float16`.
+    pub dtype: String,
+    /// `None` if the graph doesn't declare a shape.
+    pub shape: Option<Vec<Dim>>,
+}
+
+impl TensorInfo {
+    /// Size of dimension `i` if it is fixed.
+    pub fn fixed_dim(&self, i: usize) -> Option<i64> {
+        match self.shape.as_ref()?.get(i)? {
+            Dim::Fixed(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TensorInfo {
+    /// `name: float32[batch, 2, 343980]`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.dtype)?;
+        let Some(shape) = &self.shape else {
+            return write!(f, "[?]");
+        };
+        let dims: Vec<String> = shape
+            .iter()
+            .map(|d| match d {
+                Dim::Fixed(n) => n.to_string(),
+                Dim::Symbolic(s) => s.clone(),
+                Dim::Unknown => "?".into(),
+            })
+            .collect();
+        write!(f, "[{}]", dims.join(", "))
+    }
+}
+
+/// Interface of an ONNX model.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OnnxInfo {
+    pub inputs: Vec<TensorInfo>,
+    pub outputs: Vec<TensorInfo>,
+    /// `metadata_props` of the model, in file order.
+    pub metadata: Vec<(String, String)>,
+    /// Files holding initializer data stored outside the model, relative
+    /// to its directory (e.g. `model.onnx.data`), each listed once.
+    pub external_data: Vec<String>,
+}
+
+impl OnnxInfo {
+    pub fn input(&self, name: &str) -> Option<&TensorInfo> {
+        self.inputs.iter().find(|t| t.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<&TensorInfo> {
+        self.outputs.iter().find(|t| t.name == name)
+    }
+
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl fmt::Display for OnnxInfo {
+    /// `inputs (input: float32[1, 2, 343980]), outputs (...)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |ts: &[TensorInfo]| {
+            ts.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "inputs ({}), outputs ({})",
+            list(&self.inputs),
+            list(&self.outputs)
+        )
+    }
+}
+
+/// Read the inputs, outputs and metadata of the ONNX model at `path`.
+pub fn read_onnx_info(path: &Path) -> Result<OnnxInfo> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    read_model(&mut Proto::new(BufReader::new(file)), len).map_err(|e| match e {
+        StemError::Manifest(msg) => invalid(format!("{}: {msg}", path.display())),
+        StemError::Anyhow(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == ErrorKind::UnexpectedEof) =>
+        {
+            invalid(format!("{} is truncated", path.display()))
+        }
+        e => e,
+    })
+}
+
+fn invalid(msg: String) -> StemError {
+    StemError::Manifest(format!("not a valid ONNX model: {msg}"))
+}
+
+// Field numbers from onnx.proto
+const MODEL_GRAPH: u64 = 7;
+const MODEL_METADATA_PROPS: u64 = 14;
+const GRAPH_INITIALIZER: u64 = 5;
+const GRAPH_INPUT: u64 = 11;
+const GRAPH_OUTPUT: u64 = 12;
+const TENSOR_NAME: u64 = 8;
+const TENSOR_EXTERNAL_DATA: u64 = 13;
+
+const VARINT: u8 = 0;
+const I64: u8 = 1;
+const LEN: u8 = 2;
+const I32: u8 = 5;
+
+/// Protobuf wire-format reader that can skip fields without reading them.
+struct Proto<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Proto<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let mut b = [0u8];
+        self.inner.read_exact(&mut b)?;
+        self.pos += 1;
+        Ok(b[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(StemError::Manifest("varint too long".into()))
+    }
+
+    /// Field number and wire type of the next field.
+    fn key(&mut self) -> Result<(u64, u8)> {
+        let key = self.varint()?;
+        Ok((key >> 3, (key & 7) as u8))
+    }
+
+    /// Length of a length-delimited field, checked against `end`.
+    fn len(&mut self, end: u64) -> Result<u64> {
+        let len = self.varint()?;
+        if self.pos.checked_add(len).map_or(true, |e| e > end) {
+            return Err(StemError::Manifest("field runs past its message".into()));
+        }
+        Ok(len)
+    }
+
+    fn bytes(&mut self, end: u64) -> Result<Vec<u8>> {
+        let len = self.len(end)?;
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf)?;
+        self.pos += len;
+        Ok(buf)
+    }
+
+    fn string(&mut self, end: u64) -> Result<String> {
+        String::from_utf8(self.bytes(end)?).map_err(|_| StemError::Manifest("invalid UTF-8".into()))
+    }
+
+    fn skip(&mut self, wire: u8, end: u64) -> Result<()> {
+        let n = match wire {
+            VARINT => return self.varint().map(drop),
+            I64 => 8,
+            LEN => self.len(end)?,
+            I32 => 4,
+            other => return Err(StemError::Manifest(format!("unsupported wire type {other}"))),
+        };
+        self.inner.seek_relative(n as i64)?;
+        self.pos += n;
+        Ok(())
+    }
+
+    /// Call `field` for each field up to `end`; it must consume the value
+    /// or return `false` to have it skipped.
+    fn fields(
+        &mut self,
+        end: u64,
+        mut field: impl FnMut(&mut Self, u64, u8) -> Result<bool>,
+    ) -> Result<()> {
+        while self.pos < end {
+            let (number, wire) = self.key()?;
+            if !field(self, number, wire)? {
+                self.skip(wire, end)?;
+            }
+        }
+        if self.pos != end {
+            return Err(StemError::Manifest("field runs past its message".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Decode a small embedded message from memory.
+fn message<T>(
+    bytes: Vec<u8>,
+    parse: impl FnOnce(&mut Proto<Cursor<Vec<u8>>>, u64) -> Result<T>,
+) -> Result<T> {
+    let end = bytes.len() as u64;
+    parse(&mut Proto::new(Cursor::new(bytes)), end)
+}
+
+fn read_model<R: Read + Seek>(p: &mut Proto<R>, end: u64) -> Result<OnnxInfo> {
+    let mut info = OnnxInfo::default();
+    let mut initializers = Vec::new();
+    let mut has_graph = false;
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (MODEL_GRAPH, LEN) => {
+            has_graph = true;
+            let graph_end = p.len(end)? + p.pos;
+            read_graph(p, graph_end, &mut info, &mut initializers)?;
+            Ok(true)
+        }
+        (MODEL_METADATA_PROPS, LEN) => {
+            info.metadata.push(message(p.bytes(end)?, read_entry)?);
+            Ok(true)
+        }
+        _ => Ok(false),
+    })?;
+    if !has_graph {
+        return Err(StemError::Manifest("no graph".into()));
+    }
+    // Older exporters also list weights as graph inputs
+    info.inputs.retain(|t| !initializers.contains(&t.name));
+    Ok(info)
+}
+
+fn read_graph<R: Read + Seek>(
+    p: &mut Proto<R>,
+    end: u64,
+    info: &mut OnnxInfo,
+    initializers: &mut Vec<String>,
+) -> Result<()> {
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (GRAPH_INPUT, LEN) => {
+            info.inputs.push(message(p.bytes(end)?, read_value_info)?);
+            Ok(true)
+        }
+        (GRAPH_OUTPUT, LEN) => {
+            info.outputs.push(message(p.bytes(end)?, read_value_info)?);
+            Ok(true)
+        }
+        (GRAPH_INITIALIZER, LEN) => {
+            // Only the name and external data file; the tensor data is skipped
+            let tensor_end = p.len(end)? + p.pos;
+            let mut name = None;
+            p.fields(tensor_end, |p, number, wire| match (number, wire) {
+                (TENSOR_NAME, LEN) => {
+                    name = Some(p.string(tensor_end)?);
+                    Ok(true)
+                }
+                (TENSOR_EXTERNAL_DATA, LEN) => {
+                    let (key, location) = message(p.bytes(tensor_end)?, read_entry)?;
+                    if key == "location" && !info.external_data.contains(&location) {
+                        info.external_data.push(location);
+                    }
+                    Ok(true)
+                }
+                _ => Ok(false),
+            })?;
+            initializers.extend(name);
+            Ok(true)
+        }
+        _ => Ok(false),
+    })
+}
+
+/// StringStringEntryProto: key = 1, value = 2.
+fn read_entry<R: Read + Seek>(p: &mut Proto<R>, end: u64) -> Result<(String, String)> {
+    let (mut key, mut value) = (String::new(), String::new());
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (1, LEN) => p.string(end).map(|s| key = s).map(|()| true),
+        (2, LEN) => p.string(end).map(|s| value = s).map(|()| true),
+        _ => Ok(false),
+    })?;
+    Ok((key, value))
+}
+
+/// ValueInfoProto: name = 1, type = 2 (TypeProto, tensor_type = 1).
+fn read_value_info<R: Read + Seek>(p: &mut Proto<R>, end: u64) -> Result<TensorInfo> {
+    let mut info = TensorInfo {
+        name: String::new(),
+        dtype: "unknown".into(),
+        shape: None,
+    };
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (1, LEN) => p.string(end).map(|s| info.name = s).map(|()| true),
+        (2, LEN) => {
+            let ty = p.bytes(end)?;
+            message(ty, |p, end| {
+                p.fields(end, |p, number, wire| {
+                    if (number, wire) != (1, LEN) {
+                        return Ok(false);
+                    }
+                    let (dtype, shape) = message(p.bytes(end)?, read_tensor_type)?;
+                    info.dtype = dtype;
+                    info.shape = shape;
+                    Ok(true)
+                })
+            })?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    })?;
+    Ok(info)
+}
+
+/// TypeProto.Tensor: elem_type = 1, shape = 2 (TensorShapeProto, dim = 1).
+fn read_tensor_type<R: Read + Seek>(p: &mut Proto<R>, end: u64) -> Result<(String, Option<Vec<Dim>>)> {
+    let mut dtype = "unknown".to_string();
+    let mut shape = None;
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (1, VARINT) => {
+            dtype = elem_type_name(p.varint()?);
+            Ok(true)
+        }
+        (2, LEN) => {
+            let mut dims = Vec::new();
+            message(p.bytes(end)?, |p, end| {
+                p.fields(end, |p, number, wire| {
+                    if (number, wire) != (1, LEN) {
+                        return Ok(false);
+                    }
+                    dims.push(message(p.bytes(end)?, read_dim)?);
+                    Ok(true)
+                })
+            })?;
+            shape = Some(dims);
+            Ok(true)
+        }
+        _ => Ok(false),
+    })?;
+    Ok((dtype, shape))
+}
+
+/// TensorShapeProto.Dimension: dim_value = 1, dim_param = 2.
+fn read_dim<R: Read + Seek>(p: &mut Proto<R>, end: u64) -> Result<Dim> {
+    let mut dim = Dim::Unknown;
+    p.fields(end, |p, number, wire| match (number, wire) {
+        (1, VARINT) => p.varint().map(|v| dim = Dim::Fixed(v as i64)).map(|()| true),
+        (2, LEN) => p.string(end).map(|s| dim = Dim::Symbolic(s)).map(|()| true),
+        _ => Ok(false),
+    })?;
+    Ok(dim)
+}
+
+/// TensorProto.DataType
+fn elem_type_name(t: u64) -> String {
+    let name = match t {
+        1 => "float32",
+        2 => "uint8",
+        3 => "int8",
+        4 => "uint16",
+        5 => "int16",
+        6 => "int32",
+        7 => "int64",
+        8 => "string",
+        9 => "bool",
+        10 => "float16",
+        11 => "float64",
+        12 => "uint32",
+        13 => "uint64",
+        16 => "bfloat16",
+        other => return format!("type{other}"),
+    };
+    name.to_string()
+}
@@ -1,10 +1,15 @@
 use crate::error::{Result, StemError};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct RegistryEntry {
     pub name: String,
     pub manifest: String,
+    /// Weight precision: `fp32`, `fp16` or `int8`.
+    #[serde(default)]
+    pub precision: String,
+    #[serde(default)]
+    pub description: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,6 +20,12 @@ pub struct Registry {
 
 const REGISTRY_JSON: &str = include_str!("../../models/registry.json");
 
+/// All models in the built-in registry.
+pub fn list_models() -> Result<Vec<RegistryEntry>> {
+    let reg: Registry = serde_json::from_str(REGISTRY_JSON)?;
+    Ok(reg.models)
+}
+
 pub fn resolve_manifest_url(model_name: &str) -> Result<String> {
     let reg: Registry = serde_json::from_str(REGISTRY_JSON)?;
     let target = if model_name.is_empty() {
@@ -0,0 +1,116 @@
+//! Detached ed25519 signatures for model manifests.
+//!
+//! A manifest at `<url>` is signed by publishing `<url>.sig` next to it:
+//! the base64 ed25519 signature of the manifest's exact bytes, e.g.
+//!
+//! ```text
+//! openssl pkeyutl -sign -inkey key.pem -rawin -in manifest.json | base64 > manifest.json.sig
+//! ```
+//!
+//! No keys are built in. Once a key is trusted with [`trust_manifest_key`],
+//! every manifest must carry a signature from a trusted key, checked before
+//! anything is downloaded; unsigned manifests are refused. Until then,
+//! signatures are not checked at all, unless strict mode
+//! ([`set_strict_manifest_verification`]) is on, which refuses every
+//! manifest while no key is trusted.
+
+use crate::error::{Result, StemError};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+static USER_KEYS: Mutex<Vec<(String, [u8; 32])>> = Mutex::new(Vec::new());
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Trust manifests signed with the ed25519 public key `public_key_base64`
+/// (32 bytes, base64). From then on every manifest must be signed by a
+/// trusted key. `id` names the key in errors.
+pub fn trust_manifest_key(id: &str, public_key_base64: &str) -> Result<()> {
+    let key = decode_public_key(id, public_key_base64)?;
+    let mut keys = USER_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    if !keys.iter().any(|(_, k)| *k == key) {
+        keys.push((id.to_string(), key));
+    }
+    Ok(())
+}
+
+/// Also refuse manifests while no key is trusted, so a missing
+/// [`trust_manifest_key`] call can't silently turn verification off.
+pub fn set_strict_manifest_verification(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Whether manifests need their signature fetched: in strict mode or once
+/// any key is trusted.
+pub(crate) fn verification_enabled() -> bool {
+    STRICT.load(Ordering::Relaxed) || !trusted_keys().is_empty()
+}
+
+/// URL of the detached signature of the manifest at `manifest_url`.
+pub(crate) fn signature_url(manifest_url: &str) -> Result<String> {
+    let mut url = reqwest::Url::parse(manifest_url)
+        .map_err(|e| StemError::Manifest(format!("invalid manifest URL {manifest_url}: {e}")))?;
+    let path = format!("{}.sig", url.path());
+    url.set_path(&path);
+    Ok(url.into())
+}
+
+/// Check the raw `manifest` downloaded from `manifest_url` against its
+/// detached `signature` (the `.sig` file's contents, `None` if there is
+/// none).
+pub(crate) fn verify_manifest(
+    manifest_url: &str,
+    manifest: &[u8],
+    signature: Option<&str>,
+) -> Result<()> {
+    let keys = trusted_keys();
+    if keys.is_empty() {
+        if STRICT.load(Ordering::Relaxed) {
+            return Err(StemError::Signature(
+                "strict verification is on but no keys are trusted".into(),
+            ));
+        }
+        return Ok(());
+    }
+    let Some(signature) = signature else {
+        return Err(StemError::Signature(format!(
+            "{manifest_url} is not signed (no {})",
+            signature_url(manifest_url)?
+        )));
+    };
+
+    let signature = STANDARD
+        .decode(signature.trim())
+        .map_err(|e| StemError::Signature(format!("malformed signature for {manifest_url}: {e}")))?;
+
+    match keys
+        .iter()
+        .find(|(_, key)| UnparsedPublicKey::new(&ED25519, key).verify(manifest, &signature).is_ok())
+    {
+        Some(_) => Ok(()),
+        None => {
+            let ids: Vec<&str> = keys.iter().map(|(id, _)| id.as_str()).collect();
+            Err(StemError::Signature(format!(
+                "{manifest_url} is not signed by a trusted key (trusted: {})",
+                ids.join(", ")
+            )))
+        }
+    }
+}
+
+fn trusted_keys() -> Vec<(String, [u8; 32])> {
+    USER_KEYS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn decode_public_key(id: &str, public_key_base64: &str) -> Result<[u8; 32]> {
+    STANDARD
+        .decode(public_key_base64.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            StemError::Signature(format!("key `{id}` is not a base64 32-byte ed25519 public key"))
+        })
+}
@@ -0,0 +1,265 @@
+//! Async counterparts of the blocking API, for use from a tokio runtime
+//! (enabled by the `async` feature).
+//!
+//! Manifests and models are fetched with an async HTTP client; checksums,
+//! the download lock, decoding, inference and output writing run on
+//! tokio's blocking pool, sharing the blocking API's code and download
+//! lock. Each job reports its progress as a stream of [`SplitProgress`]
+//! events:
+//!
+//! ```no_run
+//! use stem_splitter_core::{nonblocking, SplitOptions, SplitProgress};
+//!
+//! # async fn run() {
+//! let mut task = nonblocking::spawn_split_file("song.mp3", SplitOptions::default());
+//! while let Some(event) = task.progress().next().await {
+//!     if let SplitProgress::Stage(stage) = event {
+//!         println!("{stage}");
+//!     }
+//! }
+//! let result = task.await.unwrap();
+//! # }
+//! ```
+
+use crate::{
+    core::splitter::{
+        remove_vocals_with_model, split_file_with_model, SeparatedStems, Separator,
+        VocalRemovalResult,
+    },
+    error::{Result, StemError},
+    io::{
+        crypto::verify_sha256,
+        net::nonblocking::{download_with_progress, fetch_bytes, fetch_manifest_signature, http_client},
+        paths::{models_cache_dir, models_dir},
+        progress::{with_split_progress_sink, SplitProgress},
+    },
+    model::{
+        model_manager::{self, ModelHandle},
+        registry::resolve_manifest_url,
+        signing,
+    },
+    types::{ModelManifest, ResolvedArtifact, SplitOptions, SplitResult},
+};
+use reqwest::Client;
+use futures_core::Stream;
+use std::{
+    future::Future,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+/// Async [`ensure_model`](crate::ensure_model): fetch the manifest and
+/// download the model into the cache unless a verified copy is present.
+pub async fn ensure_model(
+    model_name: &str,
+    manifest_url_override: Option<&str>,
+) -> Result<ModelHandle> {
+    ensure_model_in(model_name, manifest_url_override, &models_cache_dir()?).await
+}
+
+/// Async [`ensure_model_in`](crate::ensure_model_in). Waiting for the
+/// download lock and hashing cached files happen on the blocking pool.
+pub async fn ensure_model_in(
+    model_name: &str,
+    manifest_url_override: Option<&str>,
+    cache_dir: &Path,
+) -> Result<ModelHandle> {
+    let manifest_url = match manifest_url_override {
+        Some(url) => url.to_string(),
+        None => resolve_manifest_url(model_name)?,
+    };
+
+    let client = http_client();
+    let bytes = fetch_bytes(&client, &manifest_url).await?;
+    // Before trusting any URL or checksum in it
+    if signing::verification_enabled() {
+        let signature = fetch_manifest_signature(&client, &manifest_url).await?;
+        signing::verify_manifest(&manifest_url, &bytes, signature.as_deref())?;
+    }
+    let manifest: ModelManifest = serde_json::from_slice(&bytes)?;
+
+    let layout = model_manager::cache_layout(&manifest, cache_dir)?;
+
+    // Same lock file as the blocking API, so both wait for one download
+    let root = layout.root.clone();
+    let _lock = blocking(move || model_manager::lock_model(&root)).await?;
+
+    for (a, path) in &layout.files {
+        if !matches!(verify(path, &a.sha256).await, Ok(true)) {
+            download_artifact(&client, a, path).await?;
+        }
+    }
+    model_manager::record_use(&layout.root, &manifest);
+
+    Ok(ModelHandle {
+        manifest,
+        local_path: layout.entry_path(),
+    })
+}
+
+/// Download `a` to `path`, from its URL and then each mirror until one
+/// serves the right file.
+async fn download_artifact(client: &Client, a: &ResolvedArtifact, path: &Path) -> Result<()> {
+    let mut result = Ok(());
+    for url in a.urls() {
+        result = download_with_progress(client, url, path).await;
+        if result.is_ok() {
+            result = match verify(path, &a.sha256).await {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(StemError::Checksum {
+                    path: path.display().to_string(),
+                }),
+                Err(e) => Err(e),
+            };
+        }
+        match &result {
+            Ok(()) => break,
+            Err(e) => eprintln!("warn: downloading {url} failed: {e}"),
+        }
+    }
+    result?;
+    model_manager::warn_on_size_mismatch(path, a.size_bytes);
+    Ok(())
+}
+
+/// [`verify_sha256`] on the blocking pool.
+async fn verify(path: &Path, expected_hex: &str) -> Result<bool> {
+    let (path, expected_hex) = (path.to_path_buf(), expected_hex.to_string());
+    blocking(move || verify_sha256(&path, &expected_hex)).await
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(join_error)?
+}
+
+/// Async [`Separator::separate`].
+pub async fn separate(input_path: &str, opts: SplitOptions) -> Result<SeparatedStems> {
+    spawn_separate(input_path, opts).await
+}
+
+/// Async [`split_file`](crate::split_file).
+pub async fn split_file(input_path: &str, opts: SplitOptions) -> Result<SplitResult> {
+    spawn_split_file(input_path, opts).await
+}
+
+/// Async [`remove_vocals`](crate::remove_vocals).
+pub async fn remove_vocals(input_path: &str, opts: SplitOptions) -> Result<VocalRemovalResult> {
+    spawn_remove_vocals(input_path, opts).await
+}
+
+/// Start [`Separator::separate`] in the background. Must be called from
+/// within a tokio runtime.
+pub fn spawn_separate(input_path: &str, opts: SplitOptions) -> SplitTask<SeparatedStems> {
+    spawn(input_path, opts, Separator::separate_with_model)
+}
+
+/// Start [`split_file`](crate::split_file) in the background. Must be
+/// called from within a tokio runtime.
+pub fn spawn_split_file(input_path: &str, opts: SplitOptions) -> SplitTask<SplitResult> {
+    spawn(input_path, opts, split_file_with_model)
+}
+
+/// Start [`remove_vocals`](crate::remove_vocals) in the background. Must be
+/// called from within a tokio runtime.
+pub fn spawn_remove_vocals(input_path: &str, opts: SplitOptions) -> SplitTask<VocalRemovalResult> {
+    spawn(input_path, opts, remove_vocals_with_model)
+}
+
+fn spawn<T: Send + 'static>(
+    input_path: &str,
+    opts: SplitOptions,
+    run: fn(&str, SplitOptions, Option<ModelHandle>) -> Result<T>,
+) -> SplitTask<T> {
+    let (tx, rx) = unbounded_channel();
+    let input_path = input_path.to_string();
+    let handle = tokio::spawn(async move {
+        // Resolve the model here so a download never blocks a pool thread;
+        // local models and the test engine are resolved by the splitter
+        let model = if opts.test_engine.is_none() && opts.model_path.is_none() {
+            send(&tx, SplitProgress::Stage("resolve_model"));
+            let cache_dir = models_dir(opts.model_cache_dir.as_deref())?;
+            let url = opts.manifest_url_override.as_deref();
+            Some(ensure_model_in(&opts.model_name, url, &cache_dir).await?)
+        } else {
+            None
+        };
+
+        tokio::task::spawn_blocking(move || {
+            with_split_progress_sink(move |p| send(&tx, p), || run(&input_path, opts, model))
+        })
+        .await
+        .map_err(join_error)?
+    });
+
+    SplitTask {
+        events: ProgressStream { rx },
+        handle,
+    }
+}
+
+fn send(tx: &UnboundedSender<SplitProgress>, p: SplitProgress) {
+    // The caller may have dropped the stream; progress is best-effort
+    let _ = tx.send(p);
+}
+
+fn join_error(e: tokio::task::JoinError) -> StemError {
+    StemError::Anyhow(anyhow::anyhow!("split task failed: {e}"))
+}
+
+/// A separation running in the background. Await it for the result;
+/// [`progress`](Self::progress) streams its events meanwhile. Dropping the
+/// task does not cancel a separation that has already started.
+pub struct SplitTask<T> {
+    events: ProgressStream,
+    handle: JoinHandle<Result<T>>,
+}
+
+impl<T> SplitTask<T> {
+    /// Progress of this job. The stream ends when the job finishes.
+    pub fn progress(&mut self) -> &mut ProgressStream {
+        &mut self.events
+    }
+
+    /// Split into the progress stream and a future of the result, for
+    /// consuming them from different tasks.
+    pub fn into_parts(self) -> (ProgressStream, JoinHandle<Result<T>>) {
+        (self.events, self.handle)
+    }
+}
+
+impl<T> Future for SplitTask<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle)
+            .poll(cx)
+            .map(|r| r.map_err(join_error).and_then(|r| r))
+    }
+}
+
+/// Stream of one job's [`SplitProgress`] events, ending with the job.
+/// Events are buffered, so the stream can be read after the job is done.
+pub struct ProgressStream {
+    rx: UnboundedReceiver<SplitProgress>,
+}
+
+impl ProgressStream {
+    /// Next event, or `None` once the job has finished and every event
+    /// has been read.
+    pub async fn next(&mut self) -> Option<SplitProgress> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for ProgressStream {
+    type Item = SplitProgress;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
@@ -0,0 +1,830 @@
+//! Local HTTP job server (enabled by the `server` feature; run it with the
+//! `stem-server` binary).
+//!
+//! All jobs share the model (and inference sessions) loaded at startup and
+//! run from a bounded queue on `workers` threads. Endpoints, all JSON unless
+//! noted:
+//!
+//! | Method & path                    | Description                                      |
+//! |----------------------------------|--------------------------------------------------|
+//! | `POST /uploads?name=song.mp3`    | Store the raw request body; returns `{"upload"}` |
+//! | `DELETE /uploads/{id}`           | Delete an upload                                 |
+//! | `POST /jobs`                     | `{"input": path}` or `{"upload": id}`, plus optional `"options"` ([`SplitOptions`]) |
+//! | `GET /jobs`, `GET /jobs/{id}`    | Status, last [`SplitProgress`] event and error   |
+//! | `DELETE /jobs/{id}`              | Cancel a queued or running job; delete a finished one and its stems |
+//! | `GET /jobs/{id}/stems/{stem}`    | One stem as WAV                                  |
+//! | `GET /jobs/{id}/zip`             | All stems as a ZIP archive (ZIP64 past 4 GiB)    |
+//!
+//! Model, engine and storage settings (output, checkpoint and cache
+//! directories and the overwrite policy) come from
+//! [`ServerConfig::options`]; jobs can set everything else. Stems are
+//! written to `data_dir/jobs/{id}`, and a `filename_template` that leaves
+//! it fails the job. Nothing is deleted automatically; clients delete
+//! uploads and finished jobs when they are done with them.
+
+use crate::{
+    core::{engine, splitter::split_file_with_model},
+    error::{Result, StemError},
+    io::{
+        paths::models_dir,
+        progress::{with_split_progress_sink, SplitProgress},
+    },
+    model::model_manager::{load_model_from_path_with_manifest, ModelHandle},
+    nonblocking,
+    types::{CancelToken, SplitOptions, SplitResult},
+};
+use http_body_util::{combinators::BoxBody, BodyExt, Full, Limited};
+use hyper::{
+    body::{Body, Bytes, Frame, Incoming},
+    header,
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fs::File,
+    io::Read,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex, Weak,
+    },
+    task::{Context, Poll},
+};
+use tokio::{net::TcpListener, sync::mpsc};
+
+/// Server settings.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    /// Uploads and job outputs are stored here.
+    pub data_dir: PathBuf,
+    /// Jobs waiting to run; creating more fails with 503.
+    pub queue_capacity: usize,
+    /// Jobs running at the same time.
+    pub workers: usize,
+    /// Largest accepted request body in bytes.
+    pub max_upload_bytes: usize,
+    /// Model, engine, test engine, checkpoint and cache directories and
+    /// overwrite policy used for every job, and defaults for the options a
+    /// job leaves out.
+    pub options: SplitOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8765)),
+            data_dir: std::env::temp_dir().join("stem-splitter-server"),
+            queue_capacity: 16,
+            workers: 1,
+            max_upload_bytes: 1 << 30,
+            options: SplitOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+struct Job {
+    input: PathBuf,
+    options: SplitOptions,
+    status: JobStatus,
+    progress: Option<SplitProgress>,
+    error: Option<String>,
+    result: Option<SplitResult>,
+    cancel: CancelToken,
+}
+
+impl Job {
+    fn stems(&self) -> Vec<(&'static str, &str)> {
+        match &self.result {
+            Some(r) => vec![
+                ("vocals", r.vocals_path.as_str()),
+                ("drums", r.drums_path.as_str()),
+                ("bass", r.bass_path.as_str()),
+                ("other", r.other_path.as_str()),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn view<'a>(&'a self, id: &'a str) -> JobView<'a> {
+        JobView {
+            id,
+            status: self.status,
+            progress: self.progress.as_ref(),
+            error: self.error.as_deref(),
+            stems: self.stems().into_iter().map(|(name, _)| name).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JobView<'a> {
+    id: &'a str,
+    status: JobStatus,
+    progress: Option<&'a SplitProgress>,
+    error: Option<&'a str>,
+    stems: Vec<&'static str>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewJob {
+    input: Option<PathBuf>,
+    upload: Option<String>,
+    #[serde(default)]
+    options: Option<SplitOptions>,
+}
+
+struct State {
+    config: ServerConfig,
+    model: Option<ModelHandle>,
+    jobs: Mutex<HashMap<String, Job>>,
+    queue: SyncSender<String>,
+}
+
+/// A bound server; [`run`](Self::run) serves requests.
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+impl Server {
+    /// Load the model, start the workers and bind `config.addr`.
+    pub async fn bind(config: ServerConfig) -> Result<Self> {
+        let opts = &config.options;
+        let model = if opts.test_engine.is_some() {
+            None
+        } else {
+            let handle = match opts.model_path.clone() {
+                Some(path) => {
+                    let manifest = opts.model_manifest_path.clone();
+                    tokio::task::spawn_blocking(move || {
+                        load_model_from_path_with_manifest(&path, manifest.as_deref())
+                    })
+                    .await
+                    .map_err(join_error)??
+                }
+                None => {
+                    nonblocking::ensure_model_in(
+                        &opts.model_name,
+                        opts.manifest_url_override.as_deref(),
+                        &models_dir(opts.model_cache_dir.as_deref())?,
+                    )
+                    .await?
+                }
+            };
+            let (preload, engine_config) = (handle.clone(), opts.engine.clone());
+            tokio::task::spawn_blocking(move || {
+                engine::preload_with_config(&preload, &engine_config)
+            })
+            .await
+            .map_err(join_error)??;
+            Some(handle)
+        };
+
+        tokio::fs::create_dir_all(config.data_dir.join("uploads")).await?;
+        tokio::fs::create_dir_all(config.data_dir.join("jobs")).await?;
+        let listener = TcpListener::bind(config.addr).await?;
+
+        let (queue, rx) = sync_channel(config.queue_capacity);
+        let workers = config.workers.max(1);
+        let state = Arc::new(State {
+            config,
+            model,
+            jobs: Mutex::default(),
+            queue,
+        });
+        let rx = Arc::new(Mutex::new(rx));
+        for _ in 0..workers {
+            let (state, rx) = (Arc::downgrade(&state), rx.clone());
+            std::thread::spawn(move || worker(&state, &rx));
+        }
+
+        Ok(Self { listener, state })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accept and serve connections until the task is dropped.
+    pub async fn run(self) -> Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let state = self.state.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle(&state, req).await) }
+                });
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    eprintln!("warn: connection error: {e}");
+                }
+            });
+        }
+    }
+}
+
+fn join_error(e: tokio::task::JoinError) -> StemError {
+    StemError::Anyhow(anyhow::anyhow!("server task failed: {e}"))
+}
+
+/// Run queued jobs until the server (and with it the queue's sender) is
+/// dropped.
+fn worker(state: &Weak<State>, rx: &Mutex<Receiver<String>>) {
+    loop {
+        let Ok(id) = rx.lock().unwrap().recv() else {
+            return;
+        };
+        let Some(state) = state.upgrade() else { return };
+        let (input, opts) = {
+            let mut jobs = state.jobs.lock().unwrap();
+            let Some(job) = jobs.get_mut(&id) else {
+                continue;
+            };
+            if job.status != JobStatus::Queued {
+                // Cancelled while waiting
+                continue;
+            }
+            job.status = JobStatus::Running;
+            (job.input.clone(), job.options.clone())
+        };
+
+        let sink_state = state.clone();
+        let sink_id = id.clone();
+        let result = with_split_progress_sink(
+            move |p| {
+                if matches!(p, SplitProgress::Timing(_)) {
+                    return;
+                }
+                if let Some(job) = sink_state.jobs.lock().unwrap().get_mut(&sink_id) {
+                    job.progress = Some(p);
+                }
+            },
+            || split_file_with_model(&input.to_string_lossy(), opts, state.model.clone()),
+        );
+
+        let mut jobs = state.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            continue;
+        };
+        match result {
+            Ok(result) => {
+                job.status = JobStatus::Done;
+                job.result = Some(result);
+            }
+            Err(StemError::Cancelled) => job.status = JobStatus::Cancelled,
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+type HttpResponse = Response<BoxBody<Bytes, std::io::Error>>;
+
+async fn handle(state: &Arc<State>, req: Request<Incoming>) -> HttpResponse {
+    let path = req.uri().path().to_string();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let query = req.uri().query().unwrap_or("").to_string();
+
+    match (req.method().clone(), segments.as_slice()) {
+        (Method::POST, ["uploads"]) => match read_body(state, req).await {
+            Ok(body) => upload(state, &query, body).await,
+            Err(response) => response,
+        },
+        (Method::DELETE, ["uploads", id]) => delete_upload(state, id).await,
+        (Method::POST, ["jobs"]) => match read_body(state, req).await {
+            Ok(body) => create_job(state, &body).await,
+            Err(response) => response,
+        },
+        (Method::GET, ["jobs"]) => {
+            let jobs = state.jobs.lock().unwrap();
+            let mut views: Vec<JobView> = jobs.iter().map(|(id, job)| job.view(id)).collect();
+            views.sort_by_key(|v| v.id);
+            json(StatusCode::OK, &views)
+        }
+        (Method::GET, ["jobs", id]) => match state.jobs.lock().unwrap().get(*id) {
+            Some(job) => json(StatusCode::OK, &job.view(id)),
+            None => not_found("job"),
+        },
+        (Method::DELETE, ["jobs", id]) => delete_job(state, id).await,
+        (Method::GET, ["jobs", id, "stems", stem]) => download_stem(state, id, stem).await,
+        (Method::GET, ["jobs", id, "zip"]) => download_zip(state, id).await,
+        _ => error(StatusCode::NOT_FOUND, "no such endpoint"),
+    }
+}
+
+async fn read_body(
+    state: &State,
+    req: Request<Incoming>,
+) -> std::result::Result<Bytes, HttpResponse> {
+    match Limited::new(req.into_body(), state.config.max_upload_bytes)
+        .collect()
+        .await
+    {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(e) => Err(error(StatusCode::PAYLOAD_TOO_LARGE, &e.to_string())),
+    }
+}
+
+async fn upload(state: &State, query: &str, body: Bytes) -> HttpResponse {
+    // Decoded first, so an encoded `..%2F` can't slip past `file_name`
+    let name = form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "name")
+        .and_then(|(_, n)| {
+            Path::new(n.as_ref())
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        });
+    let Some(name) = name else {
+        return error(StatusCode::BAD_REQUEST, "missing ?name= with the file name");
+    };
+
+    let id = new_id();
+    let dir = state.config.data_dir.join("uploads").join(&id);
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(dir.join(&name), &body).await
+    };
+    match written.await {
+        Ok(()) => json(
+            StatusCode::CREATED,
+            &serde_json::json!({ "upload": id, "name": name, "bytes": body.len() }),
+        ),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+async fn create_job(state: &State, body: &[u8]) -> HttpResponse {
+    let new: NewJob = match serde_json::from_slice(body) {
+        Ok(new) => new,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &format!("invalid job: {e}")),
+    };
+    let input = match (new.input, new.upload) {
+        (Some(path), None) => path,
+        (None, Some(upload)) => {
+            let dir = state.config.data_dir.join("uploads").join(&upload);
+            let first = match std::fs::read_dir(&dir) {
+                Ok(mut entries) if is_id(&upload) => {
+                    entries.next().and_then(|e| e.ok()).map(|e| e.path())
+                }
+                _ => None,
+            };
+            match first {
+                Some(path) => path,
+                None => return not_found("upload"),
+            }
+        }
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                "set exactly one of `input` and `upload`",
+            )
+        }
+    };
+    if !input.is_file() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            &format!("no such file: {}", input.display()),
+        );
+    }
+
+    let id = new_id();
+    let base = &state.config.options;
+    let cancel = CancelToken::new();
+    let options = SplitOptions {
+        output_dir: state
+            .config
+            .data_dir
+            .join("jobs")
+            .join(&id)
+            .to_string_lossy()
+            .into_owned(),
+        // Every job shares the server's model and storage
+        model_name: base.model_name.clone(),
+        manifest_url_override: base.manifest_url_override.clone(),
+        model_path: base.model_path.clone(),
+        model_manifest_path: base.model_manifest_path.clone(),
+        model_cache_dir: base.model_cache_dir.clone(),
+        overwrite: base.overwrite,
+        cache: base.cache.clone(),
+        checkpoint_dir: base.checkpoint_dir.clone(),
+        engine: base.engine.clone(),
+        test_engine: base.test_engine.clone(),
+        cancel: Some(cancel.clone()),
+        ..new.options.unwrap_or_else(|| base.clone())
+    };
+
+    let mut jobs = state.jobs.lock().unwrap();
+    jobs.insert(
+        id.clone(),
+        Job {
+            input,
+            options,
+            status: JobStatus::Queued,
+            progress: None,
+            error: None,
+            result: None,
+            cancel,
+        },
+    );
+    match state.queue.try_send(id.clone()) {
+        Ok(()) => json(StatusCode::ACCEPTED, &jobs[&id].view(&id)),
+        Err(TrySendError::Full(_)) => {
+            jobs.remove(&id);
+            error(StatusCode::SERVICE_UNAVAILABLE, "job queue is full")
+        }
+        Err(TrySendError::Disconnected(_)) => {
+            jobs.remove(&id);
+            error(StatusCode::SERVICE_UNAVAILABLE, "workers stopped")
+        }
+    }
+}
+
+/// Cancel a queued or running job, or forget a finished one and delete
+/// its output directory.
+async fn delete_job(state: &State, id: &str) -> HttpResponse {
+    {
+        let mut jobs = state.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(id) else {
+            return not_found("job");
+        };
+        match job.status {
+            JobStatus::Queued => job.status = JobStatus::Cancelled,
+            // The worker marks it cancelled once the separation stops
+            JobStatus::Running => job.cancel.cancel(),
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled => {
+                jobs.remove(id);
+            }
+        }
+        if let Some(job) = jobs.get(id) {
+            return json(StatusCode::OK, &job.view(id));
+        }
+    }
+    remove_dir(&state.config.data_dir.join("jobs").join(id), id).await
+}
+
+async fn delete_upload(state: &State, id: &str) -> HttpResponse {
+    if !is_id(id) {
+        return not_found("upload");
+    }
+    let dir = state.config.data_dir.join("uploads").join(id);
+    if !dir.is_dir() {
+        return not_found("upload");
+    }
+    remove_dir(&dir, id).await
+}
+
+async fn remove_dir(dir: &Path, id: &str) -> HttpResponse {
+    match tokio::fs::remove_dir_all(dir).await {
+        Ok(()) => json(StatusCode::OK, &serde_json::json!({ "deleted": id })),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            json(StatusCode::OK, &serde_json::json!({ "deleted": id }))
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Output paths of a finished job, or the error to respond with.
+fn finished_stems(
+    state: &State,
+    id: &str,
+) -> std::result::Result<Vec<(&'static str, PathBuf)>, (StatusCode, &'static str)> {
+    let jobs = state.jobs.lock().unwrap();
+    let Some(job) = jobs.get(id) else {
+        return Err((StatusCode::NOT_FOUND, "no such job"));
+    };
+    if job.status != JobStatus::Done {
+        return Err((StatusCode::CONFLICT, "job is not done"));
+    }
+    Ok(job
+        .stems()
+        .into_iter()
+        .map(|(name, path)| (name, PathBuf::from(path)))
+        .collect())
+}
+
+async fn download_stem(state: &State, id: &str, stem: &str) -> HttpResponse {
+    let stems = match finished_stems(state, id) {
+        Ok(stems) => stems,
+        Err((status, message)) => return error(status, message),
+    };
+    let Some((_, path)) = stems.into_iter().find(|(name, _)| *name == stem) else {
+        return not_found("stem");
+    };
+    let len = match tokio::fs::metadata(&path).await {
+        Ok(meta) => meta.len(),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+    let body = stream_body(move |tx| send_file(tx, &path, len));
+    file_response("audio/wav", &format!("{stem}.wav"), len, body)
+}
+
+/// The stems streamed as a stored ZIP: a first pass over the files
+/// computes their checksums (ZIP needs them before the data), the second
+/// sends them. Neither holds a whole file in memory.
+async fn download_zip(state: &State, id: &str) -> HttpResponse {
+    let stems = match finished_stems(state, id) {
+        Ok(stems) => stems,
+        Err((status, message)) => return error(status, message),
+    };
+    let scanned = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<ZipEntry>> {
+        stems
+            .into_iter()
+            .map(|(name, path)| {
+                let (size, crc) = crc32_of_file(&path)?;
+                Ok(ZipEntry {
+                    name: format!("{name}.wav"),
+                    path,
+                    size,
+                    crc,
+                })
+            })
+            .collect()
+    })
+    .await;
+    let entries = match scanned {
+        Ok(Ok(entries)) => entries,
+        Ok(Err(e)) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    let (local_headers, trailer) = zip_headers(&entries);
+    let len = local_headers.iter().map(|h| h.len() as u64).sum::<u64>()
+        + entries.iter().map(|e| e.size).sum::<u64>()
+        + trailer.len() as u64;
+    let body = stream_body(move |tx| {
+        for (entry, header) in entries.iter().zip(local_headers) {
+            send(tx, Bytes::from(header))?;
+            send_file(tx, &entry.path, entry.size)?;
+        }
+        send(tx, Bytes::from(trailer))
+    });
+    file_response("application/zip", &format!("{id}.zip"), len, body)
+}
+
+/// Chunks of a streamed response body.
+const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Response body produced by `produce` on the blocking pool, a few chunks
+/// ahead of the client. An error aborts the response; a client that goes
+/// away stops the producer at its next chunk.
+fn stream_body(
+    produce: impl FnOnce(&mpsc::Sender<std::io::Result<Bytes>>) -> std::io::Result<()> + Send + 'static,
+) -> BoxBody<Bytes, std::io::Error> {
+    let (tx, rx) = mpsc::channel(4);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = produce(&tx) {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    ChannelBody { rx }.boxed()
+}
+
+fn send(tx: &mpsc::Sender<std::io::Result<Bytes>>, chunk: Bytes) -> std::io::Result<()> {
+    tx.blocking_send(Ok(chunk))
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "client went away"))
+}
+
+/// Sends the first `len` bytes of the file at `path`, the length already
+/// promised to the client.
+fn send_file(
+    tx: &mpsc::Sender<std::io::Result<Bytes>>,
+    path: &Path,
+    len: u64,
+) -> std::io::Result<()> {
+    let mut file = File::open(path)?.take(len);
+    let mut left = len;
+    while left > 0 {
+        let mut buf = vec![0; CHUNK_BYTES];
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("{} shrank while being sent", path.display()),
+            ));
+        }
+        buf.truncate(n);
+        left -= n as u64;
+        send(tx, Bytes::from(buf))?;
+    }
+    Ok(())
+}
+
+struct ChannelBody {
+    rx: mpsc::Receiver<std::io::Result<Bytes>>,
+}
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, Self::Error>>> {
+        self.rx
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> HttpResponse {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)).map_err(|e| match e {}).boxed())
+        .expect("valid response")
+}
+
+fn error(status: StatusCode, message: &str) -> HttpResponse {
+    json(status, &serde_json::json!({ "error": message }))
+}
+
+fn not_found(what: &str) -> HttpResponse {
+    error(StatusCode::NOT_FOUND, &format!("no such {what}"))
+}
+
+fn file_response(
+    content_type: &str,
+    file_name: &str,
+    len: u64,
+    body: BoxBody<Bytes, std::io::Error>,
+) -> HttpResponse {
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, len)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{file_name}\""),
+        )
+        .body(body)
+        .expect("valid response")
+}
+
+fn new_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// Ids are generated by [`new_id`]; anything else must not reach the
+/// filesystem.
+fn is_id(s: &str) -> bool {
+    s.len() == 16 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// A file of a stored ZIP archive.
+struct ZipEntry {
+    name: String,
+    path: PathBuf,
+    size: u64,
+    crc: u32,
+}
+
+/// Size and CRC-32 of the file at `path`, read in chunks.
+fn crc32_of_file(path: &Path) -> std::io::Result<(u64, u32)> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; CHUNK_BYTES];
+    let mut size = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok((size, hasher.finalize()));
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+}
+
+/// Headers of a ZIP archive storing `entries` uncompressed (WAV barely
+/// compresses): each entry's local header, to be followed by its data,
+/// and the central directory with the end records. Sizes and offsets that
+/// don't fit 32 bits go into ZIP64 extra fields and end records.
+fn zip_headers(entries: &[ZipEntry]) -> (Vec<Vec<u8>>, Vec<u8>) {
+    const DOS_DATE_1980_01_01: u16 = 0x21;
+    const MAX32: u64 = u32::MAX as u64;
+    let clamp32 = |v: u64| v.min(MAX32) as u32;
+
+    let mut locals = Vec::with_capacity(entries.len());
+    let mut central = Vec::new();
+    let mut offset = 0u64;
+    for entry in entries {
+        let name_len = entry.name.len() as u16;
+        let big_size = entry.size >= MAX32;
+        let big_offset = offset >= MAX32;
+        let version: u16 = if big_size || big_offset { 45 } else { 20 };
+
+        // Both sizes, required in the local header once either overflows
+        let mut local_extra = Vec::new();
+        if big_size {
+            local_extra.extend_from_slice(&1u16.to_le_bytes());
+            local_extra.extend_from_slice(&16u16.to_le_bytes());
+            local_extra.extend_from_slice(&entry.size.to_le_bytes());
+            local_extra.extend_from_slice(&entry.size.to_le_bytes());
+        }
+        let mut local = Vec::new();
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local.extend_from_slice(&version.to_le_bytes()); // version needed
+        local.extend_from_slice(&[0; 4]); // flags, method (stored)
+        local.extend_from_slice(&0u16.to_le_bytes()); // time
+        local.extend_from_slice(&DOS_DATE_1980_01_01.to_le_bytes());
+        local.extend_from_slice(&entry.crc.to_le_bytes());
+        local.extend_from_slice(&clamp32(entry.size).to_le_bytes());
+        local.extend_from_slice(&clamp32(entry.size).to_le_bytes());
+        local.extend_from_slice(&name_len.to_le_bytes());
+        local.extend_from_slice(&(local_extra.len() as u16).to_le_bytes());
+        local.extend_from_slice(entry.name.as_bytes());
+        local.extend_from_slice(&local_extra);
+
+        // Only the overflowing fields, in this order
+        let mut extra = Vec::new();
+        if big_size {
+            extra.extend_from_slice(&entry.size.to_le_bytes());
+            extra.extend_from_slice(&entry.size.to_le_bytes());
+        }
+        if big_offset {
+            extra.extend_from_slice(&offset.to_le_bytes());
+        }
+        let central_extra = if extra.is_empty() {
+            extra
+        } else {
+            let mut field = Vec::new();
+            field.extend_from_slice(&1u16.to_le_bytes());
+            field.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            field.extend_from_slice(&extra);
+            field
+        };
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&version.to_le_bytes()); // version made by
+        central.extend_from_slice(&version.to_le_bytes()); // version needed
+        central.extend_from_slice(&[0; 4]); // flags, method
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&DOS_DATE_1980_01_01.to_le_bytes());
+        central.extend_from_slice(&entry.crc.to_le_bytes());
+        central.extend_from_slice(&clamp32(entry.size).to_le_bytes());
+        central.extend_from_slice(&clamp32(entry.size).to_le_bytes());
+        central.extend_from_slice(&name_len.to_le_bytes());
+        central.extend_from_slice(&(central_extra.len() as u16).to_le_bytes());
+        central.extend_from_slice(&[0; 10]); // comment, disk, attributes
+        central.extend_from_slice(&clamp32(offset).to_le_bytes());
+        central.extend_from_slice(entry.name.as_bytes());
+        central.extend_from_slice(&central_extra);
+
+        offset += local.len() as u64 + entry.size;
+        locals.push(local);
+    }
+
+    let central_offset = offset;
+    let central_len = central.len() as u64;
+    let count = entries.len() as u64;
+    let mut trailer = central;
+    if central_offset >= MAX32 || central_len >= MAX32 || count >= 0xffff {
+        let record_offset = central_offset + central_len;
+        trailer.extend_from_slice(&0x0606_4b50u32.to_le_bytes());
+        trailer.extend_from_slice(&44u64.to_le_bytes()); // size of the rest
+        trailer.extend_from_slice(&45u16.to_le_bytes()); // version made by
+        trailer.extend_from_slice(&45u16.to_le_bytes()); // version needed
+        trailer.extend_from_slice(&[0; 8]); // disk numbers
+        trailer.extend_from_slice(&count.to_le_bytes());
+        trailer.extend_from_slice(&count.to_le_bytes());
+        trailer.extend_from_slice(&central_len.to_le_bytes());
+        trailer.extend_from_slice(&central_offset.to_le_bytes());
+
+        trailer.extend_from_slice(&0x0706_4b50u32.to_le_bytes());
+        trailer.extend_from_slice(&0u32.to_le_bytes()); // disk of the record
+        trailer.extend_from_slice(&record_offset.to_le_bytes());
+        trailer.extend_from_slice(&1u32.to_le_bytes()); // total disks
+    }
+    let count16 = count.min(0xffff) as u16;
+    trailer.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    trailer.extend_from_slice(&[0; 4]); // disk numbers
+    trailer.extend_from_slice(&count16.to_le_bytes());
+    trailer.extend_from_slice(&count16.to_le_bytes());
+    trailer.extend_from_slice(&clamp32(central_len).to_le_bytes());
+    trailer.extend_from_slice(&clamp32(central_offset).to_le_bytes());
+    trailer.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    (locals, trailer)
+}
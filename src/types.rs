@@ -1,4 +1,24 @@
+use crate::{
+    core::{
+        dsp::StftOptions,
+        engine::EngineConfig,
+        loudness::{self, LoudnessStats, NormalizeOptions},
+        mixer::MixSpec,
+        test_engine::TestEngine,
+    },
+    io::{
+        output::{OverwritePolicy, DEFAULT_FILENAME_TEMPLATE},
+        result_cache::CacheOptions,
+    },
+};
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 #[derive(Clone, Debug)]
 pub struct AudioData {
@@ -7,7 +27,51 @@ pub struct AudioData {
     pub channels: u16,
 }
 
+impl AudioData {
+    /// Measure EBU R128 loudness and true peak.
+    pub fn loudness(&self) -> LoudnessStats {
+        loudness::measure_loudness(&self.samples, self.channels, self.sample_rate)
+    }
+
+    /// Apply a gain in dB to all samples.
+    pub fn apply_gain_db(&mut self, gain_db: f64) {
+        loudness::apply_gain_db(&mut self.samples, gain_db);
+    }
+
+    /// Normalize to the target loudness.
+    /// Returns the stats after normalization and the gain that was applied.
+    pub fn normalize(&mut self, opts: &NormalizeOptions) -> (LoudnessStats, f64) {
+        let stats = self.loudness();
+        let gain_db = loudness::normalization_gain_db(&stats, opts);
+        self.apply_gain_db(gain_db);
+        (stats.with_gain(gain_db), gain_db)
+    }
+}
+
+/// A position in the input audio, either in seconds or in sample frames
+/// at the input's sample rate.
+///
+/// Serialized as `{"seconds": 12.5}` or `{"samples": 551250}`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimePosition {
+    Seconds(f64),
+    Samples(u64),
+}
+
+impl TimePosition {
+    /// Position in frames at the given sample rate.
+    pub fn to_frames(self, sample_rate: u32) -> u64 {
+        match self {
+            TimePosition::Seconds(s) => (s.max(0.0) * sample_rate as f64).round() as u64,
+            TimePosition::Samples(n) => n,
+        }
+    }
+}
+
+/// Fields missing when deserializing take their [`Default`] values.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SplitOptions {
     pub output_dir: String,
     pub model_name: String,
@@ -16,12 +80,109 @@ pub struct SplitOptions {
     /// If set, skips downloading and uses this file directly.
     #[serde(default)]
     pub model_path: Option<String>,
+    /// Manifest JSON for `model_path`. Defaults to one stored next to the
+    /// model, or one inferred from the ONNX graph.
+    #[serde(default)]
+    pub model_manifest_path: Option<String>,
+    /// Directory for downloaded models. Defaults to `models` in the cache
+    /// root (see [`CACHE_DIR_ENV`](crate::io::paths::CACHE_DIR_ENV)).
+    #[serde(default)]
+    pub model_cache_dir: Option<String>,
     /// Chunk duration in seconds for processing long audio files.
     /// If None or 0, uses automatic chunking based on available memory.
     /// Default: 300 seconds (5 minutes)
     /// Set to a smaller value if you run out of memory.
     #[serde(default = "default_chunk_seconds")]
     pub chunk_seconds: Option<u32>,
+    /// Measure loudness of every written stem and return it in the result.
+    #[serde(default)]
+    pub measure_loudness: bool,
+    /// Normalize every stem to a loudness target before it is written.
+    /// Implies `measure_loudness`.
+    #[serde(default)]
+    pub normalize: Option<NormalizeOptions>,
+    /// Mix used for the instrumental track written by `remove_vocals`
+    /// (e.g. vocals at -12 dB as a guide). Defaults to every stem except
+    /// vocals at unity gain.
+    #[serde(default)]
+    pub instrumental_mix: Option<MixSpec>,
+    /// Start of the range to separate. Only this range (plus a little
+    /// context for the model) is decoded and processed; the output stems
+    /// cover exactly `start..end`.
+    #[serde(default)]
+    pub start: Option<TimePosition>,
+    /// End of the range to separate. `None` means the end of the file.
+    #[serde(default)]
+    pub end: Option<TimePosition>,
+    /// Copy tags and cover art from the input into the written stems,
+    /// appending the stem name to the title (e.g. "Song (Vocals)").
+    #[serde(default = "default_true")]
+    pub copy_metadata: bool,
+    /// File name of each written stem, relative to `output_dir`.
+    /// Placeholders: `{input}` (input file name without extension), `{stem}`,
+    /// `{model}` and `{ext}`. A `/` creates subdirectories, e.g.
+    /// `"{input}/{stem}.{ext}"` for one directory per track.
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+    /// What to do when an output file already exists.
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+    /// Reuse results of earlier separations of the same audio with the same
+    /// model and settings. Disabled when `None`.
+    #[serde(default)]
+    pub cache: Option<CacheOptions>,
+    /// Job directory for checkpoints: every finished chunk is saved there,
+    /// and a rerun with the same input and options resumes after the last
+    /// one instead of starting over. Removed once the separation finishes.
+    #[serde(default)]
+    pub checkpoint_dir: Option<String>,
+    /// Execution providers, threading and memory settings for the
+    /// inference session.
+    #[serde(default)]
+    pub engine: EngineConfig,
+    /// Run a scripted [`TestEngine`] instead of a model. The model is not
+    /// resolved or loaded, and the result cache is bypassed.
+    #[serde(skip)]
+    pub test_engine: Option<TestEngine>,
+    /// Stop the separation early when this token is cancelled; it then
+    /// fails with a cancellation error and writes no stems.
+    #[serde(skip)]
+    pub cancel: Option<CancelToken>,
+}
+
+/// Cancels a running separation from another thread. Clones share the
+/// same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err(StemError::Cancelled)` once cancelled.
+    pub(crate) fn check(&self) -> crate::error::Result<()> {
+        if self.is_cancelled() {
+            return Err(crate::error::StemError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+fn default_filename_template() -> String {
+    DEFAULT_FILENAME_TEMPLATE.into()
+}
+
+fn default_true() -> bool {
+    true
 }
 
 fn default_chunk_seconds() -> Option<u32> {
@@ -35,7 +196,22 @@ impl Default for SplitOptions {
             model_name: "htdemucs_ort_v1".into(),
             manifest_url_override: None,
             model_path: None,
+            model_manifest_path: None,
+            model_cache_dir: None,
             chunk_seconds: default_chunk_seconds(),
+            measure_loudness: false,
+            normalize: None,
+            instrumental_mix: None,
+            start: None,
+            end: None,
+            copy_metadata: true,
+            filename_template: default_filename_template(),
+            overwrite: OverwritePolicy::default(),
+            cache: None,
+            checkpoint_dir: None,
+            engine: EngineConfig::default(),
+            test_engine: None,
+            cancel: None,
         }
     }
 }
@@ -46,6 +222,9 @@ pub struct SplitResult {
     pub drums_path: String,
     pub bass_path: String,
     pub other_path: String,
+    /// Loudness of each written stem keyed by stem name (after normalization).
+    /// Empty unless `measure_loudness` or `normalize` was set.
+    pub loudness: HashMap<String, LoudnessStats>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -55,6 +234,10 @@ pub struct Artifact {
     #[serde(alias = "size_bytes")]
     pub size_bytes: u64,
     pub url: String,
+    /// Fallback URLs, tried in order when `url` fails or serves a file
+    /// with the wrong checksum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -62,12 +245,36 @@ pub struct IODesc {
     pub name: String,
     #[serde(default)]
     pub layout: String,
+    /// Element type: `float32` (default) or `float16`. Quantized (INT8)
+    /// models keep float32 inputs and outputs.
     #[serde(default)]
     pub dtype: String,
     #[serde(default)]
     pub shape: Vec<String>,
 }
 
+/// Element type of a model input or output tensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TensorDType {
+    Float32,
+    Float16,
+}
+
+impl IODesc {
+    /// Parsed `dtype`, or `None` if the manifest doesn't declare one.
+    pub fn tensor_dtype(&self) -> std::result::Result<Option<TensorDType>, String> {
+        match self.dtype.to_ascii_lowercase().as_str() {
+            "" => Ok(None),
+            "float32" | "float" | "f32" | "fp32" => Ok(Some(TensorDType::Float32)),
+            "float16" | "half" | "f16" | "fp16" => Ok(Some(TensorDType::Float16)),
+            other => Err(format!(
+                "unsupported dtype `{other}` for tensor `{}` (expected float32 or float16)",
+                self.name
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ModelManifest {
     pub name: String,
@@ -85,6 +292,10 @@ pub struct ModelManifest {
     pub sample_rate: u32,
     pub window: usize,
     pub hop: usize,
+    /// STFT window and padding the model was trained with, e.g.
+    /// `{"window": "hann_periodic", "pad_mode": "reflect"}` (the default).
+    #[serde(default)]
+    pub stft: StftOptions,
 
     #[serde(default)]
     pub stems: Vec<String>,
@@ -118,32 +329,20 @@ pub struct ResolvedArtifact {
     pub sha256: String,
     pub size_bytes: u64,
     pub url: String,
+    pub mirrors: Vec<String>,
+}
+
+impl ResolvedArtifact {
+    /// `url` followed by the mirrors.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
 }
 
 impl ModelManifest {
     pub fn resolve_primary_artifact(&self) -> Result<ResolvedArtifact, String> {
         if !self.artifacts.is_empty() {
-            if !self.entry.is_empty() {
-                if let Some(a) = self.artifacts.iter().find(|a| a.file == self.entry) {
-                    return Ok(ResolvedArtifact {
-                        file: a.file.clone(),
-                        sha256: a.sha256.clone(),
-                        size_bytes: a.size_bytes,
-                        url: a.url.clone(),
-                    });
-                }
-                return Err(format!("entry '{}' not found in artifacts[]", self.entry));
-            }
-            if self.artifacts.len() == 1 {
-                let a = &self.artifacts[0];
-                return Ok(ResolvedArtifact {
-                    file: a.file.clone(),
-                    sha256: a.sha256.clone(),
-                    size_bytes: a.size_bytes,
-                    url: a.url.clone(),
-                });
-            }
-            return Err("multiple artifacts present but no 'entry' specified".into());
+            return Ok(resolve(&self.artifacts[self.entry_index()?]));
         }
 
         if self.url.is_empty() || self.sha256.is_empty() || self.filesize == 0 {
@@ -156,8 +355,51 @@ impl ModelManifest {
             sha256: self.sha256.clone(),
             size_bytes: self.filesize,
             url: self.url.clone(),
+            mirrors: Vec::new(),
         })
     }
+
+    /// Every file the model needs, the primary (entry) artifact first, e.g.
+    /// an ONNX graph followed by its external weights.
+    pub fn resolve_artifacts(&self) -> Result<Vec<ResolvedArtifact>, String> {
+        if self.artifacts.is_empty() {
+            return Ok(vec![self.resolve_primary_artifact()?]);
+        }
+        let entry = self.entry_index()?;
+        let rest = self
+            .artifacts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != entry)
+            .map(|(_, a)| resolve(a));
+        Ok(std::iter::once(resolve(&self.artifacts[entry]))
+            .chain(rest)
+            .collect())
+    }
+
+    fn entry_index(&self) -> Result<usize, String> {
+        if !self.entry.is_empty() {
+            return self
+                .artifacts
+                .iter()
+                .position(|a| a.file == self.entry)
+                .ok_or_else(|| format!("entry '{}' not found in artifacts[]", self.entry));
+        }
+        if self.artifacts.len() == 1 {
+            return Ok(0);
+        }
+        Err("multiple artifacts present but no 'entry' specified".into())
+    }
+}
+
+fn resolve(a: &Artifact) -> ResolvedArtifact {
+    ResolvedArtifact {
+        file: a.file.clone(),
+        sha256: a.sha256.clone(),
+        size_bytes: a.size_bytes,
+        url: a.url.clone(),
+        mirrors: a.mirrors.clone(),
+    }
 }
 
 fn infer_filename_from_url(url: &str) -> Option<String> {
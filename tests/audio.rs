@@ -3,8 +3,8 @@ use std::f32::consts::PI;
 use tempfile::tempdir;
 
 use hound;
-use stem_splitter_core::core::audio::{read_audio, write_audio};
-use stem_splitter_core::AudioData;
+use stem_splitter_core::core::audio::{read_audio, read_audio_range, write_audio};
+use stem_splitter_core::{AudioData, TimePosition};
 
 fn mono_sine(sample_rate: u32, freq: f32, seconds: f32) -> Vec<f32> {
     let n = (sample_rate as f32 * seconds) as usize;
@@ -154,3 +154,66 @@ fn read_audio_nonexistent_file_returns_error() {
         "expected an open/read error, got: {msg}"
     );
 }
+
+#[test]
+fn read_audio_range_seeks_and_reports_span() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("range/ramp.wav");
+    let path_str = path.to_string_lossy().to_string();
+
+    // Each frame holds a distinct value so offsets can be checked exactly.
+    let frames = 20_000usize;
+    let audio = AudioData {
+        samples: (0..frames).map(|i| i as f32 / 32_768.0).collect(),
+        sample_rate: 44_100,
+        channels: 1,
+    };
+    write_audio(&path_str, &audio).expect("write_audio failed");
+
+    let range = read_audio_range(
+        &path,
+        Some(TimePosition::Samples(10_000)),
+        Some(TimePosition::Samples(12_000)),
+        500,
+    )
+    .expect("read_audio_range failed");
+
+    assert_eq!(range.decoded_start, 9_500);
+    assert_eq!(range.start, 10_000);
+    assert_eq!(range.end, 12_000);
+    assert_eq!(range.start_offset(), 500);
+    assert_eq!(range.len(), 2_000);
+    assert_eq!(range.audio.samples.len(), 3_000);
+
+    let first = range.audio.samples[range.start_offset()];
+    assert!(approx_eq(first, 10_000.0 / 32_768.0, 1e-4), "got {first}");
+}
+
+#[test]
+fn read_audio_range_clamps_to_end_of_file() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("range/short.wav");
+    let path_str = path.to_string_lossy().to_string();
+
+    let audio = AudioData {
+        samples: vec![0.25; 1_000],
+        sample_rate: 8_000,
+        channels: 1,
+    };
+    write_audio(&path_str, &audio).expect("write_audio failed");
+
+    let range = read_audio_range(&path, Some(TimePosition::Seconds(0.1)), None, 100)
+        .expect("read_audio_range failed");
+    assert_eq!(range.start, 800);
+    assert_eq!(range.end, 1_000);
+    assert_eq!(range.decoded_start, 700);
+
+    let err = read_audio_range(
+        &path,
+        Some(TimePosition::Samples(500)),
+        Some(TimePosition::Samples(400)),
+        0,
+    )
+    .unwrap_err();
+    assert!(format!("{err}").contains("Invalid range"));
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use std::path::Path;
+use tempfile::tempdir;
+
+use common::write_input;
+use stem_splitter_core::{
+    Separator, SeparatedStems, SplitOptions, SplitProgress, Stem, StemScript,
+    TestEngine,
+};
+
+// Four chunks of one second (with overlap) at 44.1 kHz
+const FRAMES: usize = 150_000;
+
+fn engine() -> TestEngine {
+    TestEngine::new()
+        .with_window(4096, 2048)
+        .with_stem("vocals", StemScript::LowPass { cutoff_hz: 500.0 })
+        .with_stem("drums", StemScript::Delay { samples: 7, gain: 0.5 })
+}
+
+fn options(engine: &TestEngine, job_dir: Option<&Path>) -> SplitOptions {
+    SplitOptions {
+        chunk_seconds: Some(1),
+        checkpoint_dir: job_dir.map(|d| d.to_string_lossy().into_owned()),
+        test_engine: Some(engine.clone()),
+        ..Default::default()
+    }
+}
+
+fn assert_bit_identical(a: &SeparatedStems, b: &SeparatedStems) {
+    for &stem in Stem::all() {
+        let (a, b) = (a.get(stem), b.get(stem));
+        assert_eq!(a.len(), b.len());
+        assert!(
+            a.iter().zip(&b).all(|(x, y)| x.to_bits() == y.to_bits()),
+            "{} differs",
+            stem.name()
+        );
+    }
+}
+
+#[test]
+fn interrupted_job_resumes_after_the_last_finished_chunk() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    let input = input.to_str().unwrap();
+    let job = tmp.path().join("job");
+
+    let uninterrupted = engine();
+    let expected = Separator::separate(input, options(&uninterrupted, None)).unwrap();
+    let total_windows = uninterrupted.requests().len();
+
+    // Dies in the third chunk
+    let crashing = engine().with_failure_at(total_windows / 2, "out of memory");
+    assert!(Separator::separate(input, options(&crashing, Some(&job))).is_err());
+    let journal = std::fs::read_to_string(job.join("journal.jsonl")).unwrap();
+    assert_eq!(journal.lines().count(), 3, "header and two chunks:\n{journal}");
+
+    let resumed = engine();
+    let stages = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = stages.clone();
+    stem_splitter_core::set_split_progress_callback(move |p| {
+        if let SplitProgress::Stage(stage) = p {
+            seen.lock().unwrap().push(stage);
+        }
+    });
+    let stems = Separator::separate(input, options(&resumed, Some(&job))).unwrap();
+
+    assert_bit_identical(&stems, &expected);
+    let rerun = resumed.requests().len();
+    assert!(rerun < total_windows * 2 / 3, "{rerun} of {total_windows} windows rerun");
+    assert!(stages.lock().unwrap().contains(&"resume_checkpoint"));
+    // The job directory is cleaned up once finished
+    assert!(!job.exists());
+}
+
+#[test]
+fn checkpoint_of_other_options_is_discarded() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    let input = input.to_str().unwrap();
+    let job = tmp.path().join("job");
+
+    let crashing = engine().with_failure_at(40, "killed");
+    assert!(Separator::separate(input, options(&crashing, Some(&job))).is_err());
+    assert!(job.join("chunk-0001.f32").exists());
+
+    // A different chunk size shapes the output differently: start over
+    let other = engine();
+    let opts = SplitOptions {
+        chunk_seconds: Some(2),
+        ..options(&other, Some(&job))
+    };
+    let stems = Separator::separate(input, opts).unwrap();
+    let expected = Separator::separate(
+        input,
+        SplitOptions {
+            chunk_seconds: Some(2),
+            ..options(&engine(), None)
+        },
+    )
+    .unwrap();
+    assert_bit_identical(&stems, &expected);
+    assert_eq!(other.requests().len(), engine_windows(input, 2));
+}
+
+#[test]
+fn checkpoint_of_other_test_scripts_is_discarded() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    let input = input.to_str().unwrap();
+    let job = tmp.path().join("job");
+
+    let crashing = engine().with_failure_at(40, "killed");
+    assert!(Separator::separate(input, options(&crashing, Some(&job))).is_err());
+    assert!(job.join("chunk-0001.f32").exists());
+
+    // Same manifest, different vocals script: nothing may be replayed
+    let rescripted = || engine().with_stem("vocals", StemScript::Gain(0.9));
+    let other = rescripted();
+    let stems = Separator::separate(input, options(&other, Some(&job))).unwrap();
+    let expected = Separator::separate(input, options(&rescripted(), None)).unwrap();
+    assert_bit_identical(&stems, &expected);
+    assert_eq!(other.requests().len(), engine_windows(input, 1));
+}
+
+#[test]
+fn corrupted_chunk_is_recomputed() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    let input = input.to_str().unwrap();
+    let job = tmp.path().join("job");
+
+    let expected = Separator::separate(input, options(&engine(), None)).unwrap();
+    let crashing = engine().with_failure_at(60, "killed");
+    assert!(Separator::separate(input, options(&crashing, Some(&job))).is_err());
+
+    // Flip a byte of the first chunk and append a torn journal line
+    let chunk = job.join("chunk-0001.f32");
+    let mut bytes = std::fs::read(&chunk).unwrap();
+    bytes[100] ^= 0xff;
+    std::fs::write(&chunk, bytes).unwrap();
+    let journal = job.join("journal.jsonl");
+    let mut text = std::fs::read_to_string(&journal).unwrap();
+    text.push_str("{\"chunk\": 3, \"sta");
+    std::fs::write(&journal, text).unwrap();
+
+    let stems = Separator::separate(input, options(&engine(), Some(&job))).unwrap();
+    assert_bit_identical(&stems, &expected);
+}
+
+/// Windows an uninterrupted run requests with `chunk_seconds`.
+fn engine_windows(input: &str, chunk_seconds: u32) -> usize {
+    let engine = engine();
+    let opts = SplitOptions {
+        chunk_seconds: Some(chunk_seconds),
+        ..options(&engine, None)
+    };
+    Separator::separate(input, opts).unwrap();
+    engine.requests().len()
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the integration tests.
+
+use std::f32::consts::PI;
+use std::path::Path;
+
+use stem_splitter_core::core::audio::write_audio;
+use stem_splitter_core::AudioData;
+
+/// Write `frames` of a stereo tone (440 Hz left, 660 Hz right) as a 44.1 kHz
+/// WAV; returns its interleaved samples.
+pub fn write_input(path: &Path, frames: usize) -> Vec<f32> {
+    let samples: Vec<f32> = (0..frames)
+        .flat_map(|i| {
+            let t = i as f32 / 44_100.0;
+            [(2.0 * PI * 440.0 * t).sin() * 0.2, (2.0 * PI * 660.0 * t).sin() * 0.2]
+        })
+        .collect();
+    let audio = AudioData {
+        samples: samples.clone(),
+        sample_rate: 44_100,
+        channels: 2,
+    };
+    write_audio(path.to_str().unwrap(), &audio).unwrap();
+    samples
+}
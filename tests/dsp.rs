@@ -1,5 +1,8 @@
 use approx::assert_abs_diff_eq;
-use stem_splitter_core::core::dsp::{istft_cac_stereo, stft_cac_stereo_centered, to_planar_stereo};
+use stem_splitter_core::core::dsp::{
+    istft_cac_stereo, istft_cac_stereo_with, stft_cac_stereo_centered, stft_cac_stereo_centered_with,
+    to_planar_stereo, PadMode, Stft, StftOptions, WindowKind,
+};
 
 #[test]
 fn to_planar_stereo_mono_duplicates_channel() {
@@ -63,3 +66,314 @@ fn stft_demucs_dims_reference() {
     assert_eq!(f_bins, 2048);
     assert_eq!(frames, 1 + (t / hop));
 }
+
+#[test]
+fn stft_matches_complex_fft_reference() {
+    use rustfft::{num_complex::Complex32, FftPlanner};
+
+    let n_fft = 512usize;
+    let hop = 128usize;
+    let t = 2000usize;
+    let left: Vec<f32> = (0..t).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+    let right: Vec<f32> = (0..t).map(|i| ((i * 7919) % 101) as f32 / 101.0 - 0.5).collect();
+
+    let opts = StftOptions {
+        window: WindowKind::HannSymmetric,
+        pad_mode: PadMode::Zero,
+    };
+    let (spec, f_bins, frames) = stft_cac_stereo_centered_with(&left, &right, n_fft, hop, &opts);
+    let plane = f_bins * frames;
+
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(n_fft);
+    let pad = n_fft / 2;
+    for fr in [0, 3, frames - 1] {
+        for (ch, sig) in [&left, &right].into_iter().enumerate() {
+            let mut buf: Vec<Complex32> = (0..n_fft)
+                .map(|i| {
+                    let pos = (fr * hop + i) as isize - pad as isize;
+                    let x = if pos >= 0 && (pos as usize) < t { sig[pos as usize] } else { 0.0 };
+                    let w = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (n_fft - 1) as f32).cos();
+                    Complex32::new(x * w, 0.0)
+                })
+                .collect();
+            fft.process(&mut buf);
+            for (fi, bin) in buf.iter().take(f_bins).enumerate() {
+                let idx = fi * frames + fr;
+                assert_abs_diff_eq!(spec[2 * ch * plane + idx], bin.re, epsilon = 1e-3);
+                assert_abs_diff_eq!(spec[(2 * ch + 1) * plane + idx], bin.im, epsilon = 1e-3);
+            }
+        }
+    }
+}
+
+/// Reference spectra and reconstructions generated by
+/// `tests/fixtures/gen_stft_reference.py`: `torch.stft` / `torch.istft` if
+/// the `generator` field names a torch version, otherwise the script's
+/// float64 transcription of them.
+#[test]
+fn stft_matches_reference_fixtures() {
+    let fixture: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/stft_reference.json")).unwrap();
+    let floats = |v: &serde_json::Value| -> Vec<f32> {
+        v.as_array().unwrap().iter().map(|x| x.as_f64().unwrap() as f32).collect()
+    };
+    let n_fft = fixture["n_fft"].as_u64().unwrap() as usize;
+    let hop = fixture["hop"].as_u64().unwrap() as usize;
+    let left = floats(&fixture["left"]);
+    let right = floats(&fixture["right"]);
+
+    for case in fixture["cases"].as_array().unwrap() {
+        let opts = StftOptions {
+            window: serde_json::from_value(case["window"].clone()).unwrap(),
+            pad_mode: serde_json::from_value(case["pad_mode"].clone()).unwrap(),
+        };
+        let (spec, f_bins, frames) = stft_cac_stereo_centered_with(&left, &right, n_fft, hop, &opts);
+        assert_eq!(frames as u64, case["frames"].as_u64().unwrap());
+        let expected = floats(&case["spec"]);
+        assert_eq!(spec.len(), expected.len());
+        for (i, (got, want)) in spec.iter().zip(&expected).enumerate() {
+            assert!((got - want).abs() < 1e-4, "{opts:?} spec[{i}]: {got} vs {want}");
+        }
+
+        let (l, r) = istft_cac_stereo_with(&spec, f_bins, frames, n_fft, hop, left.len(), &opts);
+        for (got, want) in [(l, &case["istft_left"]), (r, &case["istft_right"])] {
+            for (i, (g, w)) in got.iter().zip(floats(want)).enumerate() {
+                assert!((g - w).abs() < 1e-4, "{opts:?} istft[{i}]: {g} vs {w}");
+            }
+        }
+    }
+}
+
+#[test]
+fn stft_defaults_are_periodic_hann_with_reflect_padding() {
+    let opts = StftOptions::default();
+    assert_eq!(opts.window, WindowKind::HannPeriodic);
+    assert_eq!(opts.pad_mode, PadMode::Reflect);
+
+    let parsed: StftOptions =
+        serde_json::from_str(r#"{"window": "hann_symmetric", "pad_mode": {"constant": 0.5}}"#).unwrap();
+    assert_eq!(parsed.window, WindowKind::HannSymmetric);
+    assert_eq!(parsed.pad_mode, PadMode::Constant(0.5));
+}
+
+fn test_signal(channels: usize, len: usize) -> ndarray::Array2<f32> {
+    ndarray::Array2::from_shape_fn((channels, len), |(c, i)| {
+        ((i * (c + 3)) as f32 * 0.037).sin() + 0.2 * (((i * 7919 + c * 31) % 97) as f32 / 97.0 - 0.5)
+    })
+}
+
+#[test]
+fn stft_type_reconstructs_any_channel_count() {
+    for channels in [1usize, 2, 3] {
+        for stft in [
+            Stft::new(256, 64),
+            Stft::new(255, 85).with_window(WindowKind::HannSymmetric),
+            Stft::new(128, 32).with_pad_mode(PadMode::Zero),
+        ] {
+            let x = test_signal(channels, 1001);
+            let spec = stft.forward(x.view()).unwrap();
+            assert_eq!(spec.dim(), (channels, stft.bins(), stft.frames(1001)));
+
+            let y = stft.inverse(spec.view(), Some(1001)).unwrap();
+            assert_eq!(y.dim(), x.dim());
+            for (a, b) in x.iter().zip(y.iter()) {
+                assert_abs_diff_eq!(a, b, epsilon = 1e-4);
+            }
+        }
+    }
+}
+
+#[test]
+fn stft_type_matches_cac_stereo_layout() {
+    let x = test_signal(2, 3000);
+    let (n_fft, hop) = (512, 128);
+    let spec = Stft::new(n_fft, hop).forward(x.view()).unwrap();
+    let (cac, f_bins, frames) = stft_cac_stereo_centered(
+        x.row(0).as_slice().unwrap(),
+        x.row(1).as_slice().unwrap(),
+        n_fft,
+        hop,
+    );
+    let plane = f_bins * frames;
+    for ch in 0..2 {
+        for k in 0..f_bins {
+            for fr in 0..frames {
+                let z = spec[[ch, k, fr]];
+                assert_abs_diff_eq!(z.re, cac[2 * ch * plane + k * frames + fr], epsilon = 1e-3);
+                assert_abs_diff_eq!(z.im, cac[(2 * ch + 1) * plane + k * frames + fr], epsilon = 1e-3);
+            }
+        }
+    }
+}
+
+#[test]
+fn stft_type_rejects_configurations_without_perfect_reconstruction() {
+    let x = test_signal(1, 2048);
+
+    // Uncentered Hann frames have a zero envelope at the first sample
+    let stft = Stft::new(256, 64).with_center(false);
+    let spec = stft.forward(x.view()).unwrap();
+    assert!(stft.inverse(spec.view(), None).is_err());
+
+    // Hann without overlap vanishes at every frame boundary
+    let stft = Stft::new(256, 256);
+    let spec = stft.forward(x.view()).unwrap();
+    assert!(stft.inverse(spec.view(), None).is_err());
+
+    assert!(Stft::new(256, 512).forward(x.view()).is_err());
+}
+
+#[test]
+fn magnitude_and_phase_round_trip_through_from_polar() {
+    use stem_splitter_core::core::dsp::{from_polar, magnitude, phase};
+
+    let stft = Stft::new(256, 64);
+    let x = test_signal(1, 4000);
+    let spec = stft.forward_mono(x.row(0).as_slice().unwrap()).unwrap();
+
+    // Halving the magnitude halves the reconstructed signal
+    let mag = magnitude(&spec) * 0.5;
+    let edited = from_polar(&mag, &phase(&spec)).unwrap();
+    let y = stft.inverse_mono(edited.view(), Some(4000)).unwrap();
+    for (a, b) in x.iter().zip(&y) {
+        assert_abs_diff_eq!(a * 0.5, *b, epsilon = 1e-4);
+    }
+}
+
+/// The STFT/iSTFT as they were before the real-valued FFT path: a padded
+/// copy of each channel and one full complex transform per channel and
+/// frame. Kept as the baseline for `real_fft_stft_istft_timing`.
+fn full_complex_stft_istft(
+    left: &[f32],
+    right: &[f32],
+    n_fft: usize,
+    hop: usize,
+    fft: &dyn rustfft::Fft<f32>,
+    ifft: &dyn rustfft::Fft<f32>,
+) -> (Vec<f32>, Vec<f32>) {
+    use rustfft::num_complex::Complex32;
+
+    let t = left.len();
+    let pad = n_fft / 2;
+    let frames = 1 + t / hop;
+    let f_bins = n_fft / 2;
+    let plane = f_bins * frames;
+    let window: Vec<f32> = (0..n_fft)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n_fft as f32).cos())
+        .collect();
+
+    let mut l_sig = vec![0.0f32; t + 2 * pad];
+    let mut r_sig = vec![0.0f32; t + 2 * pad];
+    l_sig[pad..pad + t].copy_from_slice(left);
+    r_sig[pad..pad + t].copy_from_slice(right);
+
+    let mut spec = vec![0.0f32; 4 * plane];
+    let mut buf_l = vec![Complex32::default(); n_fft];
+    let mut buf_r = vec![Complex32::default(); n_fft];
+    for fr in 0..frames {
+        let start = fr * hop;
+        for i in 0..n_fft {
+            buf_l[i] = Complex32::new(l_sig[start + i] * window[i], 0.0);
+            buf_r[i] = Complex32::new(r_sig[start + i] * window[i], 0.0);
+        }
+        fft.process(&mut buf_l);
+        fft.process(&mut buf_r);
+        for fi in 0..f_bins {
+            let idx = fi * frames + fr;
+            spec[idx] = buf_l[fi].re;
+            spec[plane + idx] = buf_l[fi].im;
+            spec[2 * plane + idx] = buf_r[fi].re;
+            spec[3 * plane + idx] = buf_r[fi].im;
+        }
+    }
+
+    let padded = t + 2 * pad;
+    let mut l_out = vec![0.0f32; padded];
+    let mut r_out = vec![0.0f32; padded];
+    let mut window_sum = vec![0.0f32; padded];
+    let scale = 1.0 / n_fft as f32;
+    for fr in 0..frames {
+        buf_l.fill(Complex32::default());
+        buf_r.fill(Complex32::default());
+        for fi in 0..f_bins {
+            let idx = fi * frames + fr;
+            buf_l[fi] = Complex32::new(spec[idx], spec[plane + idx]);
+            buf_r[fi] = Complex32::new(spec[2 * plane + idx], spec[3 * plane + idx]);
+        }
+        for fi in 1..f_bins {
+            buf_l[n_fft - fi] = buf_l[fi].conj();
+            buf_r[n_fft - fi] = buf_r[fi].conj();
+        }
+        buf_l[0].im = 0.0;
+        buf_r[0].im = 0.0;
+        ifft.process(&mut buf_l);
+        ifft.process(&mut buf_r);
+        let start = fr * hop;
+        for i in 0..n_fft.min(padded - start) {
+            l_out[start + i] += buf_l[i].re * window[i] * scale;
+            r_out[start + i] += buf_r[i].re * window[i] * scale;
+            window_sum[start + i] += window[i] * window[i];
+        }
+    }
+    for (i, &sum) in window_sum.iter().enumerate() {
+        if sum > 1e-10 {
+            l_out[i] /= sum;
+            r_out[i] /= sum;
+        }
+    }
+    (l_out[pad..pad + t].to_vec(), r_out[pad..pad + t].to_vec())
+}
+
+/// Times one HTDemucs window through the real-valued STFT + iSTFT against the
+/// full complex baseline. Run with
+/// `cargo test --release --test dsp -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn real_fft_stft_istft_timing() {
+    use std::time::{Duration, Instant};
+
+    let n_fft = 4096usize;
+    let hop = 1024usize;
+    let t = 343_980usize;
+    let left: Vec<f32> = (0..t).map(|i| (i as f32 * 0.013).sin() * 0.5).collect();
+    let right: Vec<f32> = (0..t).map(|i| ((i * 7919) % 101) as f32 / 101.0 - 0.5).collect();
+    let opts = StftOptions {
+        window: WindowKind::HannPeriodic,
+        pad_mode: PadMode::Zero,
+    };
+
+    let mut planner = rustfft::FftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(n_fft);
+    let ifft = planner.plan_fft_inverse(n_fft);
+
+    let real = || {
+        let (spec, f_bins, frames) = stft_cac_stereo_centered_with(&left, &right, n_fft, hop, &opts);
+        istft_cac_stereo_with(&spec, f_bins, frames, n_fft, hop, t, &opts)
+    };
+    let full = || full_complex_stft_istft(&left, &right, n_fft, hop, &*fft, &*ifft);
+
+    // Both paths compute the same transform
+    let (pl, pr) = real();
+    let (fl, fr) = full();
+    for i in (0..t).step_by(97) {
+        assert_abs_diff_eq!(pl[i], fl[i], epsilon = 1e-4);
+        assert_abs_diff_eq!(pr[i], fr[i], epsilon = 1e-4);
+    }
+
+    // Best of several runs, alternating so both see the same conditions
+    let time = |f: &dyn Fn() -> (Vec<f32>, Vec<f32>)| {
+        let start = Instant::now();
+        std::hint::black_box(f());
+        start.elapsed()
+    };
+    let (mut best_real, mut best_full) = (Duration::MAX, Duration::MAX);
+    for _ in 0..10 {
+        best_real = best_real.min(time(&real));
+        best_full = best_full.min(time(&full));
+    }
+    let speedup = best_full.as_secs_f64() / best_real.as_secs_f64();
+    println!(
+        "STFT + iSTFT per window: real FFT {best_real:?}, full complex {best_full:?} ({speedup:.2}x)"
+    );
+    assert!(speedup > 1.0, "real FFT path is slower than the baseline ({speedup:.2}x)");
+}
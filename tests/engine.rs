@@ -32,3 +32,72 @@ fn engine_mock_accepts_any_t_and_returns_identity_stems() {
         assert_eq!(out[(s, 1, 0)], 0.5);
     }
 }
+
+#[test]
+fn engine_config_deserializes_from_split_options() {
+    use stem_splitter_core::{ExecutionProviderKind, OptimizationLevel, SplitOptions};
+    let opts: SplitOptions = serde_json::from_str(
+        r#"{
+            "output_dir": "out",
+            "model_name": "htdemucs_ort_v1",
+            "manifest_url_override": null,
+            "engine": {
+                "providers": ["cuda", "cpu"],
+                "intra_threads": 4,
+                "optimization_level": "level1",
+                "memory_arena": false
+            }
+        }"#,
+    )
+    .unwrap();
+    let cfg = opts.engine;
+    assert_eq!(
+        cfg.providers,
+        Some(vec![ExecutionProviderKind::Cuda, ExecutionProviderKind::Cpu])
+    );
+    assert_eq!(cfg.intra_threads(), 4);
+    assert!(cfg.inter_threads() >= 1);
+    assert_eq!(cfg.optimization_level, OptimizationLevel::Level1);
+    assert!(!cfg.memory_arena);
+}
+
+#[test]
+fn engine_config_same_sessions_ignores_batch_size() {
+    use stem_splitter_core::{EngineConfig, OptimizationLevel};
+    let base = EngineConfig::default();
+    let batched = EngineConfig {
+        batch_size: 4,
+        ..Default::default()
+    };
+    assert!(base.same_sessions(&batched));
+
+    let resolved = EngineConfig {
+        intra_threads: Some(base.intra_threads()),
+        inter_threads: Some(base.inter_threads()),
+        ..Default::default()
+    };
+    assert!(base.same_sessions(&resolved));
+
+    let pooled = EngineConfig {
+        sessions: 2,
+        ..Default::default()
+    };
+    assert!(!base.same_sessions(&pooled));
+    let tuned = EngineConfig {
+        optimization_level: OptimizationLevel::Level1,
+        ..Default::default()
+    };
+    assert!(!base.same_sessions(&tuned));
+}
+
+#[test]
+fn engine_config_divides_default_threads_among_sessions() {
+    use stem_splitter_core::EngineConfig;
+    let one = EngineConfig::default();
+    let four = EngineConfig {
+        sessions: 4,
+        ..Default::default()
+    };
+    assert_eq!(four.inter_threads(), (one.inter_threads() / 4).max(1));
+    assert_eq!(four.intra_threads(), (one.intra_threads() / 4).max(1));
+}
@@ -0,0 +1,40 @@
+use stem_splitter_core::core::export::{reaper_project, stem_color, ReaperTrack};
+use stem_splitter_core::Stem;
+
+#[test]
+fn reaper_project_lists_tracks_with_items_and_colors() {
+    let tracks = vec![
+        ReaperTrack {
+            name: "Vocals".into(),
+            file: "song_vocals.wav".into(),
+            color: [0x10, 0x20, 0x30],
+            position: 12.5,
+            length: 30.0,
+        },
+        ReaperTrack {
+            name: "Say \"hi\"".into(),
+            file: "drums.wav".into(),
+            color: stem_color(Stem::Drums),
+            position: 12.5,
+            length: 30.0,
+        },
+    ];
+    let rpp = reaper_project(&tracks, 44_100);
+
+    assert!(rpp.starts_with("<REAPER_PROJECT"));
+    assert!(rpp.trim_end().ends_with('>'));
+    assert!(rpp.contains("SAMPLERATE 44100 0 0"));
+    assert_eq!(rpp.matches("<TRACK").count(), 2);
+    assert!(rpp.contains("NAME \"Vocals\""));
+    assert!(rpp.contains("FILE \"song_vocals.wav\""));
+    assert!(rpp.contains("POSITION 12.500000"));
+    assert!(rpp.contains("LENGTH 30.000000"));
+    assert!(rpp.contains(&format!("PEAKCOL {}", 0x0130_2010u32)));
+    // Names containing double quotes use another quote character
+    assert!(rpp.contains("NAME 'Say \"hi\"'"));
+
+    // Chunks are balanced
+    let opens = rpp.lines().filter(|l| l.trim_start().starts_with('<')).count();
+    let closes = rpp.lines().filter(|l| l.trim() == ">").count();
+    assert_eq!(opens, closes);
+}
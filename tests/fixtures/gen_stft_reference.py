@@ -0,0 +1,159 @@
+#!/usr/bin/env python3
+"""Generate stft_reference.json, the STFT/iSTFT reference used by tests/dsp.rs.
+
+Uses torch.stft / torch.istft when PyTorch is installed, recording
+"torch <version>" in the fixture's "generator" field. Otherwise it warns
+and falls back to a float64 transcription of their definitions (explicit
+padding, center=False frames, irfft overlap-add divided by the
+squared-window envelope), recorded as "python-reference". Pass
+--require-torch to fail instead of falling back.
+
+The checked-in fixture still comes from the transcription, so it checks
+the Rust code against that, not against PyTorch itself. Regenerate it
+with --require-torch to change that.
+
+Spectra are stored in the complex-as-channels layout of
+`stft_cac_stereo_centered`: [L.re, L.im, R.re, R.im] x [F, Frames] with the
+Nyquist bin dropped. The iSTFT reference inverts that truncated spectrum.
+
+    python3 tests/fixtures/gen_stft_reference.py --require-torch > tests/fixtures/stft_reference.json
+"""
+import cmath
+import json
+import math
+import sys
+
+N_FFT = 16
+HOP = 4
+T = 37
+
+CASES = [
+    ("hann_periodic", "reflect"),
+    ("hann_periodic", "zero"),
+    ("hann_symmetric", "reflect"),
+    ("hann_symmetric", {"constant": 0.25}),
+]
+
+
+def signals():
+    left = [math.sin(0.3 * i) + 0.1 * ((i * 7) % 5) for i in range(T)]
+    right = [0.5 * math.cos(0.17 * i) - 0.05 * ((i * 3) % 4) for i in range(T)]
+    return left, right
+
+
+def hann(kind):
+    denom = N_FFT if kind == "hann_periodic" else N_FFT - 1
+    return [0.5 - 0.5 * math.cos(2 * math.pi * i / denom) for i in range(N_FFT)]
+
+
+def pad(x, mode):
+    p = N_FFT // 2
+    if mode == "reflect":
+        return x[p:0:-1] + x + x[-2:-p - 2:-1]
+    c = 0.0 if mode == "zero" else mode["constant"]
+    return [c] * p + x + [c] * p
+
+
+def stft_reference(x, kind, mode):
+    w = hann(kind)
+    xp = pad(x, mode)
+    frames = 1 + (len(xp) - N_FFT) // HOP
+    spec = []
+    for f in range(frames):
+        seg = [xp[f * HOP + i] * w[i] for i in range(N_FFT)]
+        spec.append([
+            sum(seg[n] * cmath.exp(-2j * math.pi * k * n / N_FFT) for n in range(N_FFT))
+            for k in range(N_FFT // 2 + 1)
+        ])
+    return spec  # [Frames][F+1]
+
+
+def istft_reference(spec, kind):
+    w = hann(kind)
+    p = N_FFT // 2
+    frames = len(spec)
+    out = [0.0] * (N_FFT + HOP * (frames - 1))
+    env = [0.0] * len(out)
+    for f, bins in enumerate(spec):
+        full = bins + [b.conjugate() for b in bins[-2:0:-1]]
+        for n in range(N_FFT):
+            x = sum(full[k] * cmath.exp(2j * math.pi * k * n / N_FFT) for k in range(N_FFT))
+            out[f * HOP + n] += x.real / N_FFT * w[n]
+            env[f * HOP + n] += w[n] * w[n]
+    return [out[i] / env[i] for i in range(p, p + T)]
+
+
+def stft_torch(x, kind, mode):
+    import torch
+    import torch.nn.functional as F
+
+    w = torch.hann_window(N_FFT, periodic=kind == "hann_periodic", dtype=torch.float64)
+    xt = torch.tensor(x, dtype=torch.float64).view(1, 1, -1)
+    p = N_FFT // 2
+    if mode == "reflect":
+        xp = F.pad(xt, (p, p), mode="reflect")
+    else:
+        c = 0.0 if mode == "zero" else mode["constant"]
+        xp = F.pad(xt, (p, p), mode="constant", value=c)
+    z = torch.stft(xp.view(-1), N_FFT, HOP, window=w, center=False, return_complex=True)
+    return [[complex(v) for v in z[:, f]] for f in range(z.shape[1])]
+
+
+def istft_torch(spec, kind):
+    import torch
+
+    w = torch.hann_window(N_FFT, periodic=kind == "hann_periodic", dtype=torch.float64)
+    z = torch.tensor(spec, dtype=torch.complex128).T
+    x = torch.istft(z, N_FFT, HOP, window=w, center=True, length=T)
+    return x.tolist()
+
+
+def cac(spec_l, spec_r):
+    f_bins = N_FFT // 2
+    out = []
+    for spec in (spec_l, spec_r):
+        for part in ("real", "imag"):
+            for k in range(f_bins):
+                out.extend(getattr(frame[k], part) for frame in spec)
+    return out
+
+
+def main():
+    try:
+        import torch
+
+        stft, istft, generator = stft_torch, istft_torch, f"torch {torch.__version__}"
+    except ImportError:
+        if "--require-torch" in sys.argv[1:]:
+            sys.exit("PyTorch is not installed")
+        print("PyTorch is not installed, using the float64 transcription", file=sys.stderr)
+        stft, istft, generator = stft_reference, istft_reference, "python-reference"
+
+    left, right = signals()
+    cases = []
+    for kind, mode in CASES:
+        spec_l, spec_r = stft(left, kind, mode), stft(right, kind, mode)
+        # The CaC layout has no Nyquist bin; invert what the model sees.
+        trunc = [[*frame[:-1], 0j] for frame in spec_l], [[*frame[:-1], 0j] for frame in spec_r]
+        cases.append({
+            "window": kind,
+            "pad_mode": mode,
+            "frames": len(spec_l),
+            "spec": [round(v, 7) for v in cac(spec_l, spec_r)],
+            "istft_left": [round(v, 7) for v in istft(trunc[0], kind)],
+            "istft_right": [round(v, 7) for v in istft(trunc[1], kind)],
+        })
+
+    json.dump({
+        "generator": generator,
+        "n_fft": N_FFT,
+        "hop": HOP,
+        "left": left,
+        "right": right,
+        "cases": cases,
+    }, sys.stdout, indent=1)
+    print()
+
+
+if __name__ == "__main__":
+    main()
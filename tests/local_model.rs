@@ -0,0 +1,259 @@
+use std::path::Path;
+
+use tempfile::tempdir;
+
+use stem_splitter_core::model::onnx_info::{read_onnx_info, Dim};
+use stem_splitter_core::{load_model_from_path, load_model_from_path_with_manifest};
+
+// Minimal protobuf writer for ONNX test models
+
+fn varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn field(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+    varint(out, number << 3 | 2);
+    varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn field_varint(out: &mut Vec<u8>, number: u64, v: u64) {
+    varint(out, number << 3);
+    varint(out, v);
+}
+
+/// ValueInfoProto; `dims` entries that don't parse as numbers are symbolic.
+fn value_info(name: &str, elem_type: u64, dims: &[&str]) -> Vec<u8> {
+    let mut shape = Vec::new();
+    for d in dims {
+        let mut dim = Vec::new();
+        match d.parse::<u64>() {
+            Ok(n) => field_varint(&mut dim, 1, n),
+            Err(_) => field(&mut dim, 2, d.as_bytes()),
+        }
+        field(&mut shape, 1, &dim);
+    }
+    let mut tensor = Vec::new();
+    field_varint(&mut tensor, 1, elem_type);
+    field(&mut tensor, 2, &shape);
+    let mut ty = Vec::new();
+    field(&mut ty, 1, &tensor);
+    let mut info = Vec::new();
+    field(&mut info, 1, name.as_bytes());
+    field(&mut info, 2, &ty);
+    info
+}
+
+const F32: u64 = 1;
+const F16: u64 = 10;
+
+struct Graph<'a> {
+    inputs: Vec<(&'a str, u64, Vec<&'a str>)>,
+    outputs: Vec<(&'a str, u64, Vec<&'a str>)>,
+    metadata: Vec<(&'a str, &'a str)>,
+}
+
+fn htdemucs_graph(sources: &str) -> Graph<'_> {
+    Graph {
+        inputs: vec![
+            ("input", F32, vec!["batch", "2", "343980"]),
+            ("x", F32, vec!["batch", "4", "2048", "336"]),
+        ],
+        outputs: vec![
+            ("output", F32, vec!["batch", sources, "4", "2048", "336"]),
+            ("add_67", F32, vec!["batch", sources, "2", "343980"]),
+        ],
+        metadata: vec![],
+    }
+}
+
+fn write_onnx(path: &Path, g: &Graph) {
+    let mut graph = Vec::new();
+    // A node and a 1 MiB initializer the reader has to skip; older exporters
+    // also list initializers as inputs
+    let mut node = Vec::new();
+    field(&mut node, 1, b"input");
+    field(&mut node, 4, b"Identity");
+    field(&mut graph, 1, &node);
+    let mut weights = Vec::new();
+    field(&mut weights, 8, b"conv.weight");
+    field(&mut weights, 9, &vec![0u8; 1 << 20]);
+    field(&mut graph, 5, &weights);
+    field(&mut graph, 11, &value_info("conv.weight", F32, &["64", "4096"]));
+    for (name, ty, dims) in &g.inputs {
+        field(&mut graph, 11, &value_info(name, *ty, dims));
+    }
+    for (name, ty, dims) in &g.outputs {
+        field(&mut graph, 12, &value_info(name, *ty, dims));
+    }
+
+    let mut model = Vec::new();
+    field_varint(&mut model, 1, 8);
+    field(&mut model, 7, &graph);
+    for (k, v) in &g.metadata {
+        let mut entry = Vec::new();
+        field(&mut entry, 1, k.as_bytes());
+        field(&mut entry, 2, v.as_bytes());
+        field(&mut model, 14, &entry);
+    }
+    std::fs::write(path, model).unwrap();
+}
+
+#[test]
+fn external_data_files_are_listed() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("model.onnx");
+    write_onnx(&path, &htdemucs_graph("4"));
+    let mut model = std::fs::read(&path).unwrap();
+
+    // A second graph field is merged into the first; its initializers keep
+    // their data in other files
+    let mut graph = Vec::new();
+    for (name, location) in [
+        ("a", "model.onnx.data"),
+        ("b", "model.onnx.data"),
+        ("c", "extra/c.bin"),
+    ] {
+        let mut tensor = Vec::new();
+        field(&mut tensor, 8, name.as_bytes());
+        for (k, v) in [("location", location), ("offset", "0")] {
+            let mut entry = Vec::new();
+            field(&mut entry, 1, k.as_bytes());
+            field(&mut entry, 2, v.as_bytes());
+            field(&mut tensor, 13, &entry);
+        }
+        field_varint(&mut tensor, 14, 1);
+        field(&mut graph, 5, &tensor);
+    }
+    field(&mut model, 7, &graph);
+    std::fs::write(&path, model).unwrap();
+
+    let info = read_onnx_info(&path).unwrap();
+    assert_eq!(info.external_data, ["model.onnx.data", "extra/c.bin"]);
+    assert_eq!(info.inputs.len(), 2);
+}
+
+const MANIFEST: &str = r#"{"name": "from_manifest", "sample_rate": 48000, "window": 343980,
+  "hop": 100000, "stems": ["speech", "noise"]}"#;
+
+#[test]
+fn reads_graph_inputs_outputs_and_metadata() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("model.onnx");
+    let mut graph = htdemucs_graph("4");
+    graph.metadata = vec![("producer", "test"), ("stems", "a,b,c,d")];
+    write_onnx(&path, &graph);
+
+    let info = read_onnx_info(&path).unwrap();
+    let names: Vec<&str> = info.inputs.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["input", "x"]);
+    assert_eq!(info.outputs.len(), 2);
+    assert_eq!(
+        info.input("input").unwrap().shape.as_ref().unwrap()[0],
+        Dim::Symbolic("batch".into())
+    );
+    assert_eq!(info.output("output").unwrap().fixed_dim(1), Some(4));
+    assert_eq!(info.metadata("stems"), Some("a,b,c,d"));
+    assert_eq!(info.input("x").unwrap().to_string(), "x: float32[batch, 4, 2048, 336]");
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    let err = read_onnx_info(&path).unwrap_err();
+    assert!(err.to_string().contains("not a valid ONNX model"), "{err}");
+}
+
+#[test]
+fn manifest_is_inferred_from_the_graph() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("demucs_6s.onnx");
+    write_onnx(&path, &htdemucs_graph("6"));
+    let handle = load_model_from_path(path.to_str().unwrap()).unwrap();
+    let m = &handle.manifest;
+    assert_eq!(m.name, "demucs_6s");
+    assert_eq!((m.sample_rate, m.window, m.hop), (44100, 343980, 171990));
+    assert_eq!(m.stems, ["drums", "bass", "other", "vocals", "guitar", "piano"]);
+
+    let mut graph = htdemucs_graph("n");
+    graph.inputs[1].1 = F16;
+    graph.metadata = vec![("name", "karaoke"), ("stems", "vocals, accompaniment"), ("sample_rate", "48000")];
+    write_onnx(&path, &graph);
+    let m = load_model_from_path(path.to_str().unwrap()).unwrap().manifest;
+    assert_eq!(m.name, "karaoke");
+    assert_eq!(m.sample_rate, 48000);
+    assert_eq!(m.stems, ["vocals", "accompaniment"]);
+    assert_eq!(m.inputs[1].dtype, "float16");
+    assert_eq!(m.inputs[1].shape, ["batch", "4", "2048", "336"]);
+}
+
+#[test]
+fn unusable_graphs_are_described() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("other.onnx");
+    write_onnx(
+        &path,
+        &Graph {
+            inputs: vec![("audio", F32, vec!["1", "2", "44100"])],
+            outputs: vec![("stems", F32, vec!["1", "4", "2", "44100"])],
+            metadata: vec![],
+        },
+    );
+    let err = load_model_from_path(path.to_str().unwrap()).err().unwrap().to_string();
+    assert!(err.contains("missing input `input`"), "{err}");
+    assert!(err.contains("audio: float32[1, 2, 44100]"), "{err}");
+    assert!(err.contains("stems: float32[1, 4, 2, 44100]"), "{err}");
+    assert!(err.contains("other.onnx.manifest.json"), "{err}");
+
+    let mut graph = htdemucs_graph("4");
+    graph.inputs[0].2 = vec!["1", "2", "441000"];
+    write_onnx(&path, &graph);
+    let err = load_model_from_path(path.to_str().unwrap()).err().unwrap().to_string();
+    assert!(err.contains("input `input: float32[1, 2, 441000]` should be [*, 2, 343980]"), "{err}");
+
+    write_onnx(&path, &htdemucs_graph("5"));
+    let err = load_model_from_path(path.to_str().unwrap()).err().unwrap().to_string();
+    assert!(err.contains("5 stems"), "{err}");
+}
+
+#[test]
+fn manifests_next_to_the_model_or_given_explicitly_are_used() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path();
+    // Not a graph at all: the manifest alone describes it
+    let path = dir.join("model.ort");
+    std::fs::write(&path, b"\0\0\0\0ORTM").unwrap();
+    let model = path.to_str().unwrap();
+
+    let explicit = dir.join("explicit.json");
+    std::fs::write(&explicit, MANIFEST).unwrap();
+    let m = load_model_from_path_with_manifest(model, Some(explicit.to_str().unwrap()))
+        .unwrap()
+        .manifest;
+    assert_eq!(m.stems, ["speech", "noise"]);
+
+    // An ORT format model can't be inspected, so it needs a manifest
+    let err = load_model_from_path(model).err().unwrap().to_string();
+    assert!(err.contains("ORT format"), "{err}");
+    assert!(err.contains("model.ort.manifest.json"), "{err}");
+
+    // A shared manifest.json only applies to its own entry file
+    let shared = MANIFEST.replace(
+        r#""stems""#,
+        r#""artifacts": [{"file": "other.onnx", "url": "", "sha256": "", "size_bytes": 0}], "stems""#,
+    );
+    std::fs::write(dir.join("manifest.json"), &shared).unwrap();
+    assert!(load_model_from_path(model).is_err());
+    std::fs::write(dir.join("manifest.json"), shared.replace("other.onnx", "model.ort")).unwrap();
+    assert_eq!(load_model_from_path(model).unwrap().manifest.name, "from_manifest");
+
+    std::fs::write(dir.join("model.ort.manifest.json"), MANIFEST.replace("from_manifest", "sidecar"))
+        .unwrap();
+    assert_eq!(load_model_from_path(model).unwrap().manifest.name, "sidecar");
+
+    std::fs::write(dir.join("model.ort.manifest.json"), "{").unwrap();
+    let err = load_model_from_path(model).err().unwrap().to_string();
+    assert!(err.contains("model.ort.manifest.json"), "{err}");
+}
@@ -0,0 +1,82 @@
+use std::f32::consts::PI;
+
+use stem_splitter_core::core::loudness::{measure_loudness, normalization_gain_db};
+use stem_splitter_core::{AudioData, NormalizeOptions};
+
+fn stereo_sine(sample_rate: u32, freq: f32, amplitude: f32, seconds: f32) -> AudioData {
+    let n = (sample_rate as f32 * seconds) as usize;
+    let mut samples = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let v = (2.0 * PI * freq * i as f32 / sample_rate as f32).sin() * amplitude;
+        samples.push(v);
+        samples.push(v);
+    }
+    AudioData {
+        samples,
+        sample_rate,
+        channels: 2,
+    }
+}
+
+#[test]
+fn sine_1khz_reads_expected_lufs() {
+    // A 1 kHz stereo sine at -20 dBFS peak measures -20 LUFS (BS.1770 calibration).
+    let audio = stereo_sine(48_000, 1000.0, 0.1, 5.0);
+    let stats = audio.loudness();
+    assert!(
+        (stats.integrated_lufs + 20.0).abs() < 0.1,
+        "integrated = {}",
+        stats.integrated_lufs
+    );
+    assert!((stats.short_term_max_lufs + 20.0).abs() < 0.1);
+    assert!(stats.loudness_range_lu.abs() < 0.1);
+    assert!((stats.sample_peak_dbfs + 20.0).abs() < 0.1);
+}
+
+#[test]
+fn true_peak_exceeds_sample_peak_for_intersample_overs() {
+    // fs/4 sine with 45° phase: samples land at ±0.707 of the true amplitude.
+    let sr = 44_100u32;
+    let n = sr as usize;
+    let samples: Vec<f32> = (0..n)
+        .map(|i| (PI / 2.0 * (i % 4) as f32 + PI / 4.0).sin())
+        .collect();
+    let stats = measure_loudness(&samples, 1, sr);
+    assert!((stats.sample_peak_dbfs + 3.01).abs() < 0.05);
+    assert!(stats.true_peak_dbtp > -0.5, "true peak = {}", stats.true_peak_dbtp);
+}
+
+#[test]
+fn silence_is_negative_infinity_and_not_normalized() {
+    let audio = AudioData {
+        samples: vec![0.0; 44_100 * 2],
+        sample_rate: 44_100,
+        channels: 2,
+    };
+    let stats = audio.loudness();
+    assert_eq!(stats.integrated_lufs, f64::NEG_INFINITY);
+    assert_eq!(normalization_gain_db(&stats, &NormalizeOptions::default()), 0.0);
+}
+
+#[test]
+fn normalize_hits_target_and_respects_true_peak_ceiling() {
+    let mut audio = stereo_sine(44_100, 1000.0, 0.05, 4.0);
+    let opts = NormalizeOptions {
+        target_lufs: -16.0,
+        max_true_peak_dbtp: None,
+    };
+    let (reported, gain) = audio.normalize(&opts);
+    assert!(gain > 0.0);
+    let measured = audio.loudness();
+    assert!((measured.integrated_lufs + 16.0).abs() < 0.1);
+    assert!((reported.integrated_lufs - measured.integrated_lufs).abs() < 0.01);
+
+    let mut loud = stereo_sine(44_100, 1000.0, 0.05, 4.0);
+    let opts = NormalizeOptions {
+        target_lufs: 0.0,
+        max_true_peak_dbtp: Some(-1.0),
+    };
+    loud.normalize(&opts);
+    let measured = loud.loudness();
+    assert!(measured.true_peak_dbtp <= -0.99, "tp = {}", measured.true_peak_dbtp);
+}
@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use httpmock::prelude::*;
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+use stem_splitter_core::{ensure_model, set_strict_manifest_verification, trust_manifest_key};
+
+// Trusted keys and strict mode are process-wide
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn key_pair() -> Ed25519KeyPair {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+}
+
+/// Serves a model and its manifest; returns (manifest URL, manifest body,
+/// model mock).
+fn serve_model<'a>(server: &'a MockServer, name: &str) -> (String, String, httpmock::Mock<'a>) {
+    let model = vec![7u8; 4096];
+    let sha = hex::encode(Sha256::digest(&model));
+    let model_mock = server.mock(|when, then| {
+        when.method(GET).path(format!("/{name}.onnx"));
+        then.status(200).body(model.clone());
+    });
+    let manifest = format!(
+        r#"{{"name": "{name}", "version": "1", "backend": "onnx", "sample_rate": 44100,
+  "window": 441000, "hop": 220500, "stems": ["vocals", "drums", "bass", "other"],
+  "artifacts": [{{"file": "{name}.onnx", "url": "{base}/{name}.onnx", "sha256": "{sha}", "size_bytes": 4096}}]}}"#,
+        base = server.base_url()
+    );
+    let body = manifest.clone();
+    server.mock(|when, then| {
+        when.method(GET).path(format!("/{name}.json"));
+        then.status(200).body(body);
+    });
+    (
+        format!("{}/{name}.json", server.base_url()),
+        manifest,
+        model_mock,
+    )
+}
+
+fn serve_signature(server: &MockServer, name: &str, signature: &[u8]) {
+    let body = STANDARD.encode(signature);
+    server.mock(|when, then| {
+        when.method(GET).path(format!("/{name}.json.sig"));
+        then.status(200).body(body);
+    });
+}
+
+// Keys can't be untrusted, so the stages run in order in one test
+#[test]
+fn manifests_are_verified_once_a_key_is_trusted() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = tempdir().unwrap();
+    std::env::set_var("XDG_CACHE_HOME", cache.path());
+    let server = MockServer::start();
+
+    // Without trusted keys, strict mode refuses everything...
+    let (url, manifest, model) = serve_model(&server, "unsigned");
+    set_strict_manifest_verification(true);
+    let strict = ensure_model("ignored", Some(&url));
+    set_strict_manifest_verification(false);
+    let err = strict.err().unwrap();
+    assert!(err.to_string().contains("no keys are trusted"), "{err}");
+    model.assert_hits(0);
+    // ...and otherwise nothing is checked
+    ensure_model("ignored", Some(&url)).unwrap();
+    model.assert_hits(1);
+
+    let publisher = key_pair();
+    trust_manifest_key("publisher", &STANDARD.encode(publisher.public_key().as_ref())).unwrap();
+
+    // Once a key is trusted, unsigned manifests are refused even outside strict mode
+    let (url, _, model) = serve_model(&server, "unsigned_later");
+    let err = ensure_model("ignored", Some(&url)).err().unwrap();
+    assert!(err.to_string().contains("is not signed"), "{err}");
+    model.assert_hits(0);
+
+    let (url, manifest_signed, model) = serve_model(&server, "signed");
+    serve_signature(&server, "signed", publisher.sign(manifest_signed.as_bytes()).as_ref());
+    let handle = ensure_model("ignored", Some(&url)).unwrap();
+    assert!(handle.local_path.exists());
+    model.assert_hits(1);
+
+    // A swapped manifest still carrying another manifest's signature
+    let (url, _, model) = serve_model(&server, "swapped");
+    serve_signature(&server, "swapped", publisher.sign(manifest.as_bytes()).as_ref());
+    let err = ensure_model("ignored", Some(&url)).err().unwrap();
+    assert!(err.to_string().contains("not signed by a trusted key"), "{err}");
+    model.assert_hits(0);
+
+    // Signed, but by someone else
+    let (url, manifest, model) = serve_model(&server, "stranger");
+    serve_signature(&server, "stranger", key_pair().sign(manifest.as_bytes()).as_ref());
+    assert!(ensure_model("ignored", Some(&url)).is_err());
+    model.assert_hits(0);
+}
+
+#[test]
+fn malformed_keys_are_rejected() {
+    let err = trust_manifest_key("short", &STANDARD.encode([1u8; 16])).unwrap_err();
+    assert!(err.to_string().contains("`short`"), "{err}");
+    assert!(trust_manifest_key("garbage", "not base64!").is_err());
+}
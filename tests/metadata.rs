@@ -0,0 +1,177 @@
+use tempfile::tempdir;
+
+use stem_splitter_core::core::audio::{read_audio_range, write_audio_with_metadata};
+use stem_splitter_core::{AudioData, AudioMetadata, Picture};
+
+fn sample_metadata() -> AudioMetadata {
+    AudioMetadata {
+        title: Some("Song".into()),
+        artist: Some("Artist".into()),
+        album: Some("Album".into()),
+        genre: Some("House".into()),
+        date: Some("2024-05-01".into()),
+        comment: Some("ünïcödé comment".into()),
+        cover: Some(Picture {
+            media_type: "image/png".into(),
+            data: vec![0x89, b'P', b'N', b'G', 1, 2, 3],
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn for_stem_appends_stem_name_to_title() {
+    let meta = sample_metadata().for_stem("Vocals");
+    assert_eq!(meta.title.as_deref(), Some("Song (Vocals)"));
+    assert_eq!(meta.artist.as_deref(), Some("Artist"));
+    assert!(meta.cover.is_some());
+
+    let untitled = AudioMetadata::default().for_stem("Drums");
+    assert_eq!(untitled.title.as_deref(), Some("Drums"));
+}
+
+#[test]
+fn wav_tags_roundtrip_and_file_stays_valid() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("tagged.wav");
+    let path_str = path.to_string_lossy().to_string();
+
+    let audio = AudioData {
+        samples: vec![0.1, -0.1, 0.2, -0.2, 0.3, -0.3],
+        sample_rate: 44_100,
+        channels: 2,
+    };
+    let meta = sample_metadata().for_stem("Vocals");
+    write_audio_with_metadata(&path_str, &audio, &meta).expect("write failed");
+
+    // Other readers skip the extra chunks
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().channels, 2);
+    assert_eq!(reader.into_samples::<i16>().count(), 6);
+
+    // RIFF INFO tags are read back by symphonia
+    let decoded = read_audio_range(&path, None, None, 0).unwrap();
+    assert_eq!(decoded.audio.samples.len(), 6);
+    let back = decoded.metadata;
+    assert_eq!(back.title.as_deref(), Some("Song (Vocals)"));
+    assert_eq!(back.artist.as_deref(), Some("Artist"));
+    assert_eq!(back.album.as_deref(), Some("Album"));
+    assert_eq!(back.genre.as_deref(), Some("House"));
+    assert_eq!(back.comment.as_deref(), Some("ünïcödé comment"));
+
+    // Cover art travels in the ID3 chunk
+    let bytes = std::fs::read(&path).unwrap();
+    let id3 = bytes.windows(4).position(|w| w == b"id3 ").expect("no id3 chunk");
+    let data = bytes.windows(4).position(|w| w == b"data").expect("no data chunk");
+    assert!(id3 < data, "tags should precede audio data");
+    assert_eq!(&bytes[id3 + 8..id3 + 11], b"ID3");
+    let apic = bytes.windows(4).position(|w| w == b"APIC").expect("no APIC frame");
+    assert!(bytes[apic..].windows(9).any(|w| w == b"image/png"));
+
+    let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff_len, bytes.len() - 8);
+}
+
+#[test]
+fn empty_metadata_writes_plain_wav() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("plain.wav");
+    let audio = AudioData {
+        samples: vec![0.0; 4],
+        sample_rate: 8_000,
+        channels: 1,
+    };
+    write_audio_with_metadata(path.to_str().unwrap(), &audio, &AudioMetadata::default())
+        .unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(!bytes.windows(4).any(|w| w == b"LIST"));
+    assert!(read_audio_range(&path, None, None, 0).unwrap().metadata.is_empty());
+}
+
+/// A 44.1 kHz stereo FLAC file with one verbatim frame of `samples` per
+/// channel and the given Vorbis comments.
+fn flac_file(samples: &[i16], comments: &[&str]) -> Vec<u8> {
+    fn crc8(bytes: &[u8]) -> u8 {
+        bytes.iter().fold(0u8, |mut crc, &b| {
+            crc ^= b;
+            for _ in 0..8 {
+                crc = if crc & 0x80 != 0 { crc << 1 ^ 0x07 } else { crc << 1 };
+            }
+            crc
+        })
+    }
+    fn crc16(bytes: &[u8]) -> u16 {
+        bytes.iter().fold(0u16, |mut crc, &b| {
+            crc ^= u16::from(b) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 != 0 { crc << 1 ^ 0x8005 } else { crc << 1 };
+            }
+            crc
+        })
+    }
+
+    let n = samples.len() as u64;
+    let mut out = b"fLaC".to_vec();
+    // STREAMINFO: block sizes, unknown frame sizes, rate/channels/bits/total, no MD5
+    out.extend_from_slice(&[0x00, 0, 0, 34]);
+    out.extend_from_slice(&(n as u16).to_be_bytes());
+    out.extend_from_slice(&(n as u16).to_be_bytes());
+    out.extend_from_slice(&[0; 6]);
+    let packed: u64 = 44_100 << 44 | 1 << 41 | 15 << 36 | n;
+    out.extend_from_slice(&packed.to_be_bytes());
+    out.extend_from_slice(&[0; 16]);
+
+    // VORBIS_COMMENT, the last metadata block
+    let mut block = Vec::new();
+    block.extend_from_slice(&4u32.to_le_bytes());
+    block.extend_from_slice(b"test");
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for c in comments {
+        block.extend_from_slice(&(c.len() as u32).to_le_bytes());
+        block.extend_from_slice(c.as_bytes());
+    }
+    out.push(0x80 | 4);
+    out.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&block);
+
+    // Frame: block size n (8-bit code), 44.1 kHz, independent stereo, 16 bit
+    let mut frame = vec![0xFF, 0xF8, 0x69, 0x18, 0x00, (n - 1) as u8];
+    frame.push(crc8(&frame));
+    // The same verbatim subframe for both channels
+    let mut subframe = vec![0x02];
+    for s in samples {
+        subframe.extend_from_slice(&s.to_be_bytes());
+    }
+    frame.extend_from_slice(&subframe);
+    frame.extend_from_slice(&subframe);
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_be_bytes());
+    out.extend_from_slice(&frame);
+    out
+}
+
+#[test]
+fn flac_tags_are_read() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("tagged.flac");
+    let samples: Vec<i16> = (0..64).map(|i| (i * 300) as i16).collect();
+    let comments = ["TITLE=Flac Song", "ARTIST=Flac Artist", "GENRE=Ambient"];
+    std::fs::write(&path, flac_file(&samples, &comments)).unwrap();
+
+    let decoded = read_audio_range(&path, None, None, 0).unwrap();
+    assert_eq!(decoded.audio.channels, 2);
+    assert_eq!(decoded.audio.samples.len(), 128);
+    assert!((decoded.audio.samples[2] - 300.0 / 32768.0).abs() < 1e-6);
+    let meta = decoded.metadata;
+    assert_eq!(meta.title.as_deref(), Some("Flac Song"));
+    assert_eq!(meta.artist.as_deref(), Some("Flac Artist"));
+    assert_eq!(meta.genre.as_deref(), Some("Ambient"));
+
+    // And carried into a stem
+    let out = tmp.path().join("stem.wav");
+    write_audio_with_metadata(out.to_str().unwrap(), &decoded.audio, &meta.for_stem("Vocals"))
+        .unwrap();
+    let back = read_audio_range(&out, None, None, 0).unwrap();
+    assert_eq!(back.metadata.title.as_deref(), Some("Flac Song (Vocals)"));
+    assert_eq!(back.audio.samples.len(), 128);
+}
@@ -0,0 +1,89 @@
+use approx::assert_abs_diff_eq;
+use stem_splitter_core::core::mixer::apply_limiter;
+use stem_splitter_core::{LimiterSpec, MixSpec, Stem};
+
+#[test]
+fn default_spec_is_unity_centered() {
+    let spec = MixSpec::default();
+    for &stem in Stem::all() {
+        let [l, r] = spec.stem_gains(stem).unwrap();
+        assert_abs_diff_eq!(l, 1.0, epsilon = 1e-6);
+        assert_abs_diff_eq!(r, 1.0, epsilon = 1e-6);
+    }
+}
+
+#[test]
+fn gain_and_constant_power_pan() {
+    let spec = MixSpec::default()
+        .gain(Stem::Vocals, -12.0)
+        .pan(Stem::Drums, -1.0)
+        .pan(Stem::Bass, 0.5);
+
+    let [l, r] = spec.stem_gains(Stem::Vocals).unwrap();
+    assert_abs_diff_eq!(l, 10f32.powf(-12.0 / 20.0), epsilon = 1e-6);
+    assert_abs_diff_eq!(r, l, epsilon = 1e-6);
+
+    let [l, r] = spec.stem_gains(Stem::Drums).unwrap();
+    assert_abs_diff_eq!(l, std::f32::consts::SQRT_2, epsilon = 1e-6);
+    assert_abs_diff_eq!(r, 0.0, epsilon = 1e-6);
+
+    // Total power is constant across pan positions.
+    let [l, r] = spec.stem_gains(Stem::Bass).unwrap();
+    assert_abs_diff_eq!(l * l + r * r, 2.0, epsilon = 1e-5);
+}
+
+#[test]
+fn mute_and_solo() {
+    let spec = MixSpec::except(&[Stem::Vocals]);
+    assert!(spec.stem_gains(Stem::Vocals).is_none());
+    assert!(spec.stem_gains(Stem::Drums).is_some());
+
+    let spec = MixSpec::default().solo(Stem::Bass).solo(Stem::Drums).mute(Stem::Drums);
+    assert!(spec.stem_gains(Stem::Bass).is_some());
+    assert!(spec.stem_gains(Stem::Drums).is_none());
+    assert!(spec.stem_gains(Stem::Vocals).is_none());
+    assert!(spec.stem_gains(Stem::Other).is_none());
+
+    let spec = MixSpec::only(&[Stem::Drums, Stem::Bass]);
+    assert!(spec.stem_gains(Stem::Vocals).is_none());
+    assert!(spec.stem_gains(Stem::Other).is_none());
+}
+
+#[test]
+fn mix_spec_json_roundtrip() {
+    let spec = MixSpec::default()
+        .gain(Stem::Vocals, -12.0)
+        .mute(Stem::Other)
+        .with_limiter(LimiterSpec::default());
+    let json = serde_json::to_string(&spec).unwrap();
+    let back: MixSpec = serde_json::from_str(&json).unwrap();
+    assert_eq!(spec, back);
+
+    let partial: MixSpec =
+        serde_json::from_str(r#"{"stems": {"drums": {"gain_db": 3.0}}}"#).unwrap();
+    assert_eq!(partial.stems[&Stem::Drums].gain_db, 3.0);
+    assert!(!partial.stems[&Stem::Drums].mute);
+    assert!(partial.limiter.is_none());
+}
+
+#[test]
+fn limiter_keeps_samples_under_ceiling() {
+    let sr = 44_100;
+    let mut samples: Vec<f32> = (0..sr as usize)
+        .flat_map(|i| {
+            let v = (i as f32 * 0.05).sin() * 0.5;
+            [v, v]
+        })
+        .collect();
+    samples[2 * 20_000] = 1.8;
+    samples[2 * 20_000 + 1] = -1.5;
+
+    let spec = LimiterSpec::default();
+    apply_limiter(&mut samples, 2, sr, &spec);
+
+    let ceiling = 10f32.powf(spec.ceiling_db / 20.0);
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    assert!(peak <= ceiling + 1e-6, "peak {peak} > ceiling {ceiling}");
+    // Far from the transient the signal is untouched.
+    assert_abs_diff_eq!(samples[2 * 1000], (1000.0f32 * 0.05).sin() * 0.5, epsilon = 1e-6);
+}
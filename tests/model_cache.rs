@@ -0,0 +1,95 @@
+use std::time::{Duration, SystemTime};
+
+use httpmock::prelude::*;
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+use stem_splitter_core::{ensure_model_in, ModelCache};
+
+/// Serve version `version` of model `name`; returns its manifest URL.
+fn serve(server: &MockServer, name: &str, version: u8, len: usize) -> String {
+    let model = vec![version; len];
+    let sha = hex::encode(Sha256::digest(&model));
+    let file = format!("{name}-v{version}.onnx");
+    server.mock(|when, then| {
+        when.method(GET).path(format!("/{file}"));
+        then.status(200).body(model.clone());
+    });
+    let manifest = format!(
+        r#"{{"name": "{name}", "version": "{version}", "backend": "onnx", "sample_rate": 44100,
+  "window": 441000, "hop": 220500, "stems": ["vocals", "drums", "bass", "other"],
+  "artifacts": [{{"file": "{file}", "url": "{base}/{file}", "sha256": "{sha}", "size_bytes": {len}}}]}}"#,
+        base = server.base_url()
+    );
+    let path = format!("/{name}-v{version}.json");
+    server.mock(|when, then| {
+        when.method(GET).path(path.clone());
+        then.status(200).body(manifest);
+    });
+    format!("{}{path}", server.base_url())
+}
+
+fn set_last_used(path: &std::path::Path, ago_secs: u64) {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".manifest.json");
+    let used = SystemTime::now() - Duration::from_secs(ago_secs);
+    filetime::set_file_mtime(sidecar, filetime::FileTime::from_system_time(used)).unwrap();
+}
+
+#[test]
+fn lists_verifies_and_prunes_cached_models() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("models");
+    let server = MockServer::start();
+
+    let old = ensure_model_in("", Some(&serve(&server, "alpha", 1, 1000)), &dir).unwrap();
+    let new = ensure_model_in("", Some(&serve(&server, "alpha", 2, 2000)), &dir).unwrap();
+    let beta = ensure_model_in("", Some(&serve(&server, "beta", 3, 4000)), &dir).unwrap();
+    set_last_used(&old.local_path, 300);
+    set_last_used(&new.local_path, 200);
+    set_last_used(&beta.local_path, 100);
+    std::fs::write(dir.join("alpha-abcdef12.part"), vec![0u8; 500]).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a model").unwrap();
+    // Looks like a model, but without a manifest it may still be downloading
+    std::fs::write(dir.join("gamma-0123abcd.onnx"), vec![0u8; 800]).unwrap();
+    std::fs::create_dir(dir.join("delta-0123abcd")).unwrap();
+
+    let cache = ModelCache::open(Some(dir.to_str().unwrap())).unwrap();
+    let models = cache.list().unwrap();
+    let summary: Vec<(&str, u64)> = models.iter().map(|m| (m.name.as_str(), m.size_bytes)).collect();
+    assert_eq!(summary, vec![("alpha", 2000), ("alpha", 1000), ("beta", 4000)]);
+    assert_eq!(models[0].manifest.as_ref().unwrap().version, "2");
+    assert_eq!(cache.size_bytes().unwrap(), 7000);
+
+    // Corrupt one version
+    std::fs::write(&old.local_path, vec![9u8; 1000]).unwrap();
+    let verified: Vec<bool> = cache.verify().unwrap().into_iter().map(|(_, ok)| ok).collect();
+    assert_eq!(verified, vec![true, false, true]);
+
+    let partial = cache.remove_partial_downloads().unwrap();
+    assert_eq!(partial.freed_bytes, 500);
+    assert!(!dir.join("alpha-abcdef12.part").exists());
+
+    let pruned = cache.prune_versions(1).unwrap();
+    assert_eq!(pruned.removed, vec![old.local_path.clone()]);
+    assert_eq!(pruned.freed_bytes, 1000);
+
+    // Using a model makes it the most recent
+    ensure_model_in("", Some(&serve(&server, "alpha", 2, 2000)), &dir).unwrap();
+    let pruned = cache.prune_to_size(3000).unwrap();
+    assert_eq!(pruned.removed, vec![beta.local_path.clone()]);
+    let left: Vec<String> = cache.list().unwrap().into_iter().map(|m| m.name).collect();
+    assert_eq!(left, vec!["alpha"]);
+    assert!(dir.join("notes.txt").exists());
+    assert!(dir.join("gamma-0123abcd.onnx").exists());
+    assert!(dir.join("delta-0123abcd").exists());
+}
+
+#[test]
+fn cache_dir_env_var_moves_the_default_cache() {
+    let tmp = tempdir().unwrap();
+    std::env::set_var("STEM_SPLITTER_CACHE_DIR", tmp.path());
+    let cache = ModelCache::open(None).unwrap();
+    std::env::remove_var("STEM_SPLITTER_CACHE_DIR");
+    assert_eq!(cache.dir(), tmp.path().join("models"));
+}
@@ -145,3 +145,195 @@ fn checksum_mismatch_returns_error() {
         }
     }
 }
+
+#[test]
+fn registry_lists_published_models() {
+    use stem_splitter_core::list_models;
+    let models = list_models().unwrap();
+    let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["htdemucs_ort_v1"]);
+    assert_eq!(models[0].precision, "fp32");
+    assert!(models.iter().all(|m| !m.description.is_empty()));
+}
+
+#[test]
+fn manifest_tensor_dtypes_are_parsed() {
+    use stem_splitter_core::{ModelManifest, TensorDType};
+    let mf: ModelManifest = serde_json::from_str(
+        r#"{
+  "name": "m", "version": "1", "backend": "onnx", "sample_rate": 44100,
+  "window": 343980, "hop": 171990, "stems": [],
+  "inputs": [
+    {"name": "input", "dtype": "float16"},
+    {"name": "x"}
+  ],
+  "outputs": [{"name": "output", "dtype": "int4"}]
+}"#,
+    )
+    .unwrap();
+    assert_eq!(mf.inputs[0].tensor_dtype(), Ok(Some(TensorDType::Float16)));
+    assert_eq!(mf.inputs[1].tensor_dtype(), Ok(None));
+    assert!(mf.outputs[0].tensor_dtype().is_err());
+}
+
+#[test]
+fn manifest_stft_options_default_to_torch_and_can_be_overridden() {
+    use stem_splitter_core::core::dsp::{PadMode, StftOptions, WindowKind};
+    use stem_splitter_core::ModelManifest;
+
+    let base = r#""name": "m", "sample_rate": 44100, "window": 343980, "hop": 171990"#;
+    let mf: ModelManifest = serde_json::from_str(&format!("{{{base}}}")).unwrap();
+    assert_eq!(mf.stft, StftOptions::default());
+
+    let mf: ModelManifest = serde_json::from_str(&format!(
+        r#"{{{base}, "stft": {{"window": "hann_symmetric", "pad_mode": "zero"}}}}"#
+    ))
+    .unwrap();
+    assert_eq!(mf.stft.window, WindowKind::HannSymmetric);
+    assert_eq!(mf.stft.pad_mode, PadMode::Zero);
+}
+
+#[test]
+fn multi_file_models_are_cached_in_one_directory() {
+    use stem_splitter_core::{ensure_model_in, ModelCache};
+
+    let tmp = tempdir().unwrap();
+    let server = MockServer::start();
+    let (graph, graph_sha, graph_len) = make_fake_model_bytes(4096);
+    let weights = vec![7u8; 10_000];
+    let weights_sha = hex::encode(Sha256::digest(&weights));
+
+    let graph_mock = server.mock(|when, then| {
+        when.method(GET).path("/model.onnx");
+        then.status(200).body(graph.clone());
+    });
+    let weights_mock = server.mock(|when, then| {
+        when.method(GET).path("/model.onnx.data");
+        then.status(200).body(weights.clone());
+    });
+    let manifest_with = |data_file: &str, data_path: &str, data_sha: &str, stems: &str| {
+        format!(
+            r#"{{"name": "split_v2", "sample_rate": 44100, "window": 441000, "hop": 220500,
+  "stems": {stems}, "entry": "model.onnx",
+  "artifacts": [
+    {{"file": "{data_file}", "url": "{base}{data_path}", "sha256": "{data_sha}", "size_bytes": 10000}},
+    {{"file": "model.onnx", "url": "{base}/model.onnx", "sha256": "{graph_sha}", "size_bytes": {graph_len}}},
+    {{"file": "config/model.yaml", "url": "{base}/model.onnx", "sha256": "{graph_sha}", "size_bytes": {graph_len}}}
+  ]}}"#,
+            base = server.base_url()
+        )
+    };
+    let manifest = |data_file: &str| {
+        manifest_with(
+            data_file,
+            "/model.onnx.data",
+            &weights_sha,
+            r#"["vocals", "other"]"#,
+        )
+    };
+    server.mock(|when, then| {
+        when.method(GET).path("/split_v2.json");
+        then.status(200).body(manifest("model.onnx.data"));
+    });
+
+    let url = server.url("/split_v2.json");
+    let handle = ensure_model_in("", Some(&url), tmp.path()).unwrap();
+    let dir = handle.local_path.parent().unwrap().to_path_buf();
+    assert_eq!(handle.local_path, dir.join("model.onnx"));
+    let dir_name = dir.file_name().unwrap().to_str().unwrap();
+    assert!(dir_name.starts_with("split_v2-"), "{dir_name}");
+    // Named after all artifacts, not just the entry
+    assert_ne!(dir_name, format!("split_v2-{}", &graph_sha[..8]));
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), graph);
+    assert_eq!(std::fs::read(dir.join("model.onnx.data")).unwrap(), weights);
+    assert!(dir.join("config/model.yaml").is_file());
+
+    // Cached files are reused; a damaged one is fetched again
+    ensure_model_in("", Some(&url), tmp.path()).unwrap();
+    weights_mock.assert_hits(1);
+    graph_mock.assert_hits(2);
+    std::fs::write(dir.join("model.onnx.data"), b"truncated").unwrap();
+    let cache = ModelCache::open(Some(tmp.path().to_str().unwrap())).unwrap();
+    let checked = cache.verify().unwrap();
+    assert_eq!(checked.len(), 1);
+    assert_eq!(checked[0].0.path, dir);
+    assert!(!checked[0].1);
+    ensure_model_in("", Some(&url), tmp.path()).unwrap();
+    weights_mock.assert_hits(2);
+
+    let listed = cache.list().unwrap();
+    assert_eq!(listed[0].name, "split_v2");
+    assert_eq!(listed[0].size_bytes, 10_000 + 2 * graph_len);
+    assert!(cache.verify().unwrap()[0].1);
+    cache.remove(&listed[0]).unwrap();
+    assert!(!dir.exists());
+
+    // New weights for the same graph go to a new directory, next to the
+    // old version, and each is verified against its own hashes
+    let old = ensure_model_in("", Some(&url), tmp.path()).unwrap();
+    let weights_v2 = vec![9u8; 10_000];
+    let weights_v2_sha = hex::encode(Sha256::digest(&weights_v2));
+    server.mock(|when, then| {
+        when.method(GET).path("/v2/model.onnx.data");
+        then.status(200).body(weights_v2.clone());
+    });
+    let manifest_v2 = manifest_with(
+        "model.onnx.data",
+        "/v2/model.onnx.data",
+        &weights_v2_sha,
+        r#"["vocals", "other"]"#,
+    );
+    server.mock(|when, then| {
+        when.method(GET).path("/split_v2_new.json");
+        then.status(200).body(manifest_v2.clone());
+    });
+    let new = ensure_model_in("", Some(&server.url("/split_v2_new.json")), tmp.path()).unwrap();
+    assert_ne!(new.local_path, old.local_path);
+    assert_eq!(
+        std::fs::read(old.local_path.with_extension("onnx.data")).unwrap(),
+        weights
+    );
+    assert_eq!(
+        std::fs::read(new.local_path.with_extension("onnx.data")).unwrap(),
+        weights_v2
+    );
+    assert_eq!(cache.list().unwrap().len(), 2);
+    assert!(cache.verify().unwrap().iter().all(|(_, ok)| *ok));
+
+    // A changed manifest for the same files replaces the recorded one
+    let relabeled = manifest_with(
+        "model.onnx.data",
+        "/v2/model.onnx.data",
+        &weights_v2_sha,
+        r#"["speech", "rest"]"#,
+    );
+    server.mock(|when, then| {
+        when.method(GET).path("/split_v2_relabeled.json");
+        then.status(200).body(relabeled.clone());
+    });
+    let same = ensure_model_in(
+        "",
+        Some(&server.url("/split_v2_relabeled.json")),
+        tmp.path(),
+    )
+    .unwrap();
+    assert_eq!(same.local_path, new.local_path);
+    let recorded = cache
+        .list()
+        .unwrap()
+        .into_iter()
+        .find(|m| m.path == new.local_path.parent().unwrap())
+        .and_then(|m| m.manifest)
+        .unwrap();
+    assert_eq!(recorded.stems, ["speech", "rest"]);
+
+    // Artifacts can't escape the model directory
+    server.mock(|when, then| {
+        when.method(GET).path("/escape.json");
+        then.status(200).body(manifest("../model.onnx.data"));
+    });
+    let err = ensure_model_in("", Some(&server.url("/escape.json")), tmp.path())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("relative path"), "{err}");
+}
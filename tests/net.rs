@@ -0,0 +1,245 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+use stem_splitter_core::{ensure_model_in, set_net_config, NetConfig};
+
+// The network configuration is process-wide
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn model_bytes() -> (Vec<u8>, String) {
+    let model: Vec<u8> = (0..8192u32).map(|i| (i * 31 % 251) as u8).collect();
+    let sha = hex::encode(Sha256::digest(&model));
+    (model, sha)
+}
+
+fn manifest(name: &str, url: &str, mirrors: &[String], sha: &str) -> String {
+    format!(
+        r#"{{"name": "{name}", "version": "1", "backend": "onnx", "sample_rate": 44100,
+  "window": 441000, "hop": 220500, "stems": ["vocals", "drums", "bass", "other"],
+  "artifacts": [{{"file": "{name}.onnx", "url": "{url}", "mirrors": {mirrors}, "sha256": "{sha}", "size_bytes": 8192}}]}}"#,
+        mirrors = serde_json::to_string(mirrors).unwrap()
+    )
+}
+
+fn serve_manifest(server: &MockServer, name: &str, body: String) -> String {
+    server.mock(|when, then| {
+        when.method(GET).path(format!("/{name}.json"));
+        then.status(200).body(body);
+    });
+    format!("{}/{name}.json", server.base_url())
+}
+
+#[test]
+fn mirrors_are_tried_in_order() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = tempdir().unwrap();
+    let (model, sha) = model_bytes();
+    let server = MockServer::start();
+
+    let down = server.mock(|when, then| {
+        when.method(GET).path("/down.onnx");
+        then.status(503);
+    });
+    let tampered = server.mock(|when, then| {
+        when.method(GET).path("/tampered.onnx");
+        then.status(200).body(vec![0u8; 8192]);
+    });
+    let good = server.mock(|when, then| {
+        when.method(GET).path("/good.onnx");
+        then.status(200).body(model.clone());
+    });
+
+    let mirrors = [server.url("/tampered.onnx"), server.url("/good.onnx")];
+    let url = serve_manifest(
+        &server,
+        "mirrored",
+        manifest("mirrored", &server.url("/down.onnx"), &mirrors, &sha),
+    );
+    let handle = ensure_model_in("", Some(&url), tmp.path()).unwrap();
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), model);
+    down.assert_hits(1);
+    tampered.assert_hits(1);
+    good.assert_hits(1);
+
+    // Every source failing reports the last error
+    let url = serve_manifest(
+        &server,
+        "broken",
+        manifest("broken", &server.url("/down.onnx"), &[server.url("/tampered.onnx")], &sha),
+    );
+    let err = ensure_model_in("", Some(&url), tmp.path()).err().unwrap();
+    assert!(err.to_string().contains("Checksum mismatch"), "{err}");
+}
+
+#[test]
+fn configured_headers_token_and_timeout_are_used() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = tempdir().unwrap();
+    let (model, sha) = model_bytes();
+    let server = MockServer::start();
+
+    let slow = server.mock(|when, then| {
+        when.method(GET).path("/slow.onnx");
+        then.status(200).delay(Duration::from_secs(5)).body(model.clone());
+    });
+    let private = server.mock(|when, then| {
+        when.method(GET)
+            .path("/private.onnx")
+            .header("authorization", "Bearer secret")
+            .header("x-team", "audio");
+        then.status(200).body(model.clone());
+    });
+    let url = server.url("/private.json");
+    let body = manifest("private", &server.url("/slow.onnx"), &[server.url("/private.onnx")], &sha);
+    let manifest_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/private.json")
+            .header("authorization", "Bearer secret");
+        then.status(200).body(body);
+    });
+
+    set_net_config(NetConfig {
+        headers: vec![("X-Team".into(), "audio".into())],
+        bearer_token: Some("secret".into()),
+        auth_hosts: vec!["127.0.0.1".into()],
+        timeout: Duration::from_millis(500),
+        ..Default::default()
+    })
+    .unwrap();
+    let result = ensure_model_in("", Some(&url), tmp.path());
+    set_net_config(NetConfig::default()).unwrap();
+
+    result.unwrap();
+    manifest_mock.assert_hits(1);
+    slow.assert_hits(1);
+    private.assert_hits(1);
+}
+
+#[test]
+fn credentials_are_only_sent_to_auth_hosts() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = tempdir().unwrap();
+    let (model, sha) = model_bytes();
+    let server = MockServer::start();
+    let mirror = MockServer::start();
+
+    let primary = server.mock(|when, then| {
+        when.method(GET)
+            .path("/down.onnx")
+            .header("authorization", "Bearer secret")
+            .header("x-team", "audio");
+        then.status(503);
+    });
+    let leaked = mirror.mock(|when, then| {
+        when.method(GET).header_exists("authorization");
+        then.status(403);
+    });
+    let leaked_header = mirror.mock(|when, then| {
+        when.method(GET).header_exists("x-team");
+        then.status(403);
+    });
+    let mirrored = mirror.mock(|when, then| {
+        when.method(GET).path("/model.onnx");
+        then.status(200).body(model.clone());
+    });
+    // Same machine, but another host name
+    let mirror_url = format!("http://localhost:{}/model.onnx", mirror.port());
+    let url = serve_manifest(
+        &server,
+        "scoped",
+        manifest("scoped", &server.url("/down.onnx"), &[mirror_url], &sha),
+    );
+
+    set_net_config(NetConfig {
+        headers: vec![("X-Team".into(), "audio".into())],
+        bearer_token: Some("secret".into()),
+        auth_hosts: vec!["127.0.0.1".into()],
+        ..Default::default()
+    })
+    .unwrap();
+    let result = ensure_model_in("", Some(&url), tmp.path());
+    set_net_config(NetConfig::default()).unwrap();
+
+    result.unwrap();
+    primary.assert_hits(1);
+    leaked.assert_hits(0);
+    leaked_header.assert_hits(0);
+    mirrored.assert_hits(1);
+}
+
+#[test]
+fn hf_token_is_only_sent_to_hugging_face() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = tempdir().unwrap();
+    let (model, sha) = model_bytes();
+    let server = MockServer::start();
+
+    let leaked = server.mock(|when, then| {
+        when.method(GET).header_exists("authorization");
+        then.status(403);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/public.onnx");
+        then.status(200).body(model.clone());
+    });
+    let url = serve_manifest(
+        &server,
+        "public",
+        manifest("public", &server.url("/public.onnx"), &[], &sha),
+    );
+
+    std::env::set_var("HF_TOKEN", "hf_secret");
+    let result = ensure_model_in("", Some(&url), tmp.path());
+    std::env::remove_var("HF_TOKEN");
+    result.unwrap();
+    leaked.assert_hits(0);
+}
+
+#[test]
+fn file_urls_work_for_manifests_and_artifacts() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let share = tempdir().unwrap();
+    let cache = tempdir().unwrap();
+    let (model, sha) = model_bytes();
+
+    let model_path = share.path().join("shared model.onnx");
+    std::fs::write(&model_path, &model).unwrap();
+    let model_url = Url::from_file_path(&model_path).unwrap().to_string();
+    let manifest_path = share.path().join("manifest.json");
+    std::fs::write(&manifest_path, manifest("shared", &model_url, &[], &sha)).unwrap();
+    let manifest_url = Url::from_file_path(&manifest_path).unwrap().to_string();
+
+    let handle = ensure_model_in("", Some(&manifest_url), cache.path()).unwrap();
+    assert!(handle.local_path.starts_with(cache.path()));
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), model);
+
+    let missing = Url::from_file_path(share.path().join("nope.json")).unwrap().to_string();
+    assert!(ensure_model_in("", Some(&missing), cache.path()).is_err());
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let bad_header = NetConfig {
+        headers: vec![("bad header".into(), "x".into())],
+        auth_hosts: vec!["example.com".into()],
+        ..Default::default()
+    };
+    assert!(set_net_config(bad_header).is_err());
+    let bad_value = NetConfig {
+        headers: vec![("x-ok".into(), "line\nbreak".into())],
+        auth_hosts: vec!["example.com".into()],
+        ..Default::default()
+    };
+    assert!(set_net_config(bad_value).is_err());
+    let nowhere = NetConfig {
+        bearer_token: Some("secret".into()),
+        ..Default::default()
+    };
+    assert!(set_net_config(nowhere).is_err());
+}
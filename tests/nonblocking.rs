@@ -0,0 +1,228 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use std::path::Path;
+
+use httpmock::prelude::*;
+use sha2::{Digest, Sha256};
+use tempfile::tempdir;
+
+use common::write_input;
+use stem_splitter_core::{nonblocking, SplitOptions, SplitProgress, TestEngine};
+
+// A current-thread runtime: any blocking call on it would stall or panic
+#[tokio::test]
+async fn split_file_streams_progress_of_its_own_job() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 10_000);
+
+    let opts = SplitOptions {
+        output_dir: tmp.path().join("out").to_string_lossy().into_owned(),
+        test_engine: Some(TestEngine::new().with_window(4096, 2048)),
+        ..Default::default()
+    };
+    let mut task = nonblocking::spawn_split_file(input.to_str().unwrap(), opts);
+
+    let mut events = Vec::new();
+    while let Some(event) = task.progress().next().await {
+        events.push(event);
+    }
+    let result = task.await.unwrap();
+    assert!(Path::new(&result.vocals_path).exists());
+
+    let stages: Vec<&str> = events
+        .iter()
+        .filter_map(|e| match e {
+            SplitProgress::Stage(stage) => Some(*stage),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        stages,
+        [
+            "resolve_model",
+            "engine_preload",
+            "read_audio",
+            "infer",
+            "write_stems",
+            "finalize"
+        ]
+    );
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, SplitProgress::Writing { .. })));
+    assert!(matches!(events.last(), Some(SplitProgress::Finished)));
+}
+
+#[tokio::test]
+async fn failures_end_the_stream_and_resolve_the_task_with_an_error() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 10_000);
+
+    let engine = TestEngine::new()
+        .with_window(4096, 2048)
+        .with_failure_at(2, "device lost");
+    let opts = SplitOptions {
+        test_engine: Some(engine),
+        ..Default::default()
+    };
+    let (mut events, result) =
+        nonblocking::spawn_separate(input.to_str().unwrap(), opts).into_parts();
+
+    while let Some(event) = events.next().await {
+        assert!(!matches!(event, SplitProgress::Finished));
+    }
+    let Err(err) = result.await.unwrap() else {
+        panic!("expected the separation to fail");
+    };
+    assert!(err.to_string().contains("device lost"), "{err}");
+}
+
+#[tokio::test]
+async fn ensure_model_downloads_without_blocking_the_runtime() {
+    let tmp_cache = tempdir().unwrap();
+    std::env::set_var("XDG_CACHE_HOME", tmp_cache.path());
+
+    let model_bytes: Vec<u8> = (0..200_000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let sha = hex::encode(Sha256::digest(&model_bytes));
+
+    let server = MockServer::start_async().await;
+    let model_mock = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/async.onnx");
+            then.status(200).body(model_bytes.clone());
+        })
+        .await;
+    let manifest = format!(
+        r#"{{"name": "async_model", "sample_rate": 44100, "window": 343980, "hop": 171990,
+            "stems": ["vocals", "drums", "bass", "other"],
+            "artifacts": [{{"file": "async.onnx", "url": "{}", "sha256": "{sha}",
+                            "size_bytes": {}}}]}}"#,
+        server.url("/async.onnx"),
+        model_bytes.len()
+    );
+    server
+        .mock_async(|when, then| {
+            when.method(GET).path("/async.json");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .body(manifest);
+        })
+        .await;
+
+    let url = server.url("/async.json");
+    let handle = nonblocking::ensure_model("ignored", Some(&url))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), model_bytes);
+    assert!(!handle.local_path.with_extension("part").exists());
+
+    // Verified copy in the cache is reused
+    let again = nonblocking::ensure_model("ignored", Some(&url))
+        .await
+        .unwrap();
+    assert_eq!(again.local_path, handle.local_path);
+    model_mock.assert_hits_async(1).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_and_async_ensure_model_share_one_download() {
+    let tmp_cache = tempdir().unwrap();
+    let model_bytes: Vec<u8> = (0..300_000u32).map(|i| (i * 17 % 241) as u8).collect();
+    let sha = hex::encode(Sha256::digest(&model_bytes));
+
+    let server = MockServer::start_async().await;
+    let model_mock = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/shared.onnx");
+            then.status(200)
+                .delay(std::time::Duration::from_millis(200))
+                .body(model_bytes.clone());
+        })
+        .await;
+    let manifest = format!(
+        r#"{{"name": "shared_model", "sample_rate": 44100, "window": 343980, "hop": 171990,
+            "stems": ["vocals", "drums", "bass", "other"],
+            "artifacts": [{{"file": "shared.onnx", "url": "{}", "sha256": "{sha}",
+                            "size_bytes": {}}}]}}"#,
+        server.url("/shared.onnx"),
+        model_bytes.len()
+    );
+    server
+        .mock_async(|when, then| {
+            when.method(GET).path("/shared.json");
+            then.status(200).body(manifest);
+        })
+        .await;
+
+    let url = server.url("/shared.json");
+    let cache = tmp_cache.path().to_path_buf();
+    let blocking = {
+        let (url, cache) = (url.clone(), cache.clone());
+        std::thread::spawn(move || {
+            stem_splitter_core::ensure_model_in("ignored", Some(&url), &cache).unwrap()
+        })
+    };
+    let handle = nonblocking::ensure_model_in("ignored", Some(&url), &cache)
+        .await
+        .unwrap();
+    let other = blocking.join().unwrap();
+
+    assert_eq!(handle.local_path, other.local_path);
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), model_bytes);
+    model_mock.assert_hits_async(1).await;
+}
+
+#[tokio::test]
+async fn ensure_model_tries_mirrors_until_one_verifies() {
+    let tmp_cache = tempdir().unwrap();
+    let model_bytes: Vec<u8> = (0..8192u32).map(|i| (i * 13 % 239) as u8).collect();
+    let sha = hex::encode(Sha256::digest(&model_bytes));
+
+    let server = MockServer::start_async().await;
+    let down = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/down.onnx");
+            then.status(503);
+        })
+        .await;
+    let tampered = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/tampered.onnx");
+            then.status(200).body(vec![0u8; 8192]);
+        })
+        .await;
+    let good = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/good.onnx");
+            then.status(200).body(model_bytes.clone());
+        })
+        .await;
+    let manifest = format!(
+        r#"{{"name": "mirrored", "sample_rate": 44100, "window": 343980, "hop": 171990,
+            "stems": ["vocals", "drums", "bass", "other"],
+            "artifacts": [{{"file": "mirrored.onnx", "url": "{}", "mirrors": ["{}", "{}"],
+                            "sha256": "{sha}", "size_bytes": 8192}}]}}"#,
+        server.url("/down.onnx"),
+        server.url("/tampered.onnx"),
+        server.url("/good.onnx"),
+    );
+    server
+        .mock_async(|when, then| {
+            when.method(GET).path("/mirrored.json");
+            then.status(200).body(manifest);
+        })
+        .await;
+
+    let url = server.url("/mirrored.json");
+    let handle = nonblocking::ensure_model_in("ignored", Some(&url), tmp_cache.path())
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&handle.local_path).unwrap(), model_bytes);
+    down.assert_hits_async(1).await;
+    tampered.assert_hits_async(1).await;
+    good.assert_hits_async(1).await;
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::Write;
+use tempfile::tempdir;
+
+use stem_splitter_core::io::output::{
+    check_destinations, render_filename, unique_destinations, write_atomic,
+};
+use stem_splitter_core::OverwritePolicy;
+
+#[test]
+fn render_filename_expands_placeholders_and_subdirs() {
+    let p = render_filename("out", "{model}/{input}_{stem}.{ext}", "song", "bass", "m1", "wav");
+    assert_eq!(p.unwrap(), std::path::Path::new("out").join("m1").join("song_bass.wav"));
+
+    let dup = vec![
+        render_filename("out", "{input}.{ext}", "song", "vocals", "m", "wav").unwrap(),
+        render_filename("out", "{input}.{ext}", "song", "drums", "m", "wav").unwrap(),
+    ];
+    assert!(check_destinations(&dup, OverwritePolicy::Replace).is_err());
+}
+
+#[test]
+fn render_filename_stays_inside_output_dir() {
+    for template in ["../../x/{stem}.{ext}", "{stem}/../../{ext}", "a\\..\\..\\{stem}"] {
+        let err = render_filename("out", template, "song", "bass", "m", "wav").unwrap_err();
+        assert!(err.to_string().contains("leaves the output directory"), "{template}: {err}");
+    }
+    // Placeholder values are checked too
+    assert!(render_filename("out", "{input}_{stem}.{ext}", "..", "bass", "m", "wav").is_ok());
+    assert!(render_filename("out", "{input}/{stem}.{ext}", "..", "bass", "m", "wav").is_err());
+
+    // Absolute and `.` parts stay under the output directory
+    let p = render_filename("out", "/abs/./{stem}.{ext}", "song", "bass", "m", "wav").unwrap();
+    assert_eq!(p, std::path::Path::new("out").join("abs").join("bass.wav"));
+}
+
+#[test]
+fn write_atomic_respects_overwrite_policy() {
+    let tmp = tempdir().unwrap();
+    let dest = tmp.path().join("a.wav");
+    let write = |bytes: &'static [u8]| {
+        move |w: &mut std::io::BufWriter<&fs::File>| {
+            w.write_all(bytes)?;
+            Ok(())
+        }
+    };
+
+    write_atomic(&dest, OverwritePolicy::Error, write(b"one")).unwrap();
+    assert!(write_atomic(&dest, OverwritePolicy::Error, write(b"two")).is_err());
+
+    write_atomic(&dest, OverwritePolicy::Skip, write(b"two")).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), b"one");
+
+    let other = write_atomic(&dest, OverwritePolicy::UniqueSuffix, write(b"three")).unwrap();
+    assert_eq!(other, tmp.path().join("a_1.wav"));
+
+    write_atomic(&dest, OverwritePolicy::Replace, write(b"four")).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), b"four");
+
+    // Temp files are renamed away, never left behind.
+    assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn unique_destinations_share_one_suffix() {
+    let tmp = tempdir().unwrap();
+    let paths = vec![tmp.path().join("song_vocals.wav"), tmp.path().join("song_drums.wav")];
+    assert_eq!(unique_destinations(&paths).unwrap(), paths);
+
+    // Taking any file of a set moves the whole set to the next suffix
+    fs::write(&paths[1], b"").unwrap();
+    fs::write(tmp.path().join("song_vocals_1.wav"), b"").unwrap();
+    let unique = unique_destinations(&paths).unwrap();
+    assert_eq!(
+        unique,
+        vec![tmp.path().join("song_vocals_2.wav"), tmp.path().join("song_drums_2.wav")]
+    );
+}
@@ -0,0 +1,480 @@
+#![cfg(feature = "server")]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+use common::write_input;
+use stem_splitter_core::core::audio::read_audio;
+use stem_splitter_core::server::{Server, ServerConfig};
+use stem_splitter_core::{SplitOptions, TestEngine};
+
+async fn start(data_dir: &Path, engine: TestEngine, queue_capacity: usize) -> String {
+    let config = ServerConfig {
+        addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        data_dir: data_dir.to_path_buf(),
+        queue_capacity,
+        workers: 1,
+        options: SplitOptions {
+            test_engine: Some(engine),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let server = Server::bind(config).await.unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    url
+}
+
+async fn create_job(client: &reqwest::Client, url: &str, body: Value) -> reqwest::Response {
+    client
+        .post(format!("{url}/jobs"))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn wait_for(client: &reqwest::Client, url: &str, id: &str, status: &str) -> Value {
+    for _ in 0..500 {
+        let job: Value = client
+            .get(format!("{url}/jobs/{id}"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == status {
+            return job;
+        }
+        assert!(
+            !matches!(
+                job["status"].as_str(),
+                Some("failed" | "cancelled" | "done")
+            ),
+            "job ended as {job} while waiting for {status}"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("job {id} never became {status}");
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u64 {
+    u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap()).into()
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u64 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()).into()
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+/// `(name, size, offset)` of each entry in the central directory of a
+/// ZIP archive whose last bytes are `tail`, starting at offset `base`.
+fn zip_entries(tail: &[u8], base: u64) -> Vec<(String, u64, u64)> {
+    let end = tail.len() - 22;
+    assert_eq!(u32_at(tail, end), 0x0605_4b50);
+    let (mut count, mut dir) = (u16_at(tail, end + 10), u32_at(tail, end + 16));
+    let mut dir_end = end;
+    if dir == 0xffff_ffff {
+        let locator = end - 20;
+        assert_eq!(u32_at(tail, locator), 0x0706_4b50);
+        let record = (u64_at(tail, locator + 8) - base) as usize;
+        assert_eq!(u32_at(tail, record), 0x0606_4b50);
+        assert_eq!(record, locator - 56);
+        dir_end = record;
+        (count, dir) = (u64_at(tail, record + 32), u64_at(tail, record + 48));
+    }
+
+    let mut at = (dir - base) as usize;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(tail, at), 0x0201_4b50);
+        let (name_len, extra_len) = (u16_at(tail, at + 28) as usize, u16_at(tail, at + 30));
+        let name = String::from_utf8(tail[at + 46..at + 46 + name_len].to_vec()).unwrap();
+        let (mut size, mut offset) = (u32_at(tail, at + 24), u32_at(tail, at + 42));
+        let mut extra = at + 46 + name_len + 4;
+        if size == 0xffff_ffff {
+            size = u64_at(tail, extra);
+            assert_eq!(u64_at(tail, extra + 8), size);
+            extra += 16;
+        }
+        if offset == 0xffff_ffff {
+            offset = u64_at(tail, extra);
+        }
+        entries.push((name, size, offset));
+        at += 46 + name_len + extra_len as usize;
+    }
+    assert_eq!(at, dir_end);
+    entries
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_from_an_upload_runs_and_serves_its_stems() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 10_000);
+    let url = start(
+        &tmp.path().join("data"),
+        TestEngine::new().with_window(4096, 2048),
+        4,
+    )
+    .await;
+    let client = reqwest::Client::new();
+
+    let upload: Value = client
+        .post(format!("{url}/uploads?name=../song.wav"))
+        .body(std::fs::read(&input).unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(upload["name"], "song.wav");
+
+    let response = create_job(
+        &client,
+        &url,
+        json!({ "upload": upload["upload"], "options": { "chunk_seconds": null } }),
+    )
+    .await;
+    assert_eq!(response.status(), 202);
+    let job: Value = response.json().await.unwrap();
+    let id = job["id"].as_str().unwrap().to_string();
+
+    let done = wait_for(&client, &url, &id, "done").await;
+    assert_eq!(done["progress"], "Finished");
+    assert_eq!(done["stems"], json!(["vocals", "drums", "bass", "other"]));
+
+    let wav = client
+        .get(format!("{url}/jobs/{id}/stems/drums"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wav.status(), 200);
+    assert_eq!(wav.headers()["content-type"], "audio/wav");
+    let saved = tmp.path().join("drums.wav");
+    std::fs::write(&saved, wav.bytes().await.unwrap()).unwrap();
+    let (original, drums) = (
+        read_audio(input.to_str().unwrap()).unwrap(),
+        read_audio(saved.to_str().unwrap()).unwrap(),
+    );
+    assert_eq!(drums.samples.len(), original.samples.len());
+    for (d, o) in drums.samples.iter().zip(&original.samples) {
+        assert!((d - 0.25 * o).abs() < 1e-3);
+    }
+
+    let job_dir = tmp.path().join("data").join("jobs").join(&id);
+    let zip = client
+        .get(format!("{url}/jobs/{id}/zip"))
+        .send()
+        .await
+        .unwrap();
+    let length = zip.content_length();
+    let zip = zip.bytes().await.unwrap();
+    assert_eq!(length, Some(zip.len() as u64));
+    let entries = zip_entries(&zip, 0);
+    assert_eq!(entries.len(), 4);
+    for ((name, size, offset), stem) in entries
+        .into_iter()
+        .zip(["vocals", "drums", "bass", "other"])
+    {
+        assert_eq!(name, format!("{stem}.wav"));
+        let offset = offset as usize;
+        assert_eq!(&zip[offset..offset + 4], b"PK\x03\x04");
+        let data = offset + 30 + name.len() + u16_at(&zip, offset + 28) as usize;
+        let stored = std::fs::read(job_dir.join(format!("song_{stem}.wav"))).unwrap();
+        assert_eq!(size, stored.len() as u64);
+        assert_eq!(&zip[data..data + stored.len()], &stored[..]);
+    }
+
+    let missing = client
+        .get(format!("{url}/jobs/{id}/stems/piano"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+
+    // Deleting a finished job removes it and its stems
+    assert!(job_dir.join("song_drums.wav").exists());
+    let deleted = client
+        .delete(format!("{url}/jobs/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 200);
+    assert!(!job_dir.exists());
+    let gone = client.get(format!("{url}/jobs/{id}")).send().await.unwrap();
+    assert_eq!(gone.status(), 404);
+
+    let upload_id = upload["upload"].as_str().unwrap();
+    let upload_dir = tmp.path().join("data").join("uploads").join(upload_id);
+    assert!(upload_dir.exists());
+    let deleted = client
+        .delete(format!("{url}/uploads/{upload_id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 200);
+    assert!(!upload_dir.exists());
+    let again = client
+        .delete(format!("{url}/uploads/{upload_id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(again.status(), 404);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jobs_can_be_cancelled_and_the_queue_is_bounded() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 40_000);
+    let engine = TestEngine::new()
+        .with_window(4096, 2048)
+        .with_latency(Duration::from_millis(100));
+    let url = start(&tmp.path().join("data"), engine, 1).await;
+    let client = reqwest::Client::new();
+    let body = json!({ "input": input });
+
+    let running: Value = create_job(&client, &url, body.clone())
+        .await
+        .json()
+        .await
+        .unwrap();
+    let running = running["id"].as_str().unwrap().to_string();
+    wait_for(&client, &url, &running, "running").await;
+
+    let queued: Value = create_job(&client, &url, body.clone())
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(queued["status"], "queued");
+    let full = create_job(&client, &url, body.clone()).await;
+    assert_eq!(full.status(), 503);
+
+    // A queued job is dropped without running
+    let queued = queued["id"].as_str().unwrap();
+    let cancelled: Value = client
+        .delete(format!("{url}/jobs/{queued}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(cancelled["status"], "cancelled");
+
+    client
+        .delete(format!("{url}/jobs/{running}"))
+        .send()
+        .await
+        .unwrap();
+    for _ in 0..500 {
+        let job: Value = client
+            .get(format!("{url}/jobs/{running}"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "cancelled" {
+            let stems = client
+                .get(format!("{url}/jobs/{running}/zip"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(stems.status(), 409);
+            let jobs: Value = client
+                .get(format!("{url}/jobs"))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(jobs.as_array().unwrap().len(), 2);
+            return;
+        }
+        assert_eq!(job["status"], "running");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("running job was never cancelled");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests_are_rejected() {
+    let tmp = tempdir().unwrap();
+    let url = start(&tmp.path().join("data"), TestEngine::new(), 1).await;
+    let client = reqwest::Client::new();
+
+    let missing = create_job(
+        &client,
+        &url,
+        json!({ "input": tmp.path().join("nope.wav") }),
+    )
+    .await;
+    assert_eq!(missing.status(), 400);
+    let both = create_job(&client, &url, json!({ "input": "a.wav", "upload": "b" })).await;
+    assert_eq!(both.status(), 400);
+    let upload = create_job(&client, &url, json!({ "upload": "../../etc" })).await;
+    assert_eq!(upload.status(), 404);
+    let options = create_job(
+        &client,
+        &url,
+        json!({ "input": "a.wav", "options": { "chunk_seconds": "soon" } }),
+    )
+    .await;
+    assert_eq!(options.status(), 400);
+    let body: Value = options.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("invalid job"));
+
+    let unknown = client.get(format!("{url}/jobs/0123")).send().await.unwrap();
+    assert_eq!(unknown.status(), 404);
+    let no_name = client
+        .post(format!("{url}/uploads"))
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(no_name.status(), 400);
+
+    // Names are percent-decoded before they're reduced to a file name
+    for (query, name) in [
+        ("..%2F..%2Fevil.wav", "evil.wav"),
+        ("my%20song.wav", "my song.wav"),
+    ] {
+        let upload: Value = client
+            .post(format!("{url}/uploads?name={query}"))
+            .body("x")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(upload["name"], name);
+        let id = upload["upload"].as_str().unwrap();
+        let stored = tmp.path().join("data").join("uploads").join(id).join(name);
+        assert!(stored.exists());
+    }
+    assert!(!tmp.path().join("evil.wav").exists());
+}
+
+/// Writes and streams a ~4.3 GB archive (the stem is extended sparsely).
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "streams more than 4 GiB"]
+async fn zips_past_four_gib_use_zip64_records() {
+    const BIG: u64 = 4_300_000_000;
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 10_000);
+    let url = start(
+        &tmp.path().join("data"),
+        TestEngine::new().with_window(4096, 2048),
+        4,
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let job: Value = create_job(&client, &url, json!({ "input": input }))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = job["id"].as_str().unwrap();
+    wait_for(&client, &url, id, "done").await;
+    let job_dir = tmp.path().join("data").join("jobs").join(id);
+    std::fs::File::options()
+        .write(true)
+        .open(job_dir.join("in_vocals.wav"))
+        .unwrap()
+        .set_len(BIG)
+        .unwrap();
+
+    let mut zip = client
+        .get(format!("{url}/jobs/{id}/zip"))
+        .send()
+        .await
+        .unwrap();
+    let length = zip.content_length().unwrap();
+    let (mut total, mut tail) = (0u64, Vec::new());
+    while let Some(chunk) = zip.chunk().await.unwrap() {
+        total += chunk.len() as u64;
+        tail.extend_from_slice(&chunk);
+        if tail.len() > 1 << 20 {
+            tail.drain(..tail.len() - (1 << 20));
+        }
+    }
+    assert_eq!(total, length);
+
+    let entries = zip_entries(&tail, total - tail.len() as u64);
+    assert_eq!(entries[0], ("vocals.wav".to_string(), BIG, 0));
+    let mut expected = 0;
+    for (name, size, offset) in &entries {
+        assert_eq!(*offset, expected);
+        let extra = if *size >= u32::MAX.into() { 20 } else { 0 };
+        expected += 30 + name.len() as u64 + extra + size;
+    }
+    assert!(entries[1].2 > u32::MAX.into());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_options_stay_inside_the_job_directory() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, 10_000);
+    let data = tmp.path().join("data");
+    let url = start(&data, TestEngine::new().with_window(4096, 2048), 4).await;
+    let client = reqwest::Client::new();
+
+    let escaping = create_job(
+        &client,
+        &url,
+        json!({ "input": input, "options": { "filename_template": "../../escaped_{stem}" } }),
+    )
+    .await;
+    assert_eq!(escaping.status(), 202);
+    let escaping: Value = escaping.json().await.unwrap();
+    let failed = wait_for(&client, &url, escaping["id"].as_str().unwrap(), "failed").await;
+    assert!(
+        failed["error"]
+            .as_str()
+            .unwrap()
+            .contains("leaves the output directory"),
+        "{failed}"
+    );
+    assert!(!data.join("escaped_vocals.wav").exists());
+    assert!(!tmp.path().join("escaped_vocals.wav").exists());
+
+    // Storage options are the server's, not the job's
+    let (checkpoints, cache) = (tmp.path().join("checkpoints"), tmp.path().join("cache"));
+    let job: Value = create_job(
+        &client,
+        &url,
+        json!({ "input": input, "options": {
+            "chunk_seconds": 1,
+            "checkpoint_dir": checkpoints,
+            "cache": { "max_bytes": 1_000_000, "dir": cache },
+            "overwrite": "error"
+        } }),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    wait_for(&client, &url, job["id"].as_str().unwrap(), "done").await;
+    assert!(!checkpoints.exists());
+    assert!(!cache.exists());
+}
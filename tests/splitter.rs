@@ -7,9 +7,9 @@ use std::fs;
 use tempfile::tempdir;
 
 use hound;
-use stem_splitter_core::core::audio::write_audio;
+use stem_splitter_core::core::audio::{read_audio, write_audio};
 use stem_splitter_core::core::splitter::split_file;
-use stem_splitter_core::{AudioData, SplitOptions};
+use stem_splitter_core::{AudioData, Separator, SplitOptions, Stem, TimePosition};
 
 // Compute hex sha256 for arbitrary bytes
 fn sha256_hex(bytes: &[u8]) -> String {
@@ -44,14 +44,12 @@ fn manifest_json(model_url: &str, sha_hex: &str) -> String {
     )
 }
 
-#[test]
-fn split_file_produces_four_stems() {
-    let tmp = tempdir().unwrap();
-    std::env::set_var("XDG_CACHE_HOME", tmp.path());
+/// Write a stereo test tone and serve a mock model manifest.
+/// Returns (input wav path, manifest url, server guard).
+fn setup(tmp: &std::path::Path) -> (std::path::PathBuf, String, MockServer) {
+    std::env::set_var("XDG_CACHE_HOME", tmp);
 
-    let in_wav = tmp.path().join("in.wav");
-    let out_dir = tmp.path().join("out");
-    fs::create_dir_all(&out_dir).unwrap();
+    let in_wav = tmp.join("in.wav");
 
     let sr = 44_100u32;
     let frames = 8000usize;
@@ -72,14 +70,14 @@ fn split_file_produces_four_stems() {
     let model_body = b"this is the mock onnx payload";
     let model_sha = sha256_hex(model_body);
 
-    let _model = server.mock(|when, then| {
+    server.mock(|when, then| {
         when.method(GET).path("/mock.onnx");
         then.status(200)
             .header("Content-Length", model_body.len().to_string().as_str())
             .body(model_body.as_slice());
     });
 
-    let _manifest = server.mock(|when, then| {
+    server.mock(|when, then| {
         when.method(GET).path("/m.json");
         then.status(200)
             .header("Content-Type", "application/json")
@@ -90,11 +88,23 @@ fn split_file_produces_four_stems() {
     });
 
     let manifest_url = format!("{}/m.json", server.base_url());
+    (in_wav, manifest_url, server)
+}
+
+#[test]
+fn split_file_produces_four_stems() {
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+
+    let out_dir = tmp.path().join("out");
+    fs::create_dir_all(&out_dir).unwrap();
+    let sr = 44_100u32;
 
     let opts = SplitOptions {
         model_name: "ignored".into(),
         manifest_url_override: Some(manifest_url),
         output_dir: out_dir.to_string_lossy().into(),
+        ..Default::default()
     };
 
     let res = split_file(in_wav.to_str().unwrap(), opts).expect("split_file failed");
@@ -112,3 +122,255 @@ fn split_file_produces_four_stems() {
         assert!(r.into_samples::<i16>().count() > 0);
     }
 }
+
+#[test]
+fn separate_range_is_trimmed_to_requested_span() {
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+
+    let opts = SplitOptions {
+        manifest_url_override: Some(manifest_url),
+        start: Some(TimePosition::Samples(3000)),
+        end: Some(TimePosition::Seconds(0.1)),
+        ..Default::default()
+    };
+
+    let stems = Separator::separate(in_wav.to_str().unwrap(), opts).expect("separate failed");
+    assert_eq!(stems.start_sample, 3000);
+    assert_eq!(stems.num_samples, 4410 - 3000);
+
+    // The mock engine returns the input for every stem.
+    let input = read_audio(&in_wav).unwrap();
+    let vocals = stems.get(Stem::Vocals);
+    assert_eq!(vocals.len(), (4410 - 3000) * 2);
+    assert_eq!(&vocals[..], &input.samples[3000 * 2..4410 * 2]);
+}
+
+#[test]
+fn multitrack_and_reaper_exports() {
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+
+    let opts = SplitOptions {
+        manifest_url_override: Some(manifest_url),
+        ..Default::default()
+    };
+    let stems = Separator::separate(in_wav.to_str().unwrap(), opts).expect("separate failed");
+
+    let multi = tmp.path().join("out/multi.wav");
+    stems.save_multitrack(multi.to_str().unwrap()).unwrap();
+    let mut r = hound::WavReader::open(&multi).unwrap();
+    assert_eq!(r.spec().channels, 8);
+    let samples: Vec<i16> = r.samples::<i16>().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), stems.num_samples * 8);
+    // Channel pairs follow Stem::all(); the mock engine copies the input into each stem.
+    let frame = &samples[8 * 100..8 * 101];
+    for k in 1..4 {
+        assert_eq!(frame[2 * k], frame[0]);
+        assert_eq!(frame[2 * k + 1], frame[1]);
+    }
+    // WAVE_FORMAT_EXTENSIBLE with no speaker positions: the pairs are stems
+    let bytes = fs::read(&multi).unwrap();
+    let fmt = bytes.windows(4).position(|w| w == b"fmt ").unwrap() + 8;
+    assert_eq!(u16::from_le_bytes([bytes[fmt], bytes[fmt + 1]]), 0xFFFE);
+    let mask = u32::from_le_bytes(bytes[fmt + 20..fmt + 24].try_into().unwrap());
+    assert_eq!(mask, 0);
+
+    let export_dir = tmp.path().join("out/reaper");
+    let export = stems
+        .export_reaper_project(export_dir.to_str().unwrap(), "song")
+        .unwrap();
+    assert_eq!(export.stem_paths.len(), 4);
+    for (_, p) in &export.stem_paths {
+        assert!(std::path::Path::new(p).exists(), "missing {p}");
+    }
+    let rpp = fs::read_to_string(&export.project_path).unwrap();
+    assert!(export.project_path.ends_with("song.RPP"));
+    assert!(rpp.contains("FILE \"song_vocals.wav\""));
+    assert!(rpp.contains("NAME \"Other\""));
+}
+
+#[test]
+fn filename_template_and_overwrite_policy() {
+    use stem_splitter_core::OverwritePolicy;
+
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+    let out_dir = tmp.path().join("out");
+
+    let opts = |overwrite| SplitOptions {
+        manifest_url_override: Some(manifest_url.clone()),
+        output_dir: out_dir.to_string_lossy().into(),
+        filename_template: "{input}/{stem}-{model}.{ext}".into(),
+        overwrite,
+        ..Default::default()
+    };
+
+    let first = split_file(in_wav.to_str().unwrap(), opts(OverwritePolicy::Error)).unwrap();
+    assert_eq!(
+        std::path::Path::new(&first.vocals_path),
+        out_dir.join("in").join("vocals-htdemucs_ort_v1.wav")
+    );
+
+    // Only the four stems, no leftover temp files.
+    assert_eq!(fs::read_dir(out_dir.join("in")).unwrap().count(), 4);
+
+    let err = split_file(in_wav.to_str().unwrap(), opts(OverwritePolicy::Error));
+    assert!(err.unwrap_err().to_string().contains("already exists"));
+
+    let skipped = split_file(in_wav.to_str().unwrap(), opts(OverwritePolicy::Skip)).unwrap();
+    assert_eq!(skipped.vocals_path, first.vocals_path);
+
+    let unique =
+        split_file(in_wav.to_str().unwrap(), opts(OverwritePolicy::UniqueSuffix)).unwrap();
+    assert!(unique.drums_path.ends_with("drums-htdemucs_ort_v1_1.wav"));
+    assert!(std::path::Path::new(&unique.drums_path).exists());
+    // One suffix for all stems, even if only some names are taken
+    fs::write(out_dir.join("in/vocals-htdemucs_ort_v1_2.wav"), b"").unwrap();
+    let unique =
+        split_file(in_wav.to_str().unwrap(), opts(OverwritePolicy::UniqueSuffix)).unwrap();
+    assert!(unique.vocals_path.ends_with("vocals-htdemucs_ort_v1_3.wav"));
+    assert!(unique.drums_path.ends_with("drums-htdemucs_ort_v1_3.wav"));
+
+    // remove_vocals skips the separation too once both tracks exist
+    let karaoke_opts = |overwrite| SplitOptions {
+        output_dir: out_dir.join("karaoke").to_string_lossy().into(),
+        ..opts(overwrite)
+    };
+    let input = in_wav.to_str().unwrap();
+    let karaoke =
+        stem_splitter_core::remove_vocals(input, karaoke_opts(OverwritePolicy::Error)).unwrap();
+    let written = fs::metadata(&karaoke.instrumental_path).unwrap().modified().unwrap();
+    let skipped =
+        stem_splitter_core::remove_vocals(input, karaoke_opts(OverwritePolicy::Skip)).unwrap();
+    assert_eq!(skipped.instrumental_path, karaoke.instrumental_path);
+    assert_eq!(skipped.vocals_path, karaoke.vocals_path);
+    assert_eq!(
+        fs::metadata(&skipped.instrumental_path).unwrap().modified().unwrap(),
+        written
+    );
+}
+
+#[test]
+fn result_cache_reuses_and_evicts_entries() {
+    use stem_splitter_core::{CacheOptions, ResultCache};
+
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+    let cache_opts = CacheOptions {
+        dir: Some(tmp.path().join("results").to_string_lossy().into()),
+        ..Default::default()
+    };
+    let opts = |start: u64, cache_opts: &CacheOptions| SplitOptions {
+        manifest_url_override: Some(manifest_url.clone()),
+        start: Some(TimePosition::Samples(start)),
+        cache: Some(cache_opts.clone()),
+        ..Default::default()
+    };
+
+    let input = in_wav.to_str().unwrap();
+    let first = Separator::separate(input, opts(0, &cache_opts)).unwrap();
+    let cache = ResultCache::open(&cache_opts).unwrap();
+    let entry_size = cache.size_bytes().unwrap();
+    assert!(entry_size > 0);
+
+    let again = Separator::separate(input, opts(0, &cache_opts)).unwrap();
+    assert_eq!(again.get(Stem::Bass), first.get(Stem::Bass));
+    assert_eq!(cache.size_bytes().unwrap(), entry_size);
+
+    // Room for one entry only: a different range evicts the older one.
+    let small = CacheOptions {
+        max_bytes: entry_size + entry_size / 2,
+        ..cache_opts.clone()
+    };
+    let ranged = Separator::separate(input, opts(1000, &small)).unwrap();
+    assert_eq!(ranged.start_sample, 1000);
+    assert!(cache.size_bytes().unwrap() <= small.max_bytes);
+    assert_eq!(fs::read_dir(cache.dir()).unwrap().count(), 1);
+}
+
+#[test]
+fn batched_inference_matches_single_window_runs() {
+    use stem_splitter_core::EngineConfig;
+
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+    let opts = |batch_size| SplitOptions {
+        manifest_url_override: Some(manifest_url.clone()),
+        engine: EngineConfig {
+            batch_size,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let input = in_wav.to_str().unwrap();
+    let single = Separator::separate(input, opts(1)).unwrap();
+    let batched = Separator::separate(input, opts(3)).unwrap();
+    for &stem in Stem::all() {
+        assert_eq!(batched.get(stem), single.get(stem));
+    }
+}
+
+#[test]
+fn pipeline_reports_stage_timing() {
+    use std::sync::{Arc, Mutex};
+    use stem_splitter_core::{set_split_progress_callback, PipelineTiming, SplitProgress};
+
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+
+    let seen: Arc<Mutex<Vec<PipelineTiming>>> = Arc::default();
+    let sink = seen.clone();
+    set_split_progress_callback(move |p| {
+        if let SplitProgress::Timing(t) = p {
+            sink.lock().unwrap().push(t);
+        }
+    });
+
+    let opts = SplitOptions {
+        manifest_url_override: Some(manifest_url),
+        ..Default::default()
+    };
+    Separator::separate(in_wav.to_str().unwrap(), opts).unwrap();
+
+    // Other tests in this binary may report too; ours processed 4 windows.
+    let seen = seen.lock().unwrap();
+    let t = seen.iter().find(|t| t.windows == 4).expect("no timing reported");
+    assert!(t.wall_secs > 0.0);
+    assert!(t.serial_secs() >= t.prepare_secs);
+    assert!(t.speedup() > 0.0);
+}
+
+#[test]
+fn concurrent_split_file_calls_share_the_session_pool() {
+    use stem_splitter_core::EngineConfig;
+
+    let tmp = tempdir().unwrap();
+    let (in_wav, manifest_url, _server) = setup(tmp.path());
+
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|job| {
+                let opts = SplitOptions {
+                    manifest_url_override: Some(manifest_url.clone()),
+                    output_dir: tmp.path().join(format!("job{job}")).to_string_lossy().into(),
+                    engine: EngineConfig {
+                        sessions: 2,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let input = in_wav.clone();
+                scope.spawn(move || split_file(input.to_str().unwrap(), opts))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let first = fs::read(&results[0].as_ref().unwrap().vocals_path).unwrap();
+    for res in &results {
+        let res = res.as_ref().expect("concurrent split_file failed");
+        assert_eq!(fs::read(&res.vocals_path).unwrap(), first);
+    }
+}
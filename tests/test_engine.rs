@@ -0,0 +1,178 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+use common::write_input;
+use stem_splitter_core::core::audio::read_audio;
+use stem_splitter_core::{
+    split_file, EngineConfig, Separator, SplitOptions, Stem, StemScript, TestEngine,
+};
+
+const FRAMES: usize = 10_000;
+
+fn options(engine: &TestEngine) -> SplitOptions {
+    SplitOptions {
+        test_engine: Some(engine.clone()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn scripted_stems_and_recorded_windows() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    let samples = write_input(&input, FRAMES);
+
+    let engine = TestEngine::new()
+        .with_window(4096, 2048)
+        .with_stem("vocals", StemScript::Gain(0.5))
+        .with_stem("drums", StemScript::Silence);
+    let stems = Separator::separate(input.to_str().unwrap(), options(&engine)).unwrap();
+
+    let vocals = stems.get(Stem::Vocals);
+    assert_eq!(vocals.len(), samples.len());
+    for (v, x) in vocals.iter().zip(&samples) {
+        assert!((v - 0.5 * x).abs() < 1e-3);
+    }
+    assert!(stems.get(Stem::Drums).iter().all(|&x| x == 0.0));
+    // Unscripted stems default to a quarter of the mix
+    for (b, x) in stems.get(Stem::Bass).iter().zip(&samples) {
+        assert!((b - 0.25 * x).abs() < 1e-3);
+    }
+
+    let requests = engine.requests();
+    let starts: Vec<usize> = requests.iter().map(|r| r.start).collect();
+    assert_eq!(starts, vec![0, 2048, 4096, 6144, 8192]);
+    assert!(requests.iter().enumerate().all(|(i, r)| r.index == i && r.len == 4096));
+}
+
+#[test]
+fn custom_scripts_see_the_window_request() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+
+    let engine = TestEngine::new().with_window(4096, 2048).with_stem(
+        "other",
+        StemScript::Custom(Arc::new(|request, left, _right| {
+            let marker = vec![request.index as f32; left.len()];
+            (marker.clone(), marker)
+        })),
+    );
+    let stems = Separator::separate(input.to_str().unwrap(), options(&engine)).unwrap();
+
+    // Each hop of the output comes from the window that starts there
+    let other = stems.get(Stem::Other);
+    for (frame, pair) in other.chunks_exact(2).enumerate() {
+        assert_eq!(pair[0], (frame / 2048) as f32, "frame {frame}");
+    }
+}
+
+#[test]
+fn injected_failures_abort_the_separation() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+
+    for (sessions, batch_size) in [(1, 1), (2, 1), (2, 2)] {
+        let engine = TestEngine::new()
+            .with_window(4096, 2048)
+            .with_latency(Duration::from_millis(5))
+            .with_failure_at(1, "device lost");
+        let opts = SplitOptions {
+            output_dir: tmp.path().join("out").to_string_lossy().into_owned(),
+            engine: EngineConfig {
+                sessions,
+                batch_size,
+                ..Default::default()
+            },
+            ..options(&engine)
+        };
+
+        let err = split_file(input.to_str().unwrap(), opts).unwrap_err();
+        assert!(err.to_string().contains("device lost"), "{err}");
+        assert!(!tmp.path().join("out").join("in_vocals.wav").exists());
+    }
+}
+
+#[test]
+fn rejected_batches_fall_back_to_single_windows() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    let batched = |engine: &TestEngine| SplitOptions {
+        engine: EngineConfig {
+            batch_size: 3,
+            ..Default::default()
+        },
+        ..options(engine)
+    };
+
+    let engine = || {
+        TestEngine::new()
+            .with_window(4096, 2048)
+            .with_stem("vocals", StemScript::Gain(0.5))
+    };
+    let expected = Separator::separate(input.to_str().unwrap(), options(&engine())).unwrap();
+
+    let fixed = engine().with_max_batch(1);
+    let stems = Separator::separate(input.to_str().unwrap(), batched(&fixed)).unwrap();
+    for stem in [Stem::Vocals, Stem::Drums, Stem::Bass, Stem::Other] {
+        assert_eq!(stems.get(stem), expected.get(stem));
+    }
+    assert!(fixed.requests().iter().any(|r| r.batch == 3));
+
+    // Other failures still abort a batched run
+    let failing = engine()
+        .with_max_batch(1)
+        .with_failure_at(4, "device lost");
+    let err = Separator::separate(input.to_str().unwrap(), batched(&failing)).err().unwrap();
+    assert!(err.to_string().contains("device lost"), "{err}");
+}
+
+#[test]
+fn latency_is_injected_per_window() {
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+
+    let engine = TestEngine::new()
+        .with_window(4096, 2048)
+        .with_latency_at(3, Duration::from_millis(150));
+    let started = Instant::now();
+    Separator::separate(input.to_str().unwrap(), options(&engine)).unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(150));
+    assert_eq!(engine.requests().len(), 5);
+}
+
+#[test]
+fn separate_audio_matches_separating_the_file() {
+    use stem_splitter_core::TimePosition;
+
+    let tmp = tempdir().unwrap();
+    let input = tmp.path().join("in.wav");
+    write_input(&input, FRAMES);
+    // What the file decodes to, so both inputs are the same samples
+    let audio = read_audio(&input).unwrap();
+
+    let engine = TestEngine::new()
+        .with_window(4096, 2048)
+        .with_stem("vocals", StemScript::LowPass { cutoff_hz: 500.0 });
+    let from_file = Separator::separate(input.to_str().unwrap(), options(&engine)).unwrap();
+    let from_memory = Separator::separate_audio(&audio, options(&engine)).unwrap();
+    assert_eq!(from_memory.get(Stem::Vocals), from_file.get(Stem::Vocals));
+
+    // Ranges select part of the buffer, as for files
+    let ranged = SplitOptions {
+        start: Some(TimePosition::Samples(1000)),
+        end: Some(TimePosition::Samples(3000)),
+        ..options(&engine)
+    };
+    let part = Separator::separate_audio(&audio, ranged.clone()).unwrap();
+    assert_eq!(part.start_sample, 1000);
+    assert_eq!(part.num_samples, 2000);
+    let file_part = Separator::separate(input.to_str().unwrap(), ranged).unwrap();
+    assert_eq!(part.get(Stem::Vocals), file_part.get(Stem::Vocals));
+}